@@ -1,15 +1,29 @@
-use proton::raytrace::objects::{Light, Mesh, Sphere};
-use proton::raytrace::{Renderer, Scene, SceneGenerator};
+use proton::raytrace::objects::{Mesh, Sphere};
+use proton::raytrace::{integrators, Renderer, Scene, SceneGenerator};
 use proton::raytrace::objects::{Bounded, LightInteractable, LightSample, PartialBounded, RayTraceable};
 use proton::vector::Vector3D;
 
 use std::sync::Arc;
-use proton::raytrace::materials::{Diffuse, Refract};
+use proton::raytrace::materials::{Diffuse, Emissive, Interface, Material, Refract};
+use proton::raytrace::media::{Grid, Homogeneous, Medium};
+use proton::raytrace::environment::{Daylight, Environment};
+use proton::raytrace::textures::Constant;
 
 type RF = f64;
 type Vector3f = Vector3D<RF>;
 
-struct PracticalSceneGenerator {}
+struct PracticalSceneGenerator {
+    // Light the box with a glowing ball instead of the ceiling light
+    sphere_sun: bool,
+    // Open the box to a daylight sky shining in through the front
+    sky: bool,
+    // Trace a single wavelength per path so the glass splits light into colors
+    spectral: bool,
+    // Thin haze filling the whole scene
+    fog: bool,
+    // Swap the bigger glass ball for a ball of smoke
+    smoke: bool,
+}
 
 impl SceneGenerator<RF> for PracticalSceneGenerator {
     fn gen_scene(&self) -> Scene<RF> {
@@ -18,74 +32,81 @@ impl SceneGenerator<RF> for PracticalSceneGenerator {
             Box::new(Diffuse::new(
                 Vector3f::new(0.725, 0.71, 0.68),
             )),
-        );
+        ).expect("Something went wrong reading the mesh");
 
         let short_box = Mesh::new(
             "cornellbox/shortbox.obj".to_string(),
             Box::new(Diffuse::new(
                 Vector3f::new(0.725, 0.71, 0.68),
             )),
-        );
+        ).expect("Something went wrong reading the mesh");
         let tall_box = Mesh::new(
             "cornellbox/tallbox.obj".to_string(),
             Box::new(Diffuse::new(
                 Vector3f::new(0.725, 0.71, 0.68),
             )),
-        );
+        ).expect("Something went wrong reading the mesh");
 
         let left_wall = Mesh::new(
             "cornellbox/left.obj".to_string(),
             Box::new(Diffuse::new(
                 Vector3f::new(0.63, 0.065, 0.05),
             )),
-        );
+        ).expect("Something went wrong reading the mesh");
         let right_wall = Mesh::new(
             "cornellbox/right.obj".to_string(),
             Box::new(Diffuse::new(
                 Vector3f::new(0.14, 0.45, 0.091),
             )),
-        );
+        ).expect("Something went wrong reading the mesh");
 
         let the_ball = Sphere::new(
             Vector3f::new(200.0, 240.0, 200.0),
             60.0,
-            Box::new(Refract::new(1.2)),
+            glass(),
         );
         let the_smaller_ball = Sphere::new(
             Vector3f::new(120.0, 190.0, 200.0),
             20.0,
-            Box::new(Refract::new(1.2)),
-        );
-        let the_bigger_ball = Sphere::new(
-            Vector3f::new(400.0, 100.0, 100.0),
-            80.0,
-            Box::new(Refract::new(1.2)),
+            glass(),
         );
+        let the_bigger_ball = if self.smoke {
+            Sphere::new(
+                Vector3f::new(400.0, 100.0, 100.0),
+                80.0,
+                Box::new(Interface::new()),
+            ).with_medium(Arc::new(smoke(Vector3f::new(400.0, 100.0, 100.0), 80.0)))
+        } else {
+            Sphere::new(
+                Vector3f::new(400.0, 100.0, 100.0),
+                80.0,
+                glass(),
+            )
+        };
 
-        let the_sun = Light::new(
-            Box::new(Mesh::new(
-                "cornellbox/light.obj".to_string(),
-                Box::new(Diffuse::new(
+        let the_sum_diff = Vector3f::new(0.747 + 0.058, 0.747 + 0.258, 0.747) * 8.0
+            + Vector3f::new(0.740 + 0.287, 0.740 + 0.160, 0.740) * 15.6
+            + Vector3f::new(0.737 + 0.642, 0.737 + 0.159, 0.737) * 18.4;
+        let the_sun: Arc<dyn RayTraceable<RF>> = if self.sphere_sun {
+            Arc::new(Sphere::new(
+                Vector3f::new(278.0, 480.0, 279.5),
+                40.0,
+                Box::new(Emissive::new(
+                    Box::new(Constant::new(the_sum_diff * 2.0)),
+                    1.0,
+                ).with_surface(Box::new(Diffuse::new(
                     Vector3f::new(0.14, 0.45, 0.091),
+                )))),
+            ))
+        } else {
+            Arc::new(Mesh::new(
+                "cornellbox/light.obj".to_string(),
+                Box::new(Emissive::new(
+                    Box::new(Constant::new(the_sum_diff)),
+                    1.0,
                 )),
-            )),
-            Vector3f::new(0.747 + 0.058, 0.747 + 0.258, 0.747) * 8.0
-                + Vector3f::new(0.740 + 0.287, 0.740 + 0.160, 0.740) * 15.6
-                + Vector3f::new(0.737 + 0.642, 0.737 + 0.159, 0.737) * 18.4,
-        );
-        // let the_sum_diff = Vector3f::new(0.747 + 0.058, 0.747 + 0.258, 0.747) * 8.0
-        //     + Vector3f::new(0.740 + 0.287, 0.740 + 0.160, 0.740) * 15.6
-        //     + Vector3f::new(0.737 + 0.642, 0.737 + 0.159, 0.737) * 18.4;
-        // let the_sun = Light::new(
-        //     Box::new(Sphere::new(
-        //         Vector3f::new(278.0, 480.0, 279.5),
-        //         40.0,
-        //         Box::new(Diffuse::new(
-        //             Vector3f::new(0.14, 0.45, 0.091),
-        //         ))
-        //     )),
-        //     the_sum_diff * 2.0,
-        // );
+            ).expect("Something went wrong reading the mesh"))
+        };
 
         Scene {
             objects: vec![
@@ -93,16 +114,79 @@ impl SceneGenerator<RF> for PracticalSceneGenerator {
                 Arc::new(short_box), Arc::new(tall_box),
                 Arc::new(left_wall), Arc::new(right_wall),
                 Arc::new(the_ball), Arc::new(the_smaller_ball), Arc::new(the_bigger_ball),
-                Arc::new(the_sun),
-            ]
+                the_sun,
+            ],
+            environment: if self.sky {
+                let sun_direction = Vector3f::new(0.3, 0.4, -1.0).norm();
+                // The sky model is in kcd/m^2, far brighter than the ceiling light
+                let daylight = Daylight::new(sun_direction, 3.0, Vector3f::new(0.3, 0.3, 0.3)).scaled(0.01);
+                Some(Arc::new(daylight) as Arc<dyn Environment<RF>>)
+            } else {
+                None
+            },
+            medium: if self.fog {
+                let fog = Homogeneous::new(Vector3f::new(0.0002, 0.0002, 0.0002), Vector3f::new(0.001, 0.001, 0.001), 0.3);
+                Some(Arc::new(fog) as Arc<dyn Medium<RF>>)
+            } else {
+                None
+            },
+            spectral: self.spectral,
         }
     }
 }
 
+// Only disperses on spectral paths, the index is 1.2 in the middle of the range
+fn glass() -> Box<dyn Material<RF>> {
+    Box::new(Refract::new(1.2).with_cauchy(1.19, 0.003))
+}
+
+// Puff densest at the center and fading out to the radius
+fn smoke(center: Vector3f, radius: RF) -> Grid<RF> {
+    let n = 16;
+    let mut density = Vec::with_capacity(n * n * n);
+    for z in 0..n {
+        for y in 0..n {
+            for x in 0..n {
+                let voxel = Vector3f::new(x as RF, y as RF, z as RF) + Vector3f::one() * 0.5;
+                let offset = voxel * (2.0 / n as RF) - Vector3f::one();
+                density.push((1.0 - offset.magnitude()).max(0.0));
+            }
+        }
+    }
+
+    let corner = Vector3f::one() * radius;
+    Grid::from_density(
+        [n, n, n], density,
+        center - corner, center + corner,
+        Vector3f::new(0.002, 0.002, 0.002), Vector3f::new(0.03, 0.03, 0.03), 0.5,
+    ).expect("Something went wrong building the smoke")
+}
+
 fn main() {
-    let scene_gen = Arc::new(PracticalSceneGenerator {});
+    let mut scene_gen = PracticalSceneGenerator {
+        sphere_sun: false, sky: false, spectral: false, fog: false, smoke: false,
+    };
+    let mut integrator = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--sphere-sun" => scene_gen.sphere_sun = true,
+            "--sky" => scene_gen.sky = true,
+            "--spectral" => scene_gen.spectral = true,
+            "--fog" => scene_gen.fog = true,
+            "--smoke" => scene_gen.smoke = true,
+            name => { // One of integrators::NAMES
+                integrator = Some(integrators::from_name(name).unwrap_or_else(|| panic!(
+                    "unknown integrator {}, expected one of {} or sppm:<seconds>", name, integrators::NAMES.join(", ")
+                )));
+            }
+        }
+    }
+
     // let renderer: Renderer<f64> = Renderer::new(256, 256, 40, scene_gen);
-    let renderer: Renderer<RF> = Renderer::new(2048, 2048, 40, scene_gen, 24);
+    let mut renderer: Renderer<RF> = Renderer::new(2048, 2048, 40, Arc::new(scene_gen), 24);
+    if let Some(integrator) = integrator {
+        renderer = renderer.with_integrator(integrator);
+    }
 
     let eye_pos = Vector3f::new(278.0, 273.0, -800.0);
 
@@ -1,4 +1,4 @@
-use proton::raytrace::objects::{Light, Mesh, Sphere};
+use proton::raytrace::objects::{Mesh, Sphere};
 use proton::raytrace::{Renderer, Scene, SceneGenerator};
 use proton::raytrace::objects::{Bounded, LightInteractable, LightSample, PartialBounded, RayTraceable};
 use proton::vector::Vector3D;
@@ -6,7 +6,8 @@ use proton::vector::Vector3D;
 use std::sync::Arc;
 use std::thread::JoinHandle;
 use image::DynamicImage;
-use proton::raytrace::materials::{Diffuse, Refract};
+use proton::raytrace::materials::{Diffuse, Emissive, Refract};
+use proton::raytrace::textures::Constant;
 
 type RF = f64;
 type Vector3f = Vector3D<RF>;
@@ -30,33 +31,33 @@ impl MovieSceneGenerator {
             Box::new(Diffuse::new(
                 Vector3f::new(0.725, 0.71, 0.68),
             )),
-        );
+        ).expect("Something went wrong reading the mesh");
 
         let short_box = Mesh::new(
             "cornellbox/shortbox.obj".to_string(),
             Box::new(Diffuse::new(
                 Vector3f::new(0.725, 0.71, 0.68),
             )),
-        );
+        ).expect("Something went wrong reading the mesh");
         let tall_box = Mesh::new(
             "cornellbox/tallbox.obj".to_string(),
             Box::new(Diffuse::new(
                 Vector3f::new(0.725, 0.71, 0.68),
             )),
-        );
+        ).expect("Something went wrong reading the mesh");
 
         let left_wall = Mesh::new(
             "cornellbox/left.obj".to_string(),
             Box::new(Diffuse::new(
                 Vector3f::new(0.63, 0.065, 0.05),
             )),
-        );
+        ).expect("Something went wrong reading the mesh");
         let right_wall = Mesh::new(
             "cornellbox/right.obj".to_string(),
             Box::new(Diffuse::new(
                 Vector3f::new(0.14, 0.45, 0.091),
             )),
-        );
+        ).expect("Something went wrong reading the mesh");
 
         let the_ball = Sphere::new(
             Vector3f::new(200.0, 240.0, 200.0),
@@ -64,17 +65,17 @@ impl MovieSceneGenerator {
             Box::new(Refract::new(1.2)),
         );
 
-        let the_sun = Light::new(
-            Box::new(Mesh::new(
-                "cornellbox/light.obj".to_string(),
-                Box::new(Diffuse::new(
-                    Vector3f::new(0.14, 0.45, 0.091),
+        let the_sun = Mesh::new(
+            "cornellbox/light.obj".to_string(),
+            Box::new(Emissive::new(
+                Box::new(Constant::new(
+                    Vector3f::new(0.747 + 0.058, 0.747 + 0.258, 0.747) * 8.0
+                        + Vector3f::new(0.740 + 0.287, 0.740 + 0.160, 0.740) * 15.6
+                        + Vector3f::new(0.737 + 0.642, 0.737 + 0.159, 0.737) * 18.4,
                 )),
+                1.0,
             )),
-            Vector3f::new(0.747 + 0.058, 0.747 + 0.258, 0.747) * 8.0
-                + Vector3f::new(0.740 + 0.287, 0.740 + 0.160, 0.740) * 15.6
-                + Vector3f::new(0.737 + 0.642, 0.737 + 0.159, 0.737) * 18.4,
-        );
+        ).expect("Something went wrong reading the mesh");
         // let the_sum_diff = Vector3f::new(0.747 + 0.058, 0.747 + 0.258, 0.747) * 8.0
         //     + Vector3f::new(0.740 + 0.287, 0.740 + 0.160, 0.740) * 15.6
         //     + Vector3f::new(0.737 + 0.642, 0.737 + 0.159, 0.737) * 18.4;
@@ -167,7 +168,10 @@ impl SceneGenerator<RF> for MovieSceneGenerator {
         objects.push(Arc::new(the_bigger_ball));
 
         Scene {
-            objects
+            objects,
+            environment: None,
+            medium: None,
+            spectral: false,
         }
     }
 }
@@ -196,7 +200,7 @@ fn main() {
                     )
                 );
                 // let renderer: Renderer<f64> = Renderer::new(256, 256, 40, scene_gen);
-                let renderer: Renderer<RF> = Renderer::new(
+                let mut renderer: Renderer<RF> = Renderer::new(
                     2048, 2048, 40,
                     scene_gen,
                     4,
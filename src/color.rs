@@ -0,0 +1,49 @@
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+pub fn xyy_to_xyz<F: Float>(x: F, y: F, big_y: F) -> Vector3D<F> {
+    if y <= F::zero() {
+        return Vector3D::zero();
+    }
+
+    Vector3D::new(
+        x * big_y / y,
+        big_y,
+        (F::one() - x - y) * big_y / y,
+    )
+}
+
+// Linear sRGB, D65 white point
+pub fn xyz_to_rgb<F: Float>(xyz: Vector3D<F>) -> Vector3D<F> {
+    let c = |v: f64| F::from(v).unwrap();
+
+    Vector3D::new(
+        c(3.2404542) * xyz.x - c(1.5371385) * xyz.y - c(0.4985314) * xyz.z,
+        c(-0.9692660) * xyz.x + c(1.8760108) * xyz.y + c(0.0415560) * xyz.z,
+        c(0.0556434) * xyz.x - c(0.2040259) * xyz.y + c(1.0572252) * xyz.z,
+    )
+}
+
+// Relative luminance of linear sRGB
+pub fn luminance<F: Float>(rgb: Vector3D<F>) -> F {
+    F::from(0.2126).unwrap() * rgb.x
+        + F::from(0.7152).unwrap() * rgb.y
+        + F::from(0.0722).unwrap() * rgb.z
+}
+
+// CIE 1931 matching functions at a wavelength in nm, the multi-lobe fit by
+// Wyman, Sloan and Shirley
+pub fn cie_xyz<F: Float>(wavelength: F) -> Vector3D<F> {
+    let lambda = wavelength.to_f64().unwrap();
+    let g = |mu: f64, sigma_below: f64, sigma_above: f64| {
+        let sigma = if lambda < mu { sigma_below } else { sigma_above };
+        let t = (lambda - mu) / sigma;
+        (-0.5 * t * t).exp()
+    };
+
+    let x = 1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2);
+    let y = 0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1);
+    let z = 1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8);
+
+    Vector3D::new(F::from(x).unwrap(), F::from(y).unwrap(), F::from(z).unwrap())
+}
@@ -2,6 +2,7 @@ extern crate core;
 
 pub mod types;
 pub mod vector;
+pub mod color;
 
 pub mod objects;
 
@@ -0,0 +1,119 @@
+use crate::objects::shape::{azimuth, circle_bounds, frame, quadratic, Shape};
+use crate::raytrace::{Incident, Ray};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Open side of a cone from a circular base to the apex along axis,
+// cap it with a disk to close it
+#[derive(Debug, Clone, Copy)]
+pub struct Cone<F: Float> {
+    base: Vector3D<F>,
+    axis: Vector3D<F>,
+    height: F,
+    radius: F,
+
+    apex: Vector3D<F>,
+    // Squared radius over height, how fast the cone widens
+    slope_2: F,
+    frame: (Vector3D<F>, Vector3D<F>),
+}
+
+impl<F: Float> Cone<F> {
+    // axis spans the height
+    pub fn new(base: Vector3D<F>, axis: Vector3D<F>, radius: F) -> Self {
+        let height = axis.magnitude();
+        let unit_axis = axis / height;
+        let slope = radius / height;
+
+        Self {
+            base,
+            axis: unit_axis,
+            height,
+            radius,
+            apex: base + axis,
+            slope_2: slope * slope,
+            frame: frame(unit_axis),
+        }
+    }
+
+    // Outward, tilted toward the apex
+    fn normal_at(&self, coords: Vector3D<F>) -> Vector3D<F> {
+        let local = coords - self.apex;
+        let down = -local.dot(self.axis);
+        let radial = local + self.axis * down;
+
+        (radial + self.axis * (self.slope_2 * down)).norm()
+    }
+}
+
+impl<F: Float> Shape<F> for Cone<F> {
+    fn name(&self) -> String {
+        "cone".to_string()
+    }
+
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        let _two = F::from(2u32).unwrap();
+
+        // Radius squared equals slope squared times depth squared, below the apex
+        let o = ray.origin() - self.apex;
+        let d = ray.direction();
+        let k = F::one() + self.slope_2;
+        let (o_a, d_a) = (o.dot(self.axis), d.dot(self.axis));
+
+        let a = d.dot(d) - k * d_a * d_a;
+        let b = _two * (o.dot(d) - k * o_a * d_a);
+        let c = o.dot(o) - k * o_a * o_a;
+        let (t0, t1) = if a == F::zero() { // Parallel to the side, a single root
+            let t = -c / b;
+            (t, t)
+        } else {
+            quadratic(a, b, c)?
+        };
+
+        let t = [t0, t1].into_iter().find(|&t| {
+            let down = -(o + d * t).dot(self.axis);
+            t > F::zero() && down >= F::zero() && down <= self.height
+        })?;
+
+        let coords = ray.origin() + d * t;
+        let local = coords - self.apex;
+        let down = -local.dot(self.axis);
+        let radial = local + self.axis * down;
+
+        let outward = self.normal_at(coords);
+        let back = d.dot(outward) > F::zero();
+        Some(
+            Incident::new(
+                coords,
+                if back { -outward } else { outward },
+                t,
+                -d,
+                back,
+            ).with_uv((azimuth(radial, self.frame), F::one() - down / self.height))
+        )
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        let (min, max) = circle_bounds(self.base, self.axis, self.radius);
+
+        (min.min(self.apex), max.max(self.apex))
+    }
+
+    fn area(&self) -> F {
+        F::PI() * self.radius * (self.radius * self.radius + self.height * self.height).sqrt()
+    }
+
+    fn sample_location(&self) -> (Vector3D<F>, Vector3D<F>, (F, F)) {
+        let _two = F::from(2u32).unwrap();
+
+        // Circles grow with depth, so does their share of the area
+        let depth = F::sample_rand().sqrt();
+        let u = F::sample_rand();
+        let phi = _two * F::PI() * u;
+
+        let around = self.frame.0 * phi.cos() + self.frame.1 * phi.sin();
+        let coords = self.apex - self.axis * (depth * self.height) + around * (depth * self.radius);
+
+        (coords, self.normal_at(coords), (u, F::one() - depth))
+    }
+}
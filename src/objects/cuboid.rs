@@ -0,0 +1,142 @@
+use crate::objects::shape::Shape;
+use crate::raytrace::{Incident, Ray};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Axis-aligned box, closed so it can hold a medium
+#[derive(Debug, Clone, Copy)]
+pub struct Cuboid<F: Float> {
+    min: Vector3D<F>,
+    max: Vector3D<F>,
+}
+
+fn axes<F: Float>(v: Vector3D<F>) -> [F; 3] {
+    [v.x, v.y, v.z]
+}
+
+fn unit<F: Float>(axis: usize, sign: F) -> Vector3D<F> {
+    let mut v = [F::zero(); 3];
+    v[axis] = sign;
+
+    Vector3D::new(v[0], v[1], v[2])
+}
+
+impl<F: Float> Cuboid<F> {
+    pub fn new(min: Vector3D<F>, max: Vector3D<F>) -> Self {
+        Self {
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+
+    fn face_areas(&self) -> [F; 3] {
+        let [x, y, z] = axes(self.max - self.min);
+
+        [y * z, z * x, x * y]
+    }
+
+    // Texture coordinates on the face normal to axis, across the other two
+    fn face_uv(&self, coords: Vector3D<F>, axis: usize) -> (F, F) {
+        let local = axes((coords - self.min) / (self.max - self.min));
+
+        (local[(axis + 1) % 3], local[(axis + 2) % 3])
+    }
+}
+
+impl<F: Float> Shape<F> for Cuboid<F> {
+    fn name(&self) -> String {
+        "cuboid".to_string()
+    }
+
+    // Slabs, the entry is hit from outside and the exit from inside
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        let origin = axes(ray.origin());
+        let direction = axes(ray.direction());
+        let min = axes(self.min);
+        let max = axes(self.max);
+
+        let mut near = (F::min_value(), 0);
+        let mut far = (F::max_value(), 0);
+        for axis in 0..3 {
+            if direction[axis] == F::zero() {
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+
+            let inv = F::one() / direction[axis];
+            let t0 = (min[axis] - origin[axis]) * inv;
+            let t1 = (max[axis] - origin[axis]) * inv;
+            let (t0, t1) = (t0.min(t1), t0.max(t1));
+            if t0 > near.0 {
+                near = (t0, axis);
+            }
+            if t1 < far.0 {
+                far = (t1, axis);
+            }
+        }
+        if near.0 > far.0 || far.0 <= F::zero() {
+            return None;
+        }
+
+        let inside = near.0 <= F::zero();
+        let (t, axis) = if inside { far } else { near };
+
+        // On the face exactly, the other two axes carry the rounding
+        let mut coords = axes(ray.origin() + ray.direction() * t);
+        let leaving_max = (direction[axis] > F::zero()) == inside;
+        coords[axis] = if leaving_max { max[axis] } else { min[axis] };
+        let coords = Vector3D::new(coords[0], coords[1], coords[2]);
+
+        let outward = unit(axis, if leaving_max { F::one() } else { -F::one() });
+        Some(
+            Incident::new(
+                coords,
+                if inside { -outward } else { outward },
+                t,
+                -ray.direction(),
+                inside,
+            ).with_uv(self.face_uv(coords, axis))
+        )
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        (self.min, self.max)
+    }
+
+    fn area(&self) -> F {
+        let [x, y, z] = self.face_areas();
+
+        F::from(2u32).unwrap() * (x + y + z)
+    }
+
+    fn sample_location(&self) -> (Vector3D<F>, Vector3D<F>, (F, F)) {
+        let _half = F::from(0.5).unwrap();
+
+        // A face by its area, then either side
+        let areas = self.face_areas();
+        let mut target = F::sample_rand() * (areas[0] + areas[1] + areas[2]);
+        let mut axis = 2;
+        for (i, &area) in areas.iter().enumerate() {
+            if target < area {
+                axis = i;
+                break;
+            }
+            target = target - area;
+        }
+        let upper = F::sample_rand() < _half;
+
+        let (u, v) = (F::sample_rand(), F::sample_rand());
+        let extent = axes(self.max - self.min);
+        let mut coords = axes(self.min);
+        coords[(axis + 1) % 3] = coords[(axis + 1) % 3] + extent[(axis + 1) % 3] * u;
+        coords[(axis + 2) % 3] = coords[(axis + 2) % 3] + extent[(axis + 2) % 3] * v;
+        if upper {
+            coords[axis] = axes(self.max)[axis];
+        }
+
+        let normal = unit(axis, if upper { F::one() } else { -F::one() });
+        (Vector3D::new(coords[0], coords[1], coords[2]), normal, (u, v))
+    }
+}
@@ -0,0 +1,310 @@
+use crate::objects::shape::{frame, Shape};
+use crate::raytrace::{Incident, Ray};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Subdivisions before a piece of the curve counts as straight
+const MAX_DEPTH: u32 = 10;
+// Straight pieces the surface is measured and sampled over
+const AREA_STEPS: usize = 16;
+
+// How the width of a curve faces the ray
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveKind {
+    // Flat strip turned toward the ray, for grass blades and distant fur
+    Ribbon,
+    // Round tube, the normal bends around it across the width
+    Cylinder,
+}
+
+// Cubic Bézier segment with a width going linearly from one end to the other.
+// Hits report the tangent, u along the curve and v across its width
+#[derive(Debug, Clone, Copy)]
+pub struct Curve<F: Float> {
+    points: [Vector3D<F>; 4],
+    width: (F, F),
+
+    kind: CurveKind,
+}
+
+impl<F: Float> Curve<F> {
+    pub fn new(points: [Vector3D<F>; 4], width: (F, F), kind: CurveKind) -> Self {
+        Self { points, width, kind }
+    }
+
+    // Smooth segments through every point of a strand, width goes from root to tip
+    pub fn strand(points: &[Vector3D<F>], width: (F, F), kind: CurveKind) -> Vec<Self> {
+        let _sixth = F::from(6u32).unwrap().recip();
+
+        let count = points.len().saturating_sub(1);
+        let width_at = |i: usize| {
+            let s = F::from(i).unwrap() / F::from(count).unwrap();
+            width.0 + (width.1 - width.0) * s
+        };
+
+        // Catmull-Rom through the points, written as Bézier control points
+        (0..count).map(|i| {
+            let prev = points[i.saturating_sub(1)];
+            let next = points[(i + 2).min(count)];
+            let (p0, p1) = (points[i], points[i + 1]);
+
+            Self::new(
+                [p0, p0 + (p1 - prev) * _sixth, p1 - (next - p0) * _sixth, p1],
+                (width_at(i), width_at(i + 1)),
+                kind,
+            )
+        }).collect()
+    }
+
+    fn width_at(&self, u: F) -> F {
+        self.width.0 + (self.width.1 - self.width.0) * u
+    }
+
+    // Length times width of each straight piece, evenly spaced in u
+    fn pieces(&self) -> [F; AREA_STEPS] {
+        let steps = F::from(AREA_STEPS).unwrap();
+        let _half = F::from(0.5).unwrap();
+
+        let mut pieces = [F::zero(); AREA_STEPS];
+        let mut prev = self.points[0];
+        for (i, piece) in pieces.iter_mut().enumerate() {
+            let (point, _) = evaluate(&self.points, F::from(i + 1).unwrap() / steps);
+            let middle = (F::from(i).unwrap() + _half) / steps;
+
+            *piece = (point - prev).magnitude() * self.width_at(middle);
+            prev = point;
+        }
+
+        pieces
+    }
+}
+
+// Point and derivative at u, by de Casteljau
+fn evaluate<F: Float>(points: &[Vector3D<F>; 4], u: F) -> (Vector3D<F>, Vector3D<F>) {
+    let lerp = |a: Vector3D<F>, b: Vector3D<F>| a + (b - a) * u;
+
+    let (a, b, c) = (lerp(points[0], points[1]), lerp(points[1], points[2]), lerp(points[2], points[3]));
+    let (d, e) = (lerp(a, b), lerp(b, c));
+    let derivative = (e - d) * F::from(3u32).unwrap();
+    if derivative == Vector3D::zero() { // Repeated end points
+        return (lerp(d, e), points[3] - points[0]);
+    }
+
+    (lerp(d, e), derivative)
+}
+
+// Halves at the middle, each half is again a cubic Bézier
+fn split<F: Float>(points: &[Vector3D<F>; 4]) -> ([Vector3D<F>; 4], [Vector3D<F>; 4]) {
+    let _half = F::from(0.5).unwrap();
+    let mid = |a: Vector3D<F>, b: Vector3D<F>| (a + b) * _half;
+
+    let (a, b, c) = (mid(points[0], points[1]), mid(points[1], points[2]), mid(points[2], points[3]));
+    let (d, e) = (mid(a, b), mid(b, c));
+    let f = mid(d, e);
+
+    ([points[0], a, d, f], [f, e, c, points[3]])
+}
+
+impl<F: Float> Curve<F> {
+    // Depth after which every piece is within a twentieth of the width of a line, as in pbrt
+    fn depth(&self, points: &[Vector3D<F>; 4]) -> u32 {
+        let _two = F::from(2u32).unwrap();
+
+        let mut l0 = F::zero();
+        for i in 0..2 {
+            let d = (points[i] - points[i + 1] * _two + points[i + 2]).abs();
+            l0 = l0.max(d.x.max(d.y).max(d.z));
+        }
+
+        let epsilon = self.width.0.max(self.width.1) * F::from(0.05).unwrap();
+        if l0 <= F::zero() || epsilon <= F::zero() {
+            return 0;
+        }
+
+        let depth = (F::SQRT_2() * F::from(6u32).unwrap() * l0 / (F::from(8u32).unwrap() * epsilon)).log2() / _two;
+        depth.max(F::zero()).min(F::from(MAX_DEPTH).unwrap()).to_u32().unwrap()
+    }
+
+    // Nearest hit closer than t_max, in ray space where the ray runs up the z axis.
+    // Gives the distance, u and the offset across the width from -1 to 1
+    fn recurse(&self, points: &[Vector3D<F>; 4], range: (F, F), depth: u32, t_max: F) -> Option<(F, F, F)> {
+        let _half = F::from(0.5).unwrap();
+
+        let reach = self.width_at(range.0).max(self.width_at(range.1)) * _half;
+        let (min, max) = points.iter().fold(
+            (Vector3D::max_value(), Vector3D::min_value()),
+            |(min, max), &p| (min.min(p), max.max(p)),
+        );
+        if min.x - reach > F::zero() || max.x + reach < F::zero()
+            || min.y - reach > F::zero() || max.y + reach < F::zero()
+            || max.z + reach < F::zero() || min.z - reach > t_max {
+            return None;
+        }
+
+        if depth > 0 {
+            let (first, second) = split(points);
+            let mid = (range.0 + range.1) * _half;
+
+            let near = self.recurse(&first, (range.0, mid), depth - 1, t_max);
+            let far = self.recurse(&second, (mid, range.1), depth - 1, near.map_or(t_max, |hit| hit.0));
+            return far.or(near); // Only found if closer
+        }
+
+        // Straight enough, closest approach to the line between the end points
+        let (p0, p3) = (points[0], points[3]);
+        let (dx, dy) = (p3.x - p0.x, p3.y - p0.y);
+        let length = dx * dx + dy * dy;
+        let w = if length > F::zero() {
+            (-(p0.x * dx + p0.y * dy) / length).max(F::zero()).min(F::one())
+        } else {
+            F::zero()
+        };
+
+        let (point, derivative) = evaluate(points, w);
+        let u = range.0 + (range.1 - range.0) * w;
+        let radius = self.width_at(u) * _half;
+        let distance = (point.x * point.x + point.y * point.y).sqrt();
+        if distance > radius || point.z <= F::zero() || point.z >= t_max {
+            return None;
+        }
+
+        // Positive on the side the normal crossed with the tangent points to
+        let side = derivative.x * point.y - derivative.y * point.x;
+        let offset = if side < F::zero() { -distance } else { distance } / radius;
+
+        Some((point.z, u, offset))
+    }
+}
+
+impl<F: Float> Shape<F> for Curve<F> {
+    fn name(&self) -> String {
+        "curve".to_string()
+    }
+
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        let direction = ray.direction();
+        let (dx, dy) = frame(direction);
+        let to_ray = |p: Vector3D<F>| {
+            let o = p - ray.origin();
+            Vector3D::new(o.dot(dx), o.dot(dy), o.dot(direction))
+        };
+        let points = self.points.map(to_ray);
+
+        let (t, u, offset) = self.recurse(&points, (F::zero(), F::one()), self.depth(&points), F::infinity())?;
+
+        let (center, derivative) = evaluate(&self.points, u);
+        let tangent = derivative.norm();
+        let facing = -direction - tangent * (-direction).dot(tangent);
+        if facing.magnitude() <= F::epsilon() { // Looking down the curve
+            return None;
+        }
+        let facing = facing.norm();
+
+        let width = self.width_at(u);
+        let (coords, normal, t) = match self.kind {
+            CurveKind::Ribbon => (ray.origin() + direction * t, facing, t),
+            CurveKind::Cylinder => {
+                let side = facing.cross(tangent);
+                let normal = facing * (F::one() - offset * offset).max(F::zero()).sqrt() + side * offset;
+                let coords = center + normal * (width * F::from(0.5).unwrap());
+
+                (coords, normal, (coords - ray.origin()).dot(direction))
+            }
+        };
+        if t <= F::zero() {
+            return None;
+        }
+
+        // Only as close to the true surface as the pieces are to lines
+        Some(
+            Incident::new(coords, normal, t, -direction, false)
+                .with_uv((u, (offset + F::one()) * F::from(0.5).unwrap()))
+                .with_tangent(tangent)
+                .with_error(Vector3D::one() * width)
+        )
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        let reach = self.width.0.max(self.width.1) * F::from(0.5).unwrap();
+        let (min, max) = self.points.iter().fold(
+            (Vector3D::max_value(), Vector3D::min_value()),
+            |(min, max), &p| (min.min(p), max.max(p)),
+        );
+
+        (min - reach, max + reach)
+    }
+
+    fn area(&self) -> F {
+        let area = self.pieces().iter().fold(F::zero(), |sum, &piece| sum + piece);
+        match self.kind {
+            CurveKind::Ribbon => area,
+            CurveKind::Cylinder => area * F::PI(),
+        }
+    }
+
+    // Uniform over the same pieces area measures, so the density is one over the area
+    fn sample_location(&self) -> (Vector3D<F>, Vector3D<F>, (F, F)) {
+        let _half = F::from(0.5).unwrap();
+
+        let pieces = self.pieces();
+        let mut target = F::sample_rand() * pieces.iter().fold(F::zero(), |sum, &piece| sum + piece);
+        let mut u = F::one();
+        for (i, &piece) in pieces.iter().enumerate() {
+            if target < piece { // Lands on this one, close enough to uniform along it
+                u = (F::from(i).unwrap() + target / piece) / F::from(AREA_STEPS).unwrap();
+                break;
+            }
+            target = target - piece;
+        }
+        let v = F::sample_rand();
+
+        let (center, derivative) = evaluate(&self.points, u);
+        let across = frame(derivative.norm());
+        let width = self.width_at(u);
+
+        match self.kind {
+            CurveKind::Ribbon => (center + across.1 * ((v - _half) * width), across.0, (u, v)),
+            CurveKind::Cylinder => {
+                let phi = F::from(2u32).unwrap() * F::PI() * v;
+                let normal = across.0 * phi.cos() + across.1 * phi.sin();
+
+                (center + normal * (width * _half), normal, (u, v))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn area_of_straight_ribbon() {
+        let x = |x: f64| Vector3D::new(x, 0.0, 0.0);
+        let curve = Curve::new([x(0.0), x(1.0), x(2.0), x(3.0)], (0.1, 0.3), CurveKind::Ribbon);
+
+        assert!((curve.area() - 0.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sample_by_length() {
+        // Crowded toward the start in u, x goes as u cubed
+        let x = |x: f64| Vector3D::new(x, 0.0, 0.0);
+        let curve = Curve::new([x(0.0), x(0.0), x(0.0), x(1.0)], (0.01, 0.01), CurveKind::Cylinder);
+
+        let n = 20000;
+        let mean = (0..n).map(|_| curve.sample_location().0.x).sum::<f64>() / n as f64;
+        assert!((mean - 0.5).abs() < 0.02, "mean {}", mean);
+    }
+
+    #[test]
+    fn sample_by_width() {
+        // Three times as much surface at the wide end
+        let x = |x: f64| Vector3D::new(x, 0.0, 0.0);
+        let curve = Curve::new([x(0.0), x(1.0), x(2.0), x(3.0)], (0.0, 0.2), CurveKind::Ribbon);
+
+        let n = 20000;
+        let far = (0..n).filter(|_| curve.sample_location().0.x > 1.5).count();
+        assert!((far as f64 / n as f64 - 0.75).abs() < 0.02, "{} of {}", far, n);
+    }
+}
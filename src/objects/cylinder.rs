@@ -0,0 +1,102 @@
+use crate::objects::shape::{azimuth, circle_bounds, frame, quadratic, Shape};
+use crate::raytrace::{gamma, Incident, Ray};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Open tube from base along axis, cap it with disks to close it
+#[derive(Debug, Clone, Copy)]
+pub struct Cylinder<F: Float> {
+    base: Vector3D<F>,
+    axis: Vector3D<F>,
+    height: F,
+    radius: F,
+
+    frame: (Vector3D<F>, Vector3D<F>),
+}
+
+impl<F: Float> Cylinder<F> {
+    // axis spans the height
+    pub fn new(base: Vector3D<F>, axis: Vector3D<F>, radius: F) -> Self {
+        let height = axis.magnitude();
+        let axis = axis / height;
+
+        Self {
+            base,
+            axis,
+            height,
+            radius,
+            frame: frame(axis),
+        }
+    }
+}
+
+impl<F: Float> Shape<F> for Cylinder<F> {
+    fn name(&self) -> String {
+        "cylinder".to_string()
+    }
+
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        let _two = F::from(2u32).unwrap();
+
+        // Across the axis only
+        let across = |v: Vector3D<F>| v - self.axis * v.dot(self.axis);
+        let origin = ray.origin() - self.base;
+        let o = across(origin);
+        let d = across(ray.direction());
+
+        let a = d.dot(d);
+        if a == F::zero() { // Along the axis
+            return None;
+        }
+        let (t0, t1) = quadratic(a, _two * o.dot(d), o.dot(o) - self.radius * self.radius)?;
+
+        let t = [t0, t1].into_iter().find(|&t| {
+            let height = (origin + ray.direction() * t).dot(self.axis);
+            t > F::zero() && height >= F::zero() && height <= self.height
+        })?;
+
+        // Back onto the surface, the root itself may be far off
+        let local = origin + ray.direction() * t;
+        let along = local.dot(self.axis);
+        let radial = across(local);
+        let radial = radial * (self.radius / radial.magnitude());
+        let coords = self.base + self.axis * along + radial;
+        let error = radial.abs() * gamma::<F>(5) + coords.abs() * gamma::<F>(3);
+
+        let outward = radial / self.radius;
+        let back = ray.direction().dot(outward) > F::zero();
+        Some(
+            Incident::new(
+                coords,
+                if back { -outward } else { outward },
+                t,
+                -ray.direction(),
+                back,
+            ).with_uv((azimuth(radial, self.frame), along / self.height)).with_error(error)
+        )
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        let (min0, max0) = circle_bounds(self.base, self.axis, self.radius);
+        let (min1, max1) = circle_bounds(self.base + self.axis * self.height, self.axis, self.radius);
+
+        (min0.min(min1), max0.max(max1))
+    }
+
+    fn area(&self) -> F {
+        F::from(2u32).unwrap() * F::PI() * self.radius * self.height
+    }
+
+    fn sample_location(&self) -> (Vector3D<F>, Vector3D<F>, (F, F)) {
+        let _two = F::from(2u32).unwrap();
+
+        let u = F::sample_rand();
+        let v = F::sample_rand();
+        let phi = _two * F::PI() * u;
+
+        let normal = self.frame.0 * phi.cos() + self.frame.1 * phi.sin();
+        let coords = self.base + self.axis * (v * self.height) + normal * self.radius;
+
+        (coords, normal, (u, v))
+    }
+}
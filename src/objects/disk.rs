@@ -0,0 +1,84 @@
+use crate::objects::shape::{azimuth, circle_bounds, facing, frame, Shape};
+use crate::raytrace::{Incident, Ray};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Disk<F: Float> {
+    center: Vector3D<F>,
+    normal: Vector3D<F>,
+    radius: F,
+
+    frame: (Vector3D<F>, Vector3D<F>),
+}
+
+impl<F: Float> Disk<F> {
+    pub fn new(center: Vector3D<F>, normal: Vector3D<F>, radius: F) -> Self {
+        let normal = normal.norm();
+
+        Self {
+            center,
+            normal,
+            radius,
+            frame: frame(normal),
+        }
+    }
+}
+
+impl<F: Float> Shape<F> for Disk<F> {
+    fn name(&self) -> String {
+        "disk".to_string()
+    }
+
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        let denom = ray.direction().dot(self.normal);
+        if denom == F::zero() { // Parallel
+            return None;
+        }
+
+        let t = (self.center - ray.origin()).dot(self.normal) / denom;
+        if t <= F::zero() {
+            return None;
+        }
+
+        let coords = ray.origin() + ray.direction() * t;
+        let offset = coords - self.center;
+        let distance_2 = offset.dot(offset);
+        if distance_2 > self.radius * self.radius {
+            return None;
+        }
+
+        // Around the rim, then out from the center
+        let uv = (azimuth(offset, self.frame), distance_2.sqrt() / self.radius);
+
+        let (normal, back) = facing(ray, self.normal);
+        Some(
+            Incident::new(coords, normal, t, -ray.direction(), back)
+                .with_uv(uv)
+        )
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        circle_bounds(self.center, self.normal, self.radius)
+    }
+
+    fn area(&self) -> F {
+        F::PI() * self.radius * self.radius
+    }
+
+    fn sample_location(&self) -> (Vector3D<F>, Vector3D<F>, (F, F)) {
+        let _two = F::from(2u32).unwrap();
+
+        let r = F::sample_rand().sqrt();
+        let u = F::sample_rand();
+        let phi = _two * F::PI() * u;
+
+        let offset = (self.frame.0 * phi.cos() + self.frame.1 * phi.sin()) * (r * self.radius);
+
+        (self.center + offset, self.normal, (u, r))
+    }
+
+    fn normal_cone(&self) -> (Vector3D<F>, F) {
+        (self.normal, F::one())
+    }
+}
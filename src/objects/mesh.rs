@@ -1,29 +1,16 @@
-use crate::objects::Triangle;
+use crate::objects::{Obj, ObjError, ObjMaterial, Subdivision, Triangle};
 use crate::types::Float;
 use crate::vector::Vector3D;
 
-mod backend {
-    pub use tri_mesh::mesh::Mesh;
-    pub use tri_mesh::mesh_builder::MeshBuilder;
-    pub use tri_mesh::prelude::Vec3;
-    pub use tri_mesh::prelude::Intersection;
-    pub use tri_mesh::prelude::Primitive;
-
-    use crate::types::Float;
-    use crate::vector::Vector3D;
-
-    pub fn vector_3d_from_vec3<F: Float>(vec3: Vec3) -> Vector3D<F> {
-        Vector3D::new(
-            F::from(vec3.x).unwrap(),
-            F::from(vec3.y).unwrap(),
-            F::from(vec3.z).unwrap(),
-        )
-    }
-}
+use std::collections::HashMap;
 
 #[derive(Clone)]
 pub struct Mesh<F: Float> {
     triangles: Vec<Triangle<F>>,
+    // usemtl name of every triangle
+    triangle_materials: Vec<Option<String>>,
+
+    materials: HashMap<String, ObjMaterial<F>>,
 
     area: F,
 
@@ -32,37 +19,54 @@ pub struct Mesh<F: Float> {
 }
 
 impl<F: Float> Mesh<F> {
-    pub fn new(source: String) -> Self {
-        let obj_source = std::fs::read_to_string(source)
-            .expect("Something went wrong reading the file");
+    pub fn new(source: String) -> Result<Self, ObjError> {
+        Ok(Self::from_obj(Obj::new(&source)?))
+    }
 
-        let builder = backend::MeshBuilder::new();
-        let mesh = builder.with_obj(obj_source).build().unwrap();
+    pub fn new_subdivided(source: String, subdivision: &Subdivision<F>) -> Result<Self, ObjError> {
+        Ok(Self::from_obj(subdivision.apply(&Obj::new(&source)?)))
+    }
 
+    pub fn from_obj(obj: Obj<F>) -> Self {
         let mut min_vert = Vector3D::max_value();
         let mut max_vert = Vector3D::min_value();
 
         let mut area = F::zero();
 
         let mut triangles = Vec::new();
-        for face_id in mesh.face_iter() {
-            let (v0, v1, v2) = mesh.face_positions(face_id);
-            let v0 = backend::vector_3d_from_vec3(v0);
-            let v1 = backend::vector_3d_from_vec3(v1);
-            let v2 = backend::vector_3d_from_vec3(v2);
-
-            min_vert = min_vert.min(v0.min(v1.min(v2.clone())));
-            max_vert = max_vert.max(v0.max(v1.max(v2.clone())));
-
-            let triangle = Triangle::new(
-                v0, v1, v2,
-            );
-            area = area + triangle.area();
-            triangles.push(triangle);
+        let mut triangle_materials = Vec::new();
+        for face in &obj.faces {
+            let (p0, t0) = face.vertices[0];
+            for i in 1..face.vertices.len() - 1 { // Fan triangulation
+                let (p1, t1) = face.vertices[i];
+                let (p2, t2) = face.vertices[i + 1];
+
+                let v0 = obj.positions[p0];
+                let v1 = obj.positions[p1];
+                let v2 = obj.positions[p2];
+
+                min_vert = min_vert.min(v0.min(v1.min(v2)));
+                max_vert = max_vert.max(v0.max(v1.max(v2)));
+
+                let mut triangle = Triangle::new(
+                    v0, v1, v2,
+                );
+                if let (Some(t0), Some(t1), Some(t2)) = (t0, t1, t2) {
+                    triangle = triangle.with_uvs(
+                        obj.texcoords[t0], obj.texcoords[t1], obj.texcoords[t2],
+                    );
+                }
+                area = area + triangle.area();
+                triangles.push(triangle);
+                triangle_materials.push(face.material.clone());
+            }
         }
 
         Self {
             triangles,
+            triangle_materials,
+
+            materials: obj.materials,
 
             area,
 
@@ -75,6 +79,14 @@ impl<F: Float> Mesh<F> {
         &self.triangles
     }
 
+    pub fn triangle_materials(&self) -> &Vec<Option<String>> {
+        &self.triangle_materials
+    }
+
+    pub fn materials(&self) -> &HashMap<String, ObjMaterial<F>> {
+        &self.materials
+    }
+
     pub fn area(&self) -> F {
         self.area
     }
@@ -1,7 +1,29 @@
 mod sphere;
 mod mesh;
 mod triangle;
+mod obj;
+mod shape;
+mod quad;
+mod disk;
+mod plane;
+mod cuboid;
+mod cylinder;
+mod cone;
+mod sdf;
+mod curve;
+mod subdivide;
 
 pub use sphere::Sphere;
 pub use mesh::Mesh;
 pub use triangle::Triangle;
+pub use obj::{Obj, ObjError, ObjFace, ObjMaterial};
+pub use shape::{quadratic, Shape};
+pub use quad::Quad;
+pub use disk::Disk;
+pub use plane::Plane;
+pub use cuboid::Cuboid;
+pub use cylinder::Cylinder;
+pub use cone::Cone;
+pub use sdf::{Sdf, SdfNode};
+pub use curve::{Curve, CurveKind};
+pub use subdivide::Subdivision;
@@ -0,0 +1,319 @@
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(PathBuf, std::io::Error),
+    // Line number and the face on it
+    BadIndex(usize, String),
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "can't read {}: {}", path.display(), err),
+            Self::BadIndex(line, face) => write!(f, "index out of range on line {}: {}", line, face),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+#[derive(Debug, Clone)]
+pub struct ObjMaterial<F: Float> {
+    pub kd: Vector3D<F>,
+    pub ke: Vector3D<F>,
+
+    pub map_kd: Option<PathBuf>,
+    pub map_ke: Option<PathBuf>,
+}
+
+impl<F: Float> ObjMaterial<F> {
+    fn new() -> Self {
+        Self {
+            kd: Vector3D::zero(),
+            ke: Vector3D::zero(),
+
+            map_kd: None,
+            map_ke: None,
+        }
+    }
+
+    pub fn emissive(&self) -> bool {
+        self.ke != Vector3D::zero() || self.map_ke.is_some()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjFace {
+    // Position and texcoord indices, counter-clockwise
+    pub vertices: Vec<(usize, Option<usize>)>,
+
+    pub material: Option<String>,
+}
+
+// Wavefront OBJ with the MTL libraries it references
+#[derive(Debug, Clone)]
+pub struct Obj<F: Float> {
+    pub positions: Vec<Vector3D<F>>,
+    pub texcoords: Vec<(F, F)>,
+
+    pub faces: Vec<ObjFace>,
+
+    pub materials: HashMap<String, ObjMaterial<F>>,
+}
+
+fn parse_floats<F: Float>(tokens: &[&str]) -> Vec<F> {
+    tokens.iter()
+        .filter_map(|t| t.parse::<f64>().ok())
+        .map(|v| F::from(v).unwrap())
+        .collect()
+}
+
+fn parse_vector<F: Float>(tokens: &[&str]) -> Vector3D<F> {
+    let v = parse_floats::<F>(tokens);
+    match v.len() {
+        0 => Vector3D::zero(),
+        1 | 2 => Vector3D::new(v[0], v[0], v[0]),
+        _ => Vector3D::new(v[0], v[1], v[2]),
+    }
+}
+
+// OBJ indices are 1-based, negative ones count from the end
+fn resolve_index(token: &str, len: usize) -> Option<usize> {
+    let i = token.parse::<i64>().ok()?;
+    if i > 0 && i as usize <= len {
+        Some(i as usize - 1)
+    } else if i < 0 && (-i) as usize <= len {
+        Some((len as i64 + i) as usize)
+    } else {
+        None
+    }
+}
+
+impl<F: Float> Obj<F> {
+    pub fn new(source: &str) -> Result<Self, ObjError> {
+        let obj_source = std::fs::read_to_string(source)
+            .map_err(|err| ObjError::Io(PathBuf::from(source), err))?;
+        let base_dir = Path::new(source).parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_default();
+
+        Self::parse(&obj_source, &base_dir)
+    }
+
+    // MTL libraries are looked up relative to base_dir
+    pub fn parse(obj_source: &str, base_dir: &Path) -> Result<Self, ObjError> {
+        let mut obj = Self {
+            positions: Vec::new(),
+            texcoords: Vec::new(),
+            faces: Vec::new(),
+            materials: HashMap::new(),
+        };
+
+        let mut material = None;
+        for (number, line) in obj_source.lines().enumerate() {
+            let tokens = line.split_whitespace().collect::<Vec<&str>>();
+            if tokens.is_empty() {
+                continue;
+            }
+
+            match tokens[0] {
+                "v" => obj.positions.push(parse_vector(&tokens[1..])),
+                "vt" => {
+                    let uv = parse_floats::<F>(&tokens[1..]);
+                    obj.texcoords.push((
+                        uv.first().copied().unwrap_or(F::zero()),
+                        uv.get(1).copied().unwrap_or(F::zero()),
+                    ));
+                }
+                "f" => {
+                    let bad_index = || ObjError::BadIndex(number + 1, line.to_string());
+
+                    let mut vertices = Vec::new();
+                    for token in &tokens[1..] {
+                        let mut parts = token.split('/');
+                        let position = parts.next()
+                            .and_then(|p| resolve_index(p, obj.positions.len()))
+                            .ok_or_else(bad_index)?;
+                        let texcoord = match parts.next() {
+                            Some(t) if !t.is_empty() => Some(
+                                resolve_index(t, obj.texcoords.len()).ok_or_else(bad_index)?
+                            ),
+                            _ => None, // Absent, as in 1//1
+                        };
+
+                        vertices.push((position, texcoord));
+                    }
+
+                    if vertices.len() >= 3 {
+                        obj.faces.push(ObjFace {
+                            vertices,
+                            material: material.clone(),
+                        });
+                    }
+                }
+                "usemtl" => material = tokens.get(1).map(|m| m.to_string()),
+                "mtllib" => {
+                    for library in &tokens[1..] {
+                        obj.load_mtl(base_dir, &base_dir.join(library))?;
+                    }
+                }
+                _ => {} // Normals, groups and smoothing are not used
+            }
+        }
+
+        Ok(obj)
+    }
+
+    fn load_mtl(&mut self, base_dir: &Path, path: &Path) -> Result<(), ObjError> {
+        let mtl_source = std::fs::read_to_string(path)
+            .map_err(|err| ObjError::Io(path.to_path_buf(), err))?;
+
+        let mut current: Option<String> = None;
+        for line in mtl_source.lines() {
+            let tokens = line.split_whitespace().collect::<Vec<&str>>();
+            if tokens.is_empty() {
+                continue;
+            }
+
+            if tokens[0] == "newmtl" {
+                let name = tokens.get(1).map(|m| m.to_string()).unwrap_or_default();
+                self.materials.insert(name.clone(), ObjMaterial::new());
+                current = Some(name);
+                continue;
+            }
+
+            let material = match current.as_ref().and_then(|name| self.materials.get_mut(name)) {
+                Some(material) => material,
+                None => continue,
+            };
+            match tokens[0] {
+                "Kd" => material.kd = parse_vector(&tokens[1..]),
+                "Ke" => material.ke = parse_vector(&tokens[1..]),
+                "map_Kd" => material.map_kd = tokens.last().map(|p| base_dir.join(p)),
+                "map_Ke" => {
+                    // The emission map is loaded with the mesh, so catch a bad path here
+                    if let Some(map) = tokens.last().map(|p| base_dir.join(p)) {
+                        std::fs::File::open(&map).map_err(|err| ObjError::Io(map.clone(), err))?;
+                        material.map_ke = Some(map);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::Mesh;
+
+    fn cornellbox(name: &str) -> String {
+        format!("{}/cornellbox/{}.obj", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    #[test]
+    fn parse_faces() {
+        let obj = Obj::<f64>::parse(
+            "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 1\nvn 0 0 1\n\
+             f 1/1 2/2 3/1 4/2\nf -4//1 -3//1 -2//1\n",
+            Path::new(""),
+        ).unwrap();
+
+        assert_eq!(obj.positions.len(), 4);
+        assert_eq!(obj.faces.len(), 2);
+        assert_eq!(obj.faces[0].vertices, vec![(0, Some(0)), (1, Some(1)), (2, Some(0)), (3, Some(1))]);
+        assert_eq!(obj.faces[1].vertices, vec![(0, None), (1, None), (2, None)]);
+
+        // The quad is split into a fan
+        let mesh = Mesh::from_obj(obj);
+        assert_eq!(mesh.triangles().len(), 3);
+        assert!((mesh.area() - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reject_out_of_range_index() {
+        for face in ["f 1 2 4", "f 0 1 2", "f -4 1 2", "f 1/2 2/1 3/1"] {
+            let source = format!("v 0 0 0\nv 1 0 0\nv 1 1 0\nvt 0 0\n{}\n", face);
+            match Obj::<f64>::parse(&source, Path::new("")) {
+                Err(ObjError::BadIndex(line, _)) => assert_eq!(line, 5),
+                other => panic!("{} parsed as {:?}", face, other),
+            }
+        }
+    }
+
+    #[test]
+    fn report_missing_mtl() {
+        let result = Obj::<f64>::parse("mtllib missing.mtl\n", Path::new("no/such/dir"));
+        assert!(matches!(result, Err(ObjError::Io(path, _)) if path.ends_with("missing.mtl")));
+    }
+
+    #[test]
+    fn report_missing_obj() {
+        assert!(matches!(Obj::<f64>::new(&cornellbox("missing")), Err(ObjError::Io(..))));
+    }
+
+    #[test]
+    fn load_mtl() {
+        let dir = std::env::temp_dir().join(format!("proton-obj-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lamp.mtl"), "newmtl glow\nKd 0.5 0.5 0.5\nKe 4 3 2\nmap_Kd wood.png\n").unwrap();
+        std::fs::write(dir.join("lamp.obj"), "mtllib lamp.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nusemtl glow\nf 1 2 3\n").unwrap();
+
+        let obj = Obj::<f64>::new(dir.join("lamp.obj").to_str().unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let glow = &obj.materials["glow"];
+        assert_eq!(glow.kd, Vector3D::new(0.5, 0.5, 0.5));
+        assert_eq!(glow.ke, Vector3D::new(4.0, 3.0, 2.0));
+        assert_eq!(glow.map_kd, Some(dir.join("wood.png")));
+        assert!(glow.emissive());
+
+        assert_eq!(obj.faces[0].material, None);
+        assert_eq!(obj.faces[1].material, Some("glow".to_string()));
+    }
+
+    #[test]
+    fn missing_emission_map() {
+        let dir = std::env::temp_dir().join(format!("proton-obj-map-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lamp.mtl"), "newmtl glow\nmap_Ke missing.png\n").unwrap();
+        std::fs::write(dir.join("lamp.obj"), "mtllib lamp.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl glow\nf 1 2 3\n").unwrap();
+
+        let result = Obj::<f64>::new(dir.join("lamp.obj").to_str().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(result, Err(ObjError::Io(path, _)) if path.ends_with("missing.png")));
+    }
+
+    #[test]
+    fn load_cornellbox() {
+        // Positions, faces and total area of each part of the box
+        let parts = [
+            ("floor", 12, 6, 922522.88),
+            ("left", 4, 2, 306904.514),
+            ("light", 4, 2, 13650.0),
+            ("right", 4, 2, 306888.96),
+            ("shortbox", 20, 10, 137348.910),
+            ("tallbox", 20, 10, 247030.444),
+        ];
+
+        for (name, positions, faces, area) in parts {
+            let obj = Obj::<f64>::new(&cornellbox(name)).unwrap();
+            assert_eq!(obj.positions.len(), positions, "{}", name);
+            assert_eq!(obj.faces.len(), faces, "{}", name);
+            assert!(obj.faces.iter().all(|face| face.vertices.len() == 3 && face.material.is_none()));
+
+            let mesh = Mesh::from_obj(obj);
+            assert_eq!(mesh.triangles().len(), faces);
+            assert!((mesh.area() - area).abs() < 1e-2, "{} has area {}", name, mesh.area());
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::objects::shape::{facing, frame, Shape};
+use crate::raytrace::{Incident, Ray};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Infinite, so it has no area to emit from
+#[derive(Debug, Clone, Copy)]
+pub struct Plane<F: Float> {
+    point: Vector3D<F>,
+    normal: Vector3D<F>,
+
+    frame: (Vector3D<F>, Vector3D<F>),
+}
+
+impl<F: Float> Plane<F> {
+    pub fn new(point: Vector3D<F>, normal: Vector3D<F>) -> Self {
+        let normal = normal.norm();
+
+        Self {
+            point,
+            normal,
+            frame: frame(normal),
+        }
+    }
+}
+
+impl<F: Float> Shape<F> for Plane<F> {
+    fn name(&self) -> String {
+        "plane".to_string()
+    }
+
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        let denom = ray.direction().dot(self.normal);
+        if denom == F::zero() { // Parallel
+            return None;
+        }
+
+        let t = (self.point - ray.origin()).dot(self.normal) / denom;
+        if t <= F::zero() {
+            return None;
+        }
+
+        let coords = ray.origin() + ray.direction() * t;
+        let offset = coords - self.point;
+
+        // In world units, textures repeat every unit
+        let uv = (offset.dot(self.frame.0), offset.dot(self.frame.1));
+
+        let (normal, back) = facing(ray, self.normal);
+        Some(
+            Incident::new(coords, normal, t, -ray.direction(), back)
+                .with_uv(uv)
+        )
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        (Vector3D::min_value(), Vector3D::max_value())
+    }
+
+    fn area(&self) -> F {
+        F::infinity()
+    }
+
+    // Density is zero everywhere
+    fn sample_location(&self) -> (Vector3D<F>, Vector3D<F>, (F, F)) {
+        (self.point, self.normal, (F::zero(), F::zero()))
+    }
+
+    fn normal_cone(&self) -> (Vector3D<F>, F) {
+        (self.normal, F::one())
+    }
+}
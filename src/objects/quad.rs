@@ -0,0 +1,91 @@
+use crate::objects::shape::{facing, Shape};
+use crate::raytrace::{Incident, Ray};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Parallelogram spanned by edge_u and edge_v from corner
+#[derive(Debug, Clone, Copy)]
+pub struct Quad<F: Float> {
+    corner: Vector3D<F>,
+    edge_u: Vector3D<F>,
+    edge_v: Vector3D<F>,
+
+    normal: Vector3D<F>,
+    // Projects an offset within the plane onto the edges
+    w: Vector3D<F>,
+}
+
+impl<F: Float> Quad<F> {
+    pub fn new(corner: Vector3D<F>, edge_u: Vector3D<F>, edge_v: Vector3D<F>) -> Self {
+        let n = edge_u.cross(edge_v);
+
+        Self {
+            corner,
+            edge_u,
+            edge_v,
+            normal: n.norm(),
+            w: n / n.dot(n),
+        }
+    }
+}
+
+impl<F: Float> Shape<F> for Quad<F> {
+    fn name(&self) -> String {
+        "quad".to_string()
+    }
+
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        let denom = ray.direction().dot(self.normal);
+        if denom == F::zero() { // Parallel
+            return None;
+        }
+
+        let t = (self.corner - ray.origin()).dot(self.normal) / denom;
+        if t <= F::zero() {
+            return None;
+        }
+
+        let coords = ray.origin() + ray.direction() * t;
+        let offset = coords - self.corner;
+        let u = self.w.dot(offset.cross(self.edge_v));
+        let v = self.w.dot(self.edge_u.cross(offset));
+        if u < F::zero() || u > F::one() || v < F::zero() || v > F::one() {
+            return None;
+        }
+
+        let (normal, back) = facing(ray, self.normal);
+        Some(
+            Incident::new(coords, normal, t, -ray.direction(), back)
+                .with_uv((u, v))
+        )
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        let corners = [
+            self.corner,
+            self.corner + self.edge_u,
+            self.corner + self.edge_v,
+            self.corner + self.edge_u + self.edge_v,
+        ];
+
+        corners.iter().fold(
+            (Vector3D::max_value(), Vector3D::min_value()),
+            |(min, max), &c| (min.min(c), max.max(c)),
+        )
+    }
+
+    fn area(&self) -> F {
+        self.edge_u.cross(self.edge_v).magnitude()
+    }
+
+    fn sample_location(&self) -> (Vector3D<F>, Vector3D<F>, (F, F)) {
+        let u = F::sample_rand();
+        let v = F::sample_rand();
+
+        (self.corner + self.edge_u * u + self.edge_v * v, self.normal, (u, v))
+    }
+
+    fn normal_cone(&self) -> (Vector3D<F>, F) {
+        (self.normal, F::one())
+    }
+}
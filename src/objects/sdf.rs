@@ -0,0 +1,289 @@
+use crate::objects::shape::{circle_bounds, facing, Shape};
+use crate::raytrace::{Incident, Ray};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Signed distance, negative inside. Operators only bound the true distance
+// from below, which is all sphere tracing needs
+pub enum SdfNode<F: Float> {
+    Sphere { center: Vector3D<F>, radius: F },
+    Cuboid { center: Vector3D<F>, half_extents: Vector3D<F> },
+    Torus { center: Vector3D<F>, axis: Vector3D<F>, major: F, minor: F },
+    Round(Box<SdfNode<F>>, F),
+    Union(Box<SdfNode<F>>, Box<SdfNode<F>>),
+    SmoothUnion(Box<SdfNode<F>>, Box<SdfNode<F>>, F),
+    Subtract(Box<SdfNode<F>>, Box<SdfNode<F>>),
+    // Copies every period along each axis, zero to not repeat along it
+    Repeat(Box<SdfNode<F>>, Vector3D<F>),
+}
+
+impl<F: Float> SdfNode<F> {
+    pub fn sphere(center: Vector3D<F>, radius: F) -> Self {
+        Self::Sphere { center, radius }
+    }
+
+    pub fn cuboid(center: Vector3D<F>, half_extents: Vector3D<F>) -> Self {
+        Self::Cuboid { center, half_extents }
+    }
+
+    // Ring of radius major around axis, with a tube of radius minor
+    pub fn torus(center: Vector3D<F>, axis: Vector3D<F>, major: F, minor: F) -> Self {
+        Self::Torus { center, axis: axis.norm(), major, minor }
+    }
+
+    // Grows the surface outward by radius, rounding its edges
+    pub fn round(self, radius: F) -> Self {
+        Self::Round(Box::new(self), radius)
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self::Union(Box::new(self), Box::new(other))
+    }
+
+    // Blends where the two are within k of each other
+    pub fn smooth_union(self, other: Self, k: F) -> Self {
+        Self::SmoothUnion(Box::new(self), Box::new(other), k)
+    }
+
+    pub fn subtract(self, other: Self) -> Self {
+        Self::Subtract(Box::new(self), Box::new(other))
+    }
+
+    // Meant for shapes around the origin that fit inside a single period
+    pub fn repeat(self, period: Vector3D<F>) -> Self {
+        Self::Repeat(Box::new(self), period)
+    }
+}
+
+impl<F: Float> SdfNode<F> {
+    pub fn distance(&self, p: Vector3D<F>) -> F {
+        match self {
+            Self::Sphere { center, radius } => (p - *center).magnitude() - *radius,
+            Self::Cuboid { center, half_extents } => {
+                let q = (p - *center).abs() - *half_extents;
+                let outside = q.max(Vector3D::zero()).magnitude();
+                let inside = q.x.max(q.y).max(q.z).min(F::zero());
+
+                outside + inside
+            }
+            Self::Torus { center, axis, major, minor } => {
+                let q = p - *center;
+                let height = q.dot(*axis);
+                let ring = (q - *axis * height).magnitude() - *major;
+
+                (ring * ring + height * height).sqrt() - *minor
+            }
+            Self::Round(node, radius) => node.distance(p) - *radius,
+            Self::Union(a, b) => a.distance(p).min(b.distance(p)),
+            Self::SmoothUnion(a, b, k) => {
+                let (a, b) = (a.distance(p), b.distance(p));
+                if *k <= F::zero() { // No blending, a plain union
+                    return a.min(b);
+                }
+                let h = (*k - (a - b).abs()).max(F::zero()) / *k;
+
+                a.min(b) - h * h * *k / F::from(4u32).unwrap()
+            }
+            Self::Subtract(a, b) => a.distance(p).max(-b.distance(p)),
+            Self::Repeat(node, period) => {
+                let wrap = |x: F, period: F| if period > F::zero() {
+                    x - period * (x / period).round()
+                } else {
+                    x
+                };
+
+                node.distance(Vector3D::new(
+                    wrap(p.x, period.x),
+                    wrap(p.y, period.y),
+                    wrap(p.z, period.z),
+                ))
+            }
+        }
+    }
+
+    pub fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        match self {
+            Self::Sphere { center, radius } => (*center - *radius, *center + *radius),
+            Self::Cuboid { center, half_extents } => (*center - *half_extents, *center + *half_extents),
+            Self::Torus { center, axis, major, minor } => {
+                let (min, max) = circle_bounds(*center, *axis, *major);
+
+                (min - *minor, max + *minor)
+            }
+            Self::Round(node, radius) => {
+                let (min, max) = node.bounds();
+
+                (min - *radius, max + *radius)
+            }
+            Self::Union(a, b) => {
+                let ((min_a, max_a), (min_b, max_b)) = (a.bounds(), b.bounds());
+
+                (min_a.min(min_b), max_a.max(max_b))
+            }
+            Self::SmoothUnion(a, b, k) => { // The blend swells by at most k / 4
+                let ((min_a, max_a), (min_b, max_b)) = (a.bounds(), b.bounds());
+                let swell = k.max(F::zero()) / F::from(4u32).unwrap();
+
+                (min_a.min(min_b) - swell, max_a.max(max_b) + swell)
+            }
+            Self::Subtract(a, _) => a.bounds(),
+            Self::Repeat(node, period) => {
+                let (min, max) = node.bounds();
+                let open = |bound: F, period: F, far: F| if period > F::zero() { far } else { bound };
+
+                (
+                    Vector3D::new(
+                        open(min.x, period.x, F::min_value()),
+                        open(min.y, period.y, F::min_value()),
+                        open(min.z, period.z, F::min_value()),
+                    ),
+                    Vector3D::new(
+                        open(max.x, period.x, F::max_value()),
+                        open(max.y, period.y, F::max_value()),
+                        open(max.z, period.z, F::max_value()),
+                    ),
+                )
+            }
+        }
+    }
+}
+
+// Implicit surface found by sphere tracing, with no area to emit from
+pub struct Sdf<F: Float> {
+    root: SdfNode<F>,
+
+    bounds: (Vector3D<F>, Vector3D<F>),
+
+    // Relative to how far from the world origin the ray is
+    precision: F,
+    max_steps: u32,
+}
+
+impl<F: Float> Sdf<F> {
+    pub fn new(root: SdfNode<F>) -> Self {
+        Self {
+            bounds: root.bounds(),
+            root,
+            precision: F::from(1e-5f32).unwrap(),
+            max_steps: 512,
+        }
+    }
+
+    pub fn with_precision(mut self, precision: F) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    // Grazing rays may need more to reach the surface
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    // Part of the ray inside the bounds
+    fn clip(&self, ray: &Ray<F>) -> Option<(F, F)> {
+        let origin = [ray.origin().x, ray.origin().y, ray.origin().z];
+        let direction = [ray.direction().x, ray.direction().y, ray.direction().z];
+        let (min, max) = self.bounds;
+        let min = [min.x, min.y, min.z];
+        let max = [max.x, max.y, max.z];
+
+        let mut t0 = F::zero();
+        let mut t1 = F::max_value();
+        for axis in 0..3 {
+            if direction[axis] == F::zero() {
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+
+            let inv = F::one() / direction[axis];
+            let near = (min[axis] - origin[axis]) * inv;
+            let far = (max[axis] - origin[axis]) * inv;
+            t0 = t0.max(near.min(far));
+            t1 = t1.min(near.max(far));
+        }
+
+        if t0 > t1 {
+            return None;
+        }
+
+        Some((t0, t1))
+    }
+
+    // Tetrahedron of samples around p, step is how far they are spread
+    fn gradient(&self, p: Vector3D<F>, step: F) -> Vector3D<F> {
+        let corners = [
+            Vector3D::new(F::one(), -F::one(), -F::one()),
+            Vector3D::new(-F::one(), -F::one(), F::one()),
+            Vector3D::new(-F::one(), F::one(), -F::one()),
+            Vector3D::new(F::one(), F::one(), F::one()),
+        ];
+
+        let mut gradient = Vector3D::zero();
+        for corner in corners {
+            gradient += corner * self.root.distance(p + corner * step);
+        }
+
+        gradient.norm()
+    }
+}
+
+impl<F: Float> Shape<F> for Sdf<F> {
+    fn name(&self) -> String {
+        "sdf".to_string()
+    }
+
+    // Starting inside marches to the surface just as well, the distance only flips sign
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        let (t0, t1) = self.clip(ray)?;
+
+        let o = ray.origin().abs();
+        let scale = o.x.max(o.y).max(o.z);
+
+        let mut t = t0;
+        for _ in 0..self.max_steps {
+            if t > t1 {
+                return None;
+            }
+
+            let p = ray.origin() + ray.direction() * t;
+            let distance = self.root.distance(p);
+            let tolerance = self.precision * (scale + t);
+            if distance.abs() > tolerance {
+                t = t + distance.abs();
+                continue;
+            }
+
+            let gradient = self.gradient(p, tolerance);
+            let coords = p - gradient * distance; // Onto the surface
+
+            let (normal, back) = facing(ray, gradient);
+            // Far enough that a ray leaving won't stop here again
+            let error = Vector3D::one() * (tolerance + tolerance);
+
+            return Some(
+                Incident::new(coords, normal, t, -ray.direction(), back)
+                    .with_error(error)
+            );
+        }
+
+        None
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        self.bounds
+    }
+
+    // Unknown, so it never acts as a light
+    fn area(&self) -> F {
+        F::infinity()
+    }
+
+    fn sample_location(&self) -> (Vector3D<F>, Vector3D<F>, (F, F)) {
+        let (min, max) = self.bounds;
+
+        ((min + max) / F::from(2u32).unwrap(), Vector3D::new(F::zero(), F::zero(), F::one()), (F::zero(), F::zero()))
+    }
+}
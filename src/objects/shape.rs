@@ -0,0 +1,79 @@
+use crate::raytrace::{Incident, Ray, to_world};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Analytic surface, hit from either side with the normal flipped toward the ray
+pub trait Shape<F: Float> {
+    fn name(&self) -> String;
+
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>>;
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>);
+
+    fn area(&self) -> F;
+
+    // Uniform over the area, with the outward normal and texture coordinates
+    fn sample_location(&self) -> (Vector3D<F>, Vector3D<F>, (F, F));
+
+    // Axis and cosine of the half-angle spanning all outward normals
+    fn normal_cone(&self) -> (Vector3D<F>, F) {
+        (Vector3D::new(F::zero(), F::zero(), F::one()), -F::one())
+    }
+}
+
+pub fn quadratic<F: Float>(a: F, b: F, c: F) -> Option<(F, F)> {
+    let _neg_half = F::from(-0.5).unwrap();
+
+    let discr: F = b * b - F::from(4).unwrap() * a * c;
+    if discr < F::zero() {
+        return None;
+    }
+    if discr == F::zero() {
+        let x = _neg_half * b / a;
+        return Some((x, x));
+    }
+
+    let q = if b > F::zero() {
+        _neg_half * (b + discr.sqrt())
+    } else {
+        _neg_half * (b - discr.sqrt())
+    };
+
+    let x0 = q / a;
+    let x1 = c / q;
+
+    Some((x0.min(x1), x0.max(x1)))
+}
+
+// Two unit vectors spanning the plane normal to axis
+pub(crate) fn frame<F: Float>(axis: Vector3D<F>) -> (Vector3D<F>, Vector3D<F>) {
+    (
+        to_world(Vector3D::new(F::one(), F::zero(), F::zero()), axis),
+        to_world(Vector3D::new(F::zero(), F::one(), F::zero()), axis),
+    )
+}
+
+// Angle of offset around the axis of frame, from zero to one
+pub(crate) fn azimuth<F: Float>(offset: Vector3D<F>, frame: (Vector3D<F>, Vector3D<F>)) -> F {
+    let _two = F::from(2u32).unwrap();
+
+    let phi = offset.dot(frame.1).atan2(offset.dot(frame.0));
+    let phi = if phi < F::zero() { phi + _two * F::PI() } else { phi };
+
+    phi / (_two * F::PI())
+}
+
+// Bounds of a circle, it reaches less far along the directions close to its axis
+pub(crate) fn circle_bounds<F: Float>(center: Vector3D<F>, axis: Vector3D<F>, radius: F) -> (Vector3D<F>, Vector3D<F>) {
+    let reach = |a: F| radius * (F::one() - a * a).max(F::zero()).sqrt();
+    let extent = Vector3D::new(reach(axis.x), reach(axis.y), reach(axis.z));
+
+    (center - extent, center + extent)
+}
+
+// The side a ray came from, and the normal flipped to face it
+pub(crate) fn facing<F: Float>(ray: &Ray<F>, normal: Vector3D<F>) -> (Vector3D<F>, bool) {
+    let back = ray.direction().dot(normal) > F::zero();
+
+    (if back { -normal } else { normal }, back)
+}
@@ -0,0 +1,379 @@
+use crate::objects::{Obj, ObjFace};
+use crate::raytrace::textures::Texture;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::collections::HashMap;
+
+// Refinement applied to a mesh when it is loaded, before its BVH is built
+pub struct Subdivision<F: Float> {
+    levels: u32,
+
+    // Texture and the distance its average channel moves vertices along the normal
+    displacement: Option<(Box<dyn Texture<F>>, F)>,
+}
+
+impl<F: Float> Subdivision<F> {
+    // Loop for all-triangle meshes, Catmull-Clark otherwise
+    pub fn new(levels: u32) -> Self {
+        Self {
+            levels,
+            displacement: None,
+        }
+    }
+
+    // Looked up at the texture coordinates of each vertex after subdividing
+    pub fn with_displacement(mut self, texture: Box<dyn Texture<F>>, scale: F) -> Self {
+        self.displacement = Some((texture, scale));
+        self
+    }
+
+    pub fn apply(&self, obj: &Obj<F>) -> Obj<F> {
+        let mut obj = weld(obj);
+        for _ in 0..self.levels {
+            obj = if obj.faces.iter().all(|face| face.vertices.len() == 3) {
+                loop_subdivide(&obj)
+            } else {
+                catmull_clark(&obj)
+            };
+        }
+
+        if let Some((texture, scale)) = &self.displacement {
+            displace(&mut obj, texture.as_ref(), *scale);
+        }
+
+        obj
+    }
+}
+
+// Faces often repeat their corners instead of sharing them, which would leave
+// every face a boundary and pull the mesh apart
+fn weld<F: Float>(obj: &Obj<F>) -> Obj<F> {
+    let bits = |p: Vector3D<F>| [p.x, p.y, p.z].map(|c| c.to_f64().unwrap().to_bits());
+
+    let mut ids = HashMap::new();
+    let mut positions = Vec::new();
+    let remap: Vec<usize> = obj.positions.iter().map(|&p| {
+        *ids.entry(bits(p)).or_insert_with(|| {
+            positions.push(p);
+            positions.len() - 1
+        })
+    }).collect();
+
+    let faces = obj.faces.iter().map(|face| ObjFace {
+        vertices: face.vertices.iter().map(|&(p, t)| (remap[p], t)).collect(),
+        material: face.material.clone(),
+    }).collect();
+
+    Obj {
+        positions,
+        texcoords: obj.texcoords.clone(),
+        faces,
+        materials: obj.materials.clone(),
+    }
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+// Faces around every edge and vertex. Edges with other than two faces are
+// boundaries, kept sharp
+struct Adjacency {
+    edge_faces: HashMap<(usize, usize), Vec<usize>>,
+    // Vertices sharing an edge, each once
+    neighbors: Vec<Vec<usize>>,
+    vertex_faces: Vec<Vec<usize>>,
+}
+
+impl Adjacency {
+    fn new<F: Float>(obj: &Obj<F>) -> Self {
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        let mut neighbors = vec![Vec::new(); obj.positions.len()];
+        let mut vertex_faces = vec![Vec::new(); obj.positions.len()];
+
+        for (f, face) in obj.faces.iter().enumerate() {
+            let n = face.vertices.len();
+            for i in 0..n {
+                let a = face.vertices[i].0;
+                let b = face.vertices[(i + 1) % n].0;
+
+                edge_faces.entry(edge_key(a, b)).or_default().push(f);
+                if !neighbors[a].contains(&b) {
+                    neighbors[a].push(b);
+                }
+                if !neighbors[b].contains(&a) {
+                    neighbors[b].push(a);
+                }
+                vertex_faces[a].push(f);
+            }
+        }
+
+        Self {
+            edge_faces,
+            neighbors,
+            vertex_faces,
+        }
+    }
+
+    // In a fixed order, so the same file always gives the same mesh
+    fn edges(&self) -> Vec<(usize, usize)> {
+        let mut edges: Vec<_> = self.edge_faces.keys().copied().collect();
+        edges.sort_unstable();
+        edges
+    }
+
+    fn boundary(&self, a: usize, b: usize) -> bool {
+        self.edge_faces.get(&edge_key(a, b)).is_none_or(|faces| faces.len() != 2)
+    }
+
+    fn boundary_neighbors(&self, v: usize) -> Vec<usize> {
+        self.neighbors[v].iter().copied().filter(|&n| self.boundary(v, n)).collect()
+    }
+}
+
+// On a boundary the vertices follow the cubic B-spline along it, corners stay put
+fn boundary_vertex<F: Float>(obj: &Obj<F>, v: usize, boundary: &[usize], weight: F) -> Vector3D<F> {
+    let p = obj.positions[v];
+    if boundary.len() != 2 {
+        return p;
+    }
+
+    let _half = F::from(0.5).unwrap();
+    p * (F::one() - weight) + (obj.positions[boundary[0]] + obj.positions[boundary[1]]) * (weight * _half)
+}
+
+// Texture coordinates of a face, if every corner has them
+fn face_texcoords<F: Float>(obj: &Obj<F>, face: &ObjFace) -> Option<Vec<(F, F)>> {
+    face.vertices.iter()
+        .map(|&(_, t)| t.map(|t| obj.texcoords[t]))
+        .collect()
+}
+
+fn mean_uv<F: Float>(uvs: &[(F, F)]) -> (F, F) {
+    let n = F::from(uvs.len()).unwrap();
+    let (u, v) = uvs.iter().fold((F::zero(), F::zero()), |(u, v), uv| (u + uv.0, v + uv.1));
+
+    (u / n, v / n)
+}
+
+fn catmull_clark<F: Float>(obj: &Obj<F>) -> Obj<F> {
+    let _two = F::from(2u32).unwrap();
+    let _three = F::from(3u32).unwrap();
+    let _four = F::from(4u32).unwrap();
+
+    let adjacency = Adjacency::new(obj);
+    let mean = |points: &mut dyn Iterator<Item = Vector3D<F>>| {
+        let (sum, n) = points.fold((Vector3D::zero(), 0usize), |(sum, n), p| (sum + p, n + 1));
+        sum / F::from(n.max(1)).unwrap()
+    };
+
+    let face_points: Vec<Vector3D<F>> = obj.faces.iter()
+        .map(|face| mean(&mut face.vertices.iter().map(|&(p, _)| obj.positions[p])))
+        .collect();
+
+    let mut positions = Vec::with_capacity(obj.positions.len() + obj.faces.len() * 2);
+    for v in 0..obj.positions.len() {
+        let p = obj.positions[v];
+        let boundary = adjacency.boundary_neighbors(v);
+        if !boundary.is_empty() {
+            positions.push(boundary_vertex(obj, v, &boundary, F::from(0.25).unwrap()));
+            continue;
+        }
+
+        if adjacency.neighbors[v].is_empty() { // Not part of any face
+            positions.push(p);
+            continue;
+        }
+        let n = F::from(adjacency.neighbors[v].len()).unwrap();
+        let faces = mean(&mut adjacency.vertex_faces[v].iter().map(|&f| face_points[f]));
+        let edges = mean(&mut adjacency.neighbors[v].iter().map(|&u| (p + obj.positions[u]) / _two));
+
+        positions.push((faces + edges * _two + p * (n - _three)) / n);
+    }
+
+    let mut edge_points = HashMap::new();
+    for (a, b) in adjacency.edges() {
+        let faces = &adjacency.edge_faces[&(a, b)];
+        let (pa, pb) = (obj.positions[a], obj.positions[b]);
+        let point = if faces.len() == 2 {
+            (pa + pb + face_points[faces[0]] + face_points[faces[1]]) / _four
+        } else {
+            (pa + pb) / _two
+        };
+
+        edge_points.insert((a, b), positions.len());
+        positions.push(point);
+    }
+
+    let mut texcoords = obj.texcoords.clone();
+    let mut faces = Vec::with_capacity(obj.faces.len() * 4);
+    for (f, face) in obj.faces.iter().enumerate() {
+        let center = positions.len();
+        positions.push(face_points[f]);
+
+        let n = face.vertices.len();
+        let uvs = face_texcoords(obj, face);
+        // Texcoords of the face center and of the middle of each side, not shared
+        // with neighbours so seams keep their own
+        let uv_ids = uvs.map(|uvs| {
+            let first = texcoords.len();
+            texcoords.push(mean_uv(&uvs));
+            for i in 0..n {
+                texcoords.push(mean_uv(&[uvs[i], uvs[(i + 1) % n]]));
+            }
+            first
+        });
+
+        for i in 0..n {
+            let prev = (i + n - 1) % n;
+            let (p, t) = face.vertices[i];
+            let edge = |j: usize| edge_points[&edge_key(face.vertices[j].0, face.vertices[(j + 1) % n].0)];
+
+            faces.push(ObjFace {
+                vertices: vec![
+                    (p, t),
+                    (edge(i), uv_ids.map(|first| first + 1 + i)),
+                    (center, uv_ids),
+                    (edge(prev), uv_ids.map(|first| first + 1 + prev)),
+                ],
+                material: face.material.clone(),
+            });
+        }
+    }
+
+    Obj {
+        positions,
+        texcoords,
+        faces,
+        materials: obj.materials.clone(),
+    }
+}
+
+fn loop_subdivide<F: Float>(obj: &Obj<F>) -> Obj<F> {
+    let _three = F::from(3u32).unwrap();
+    let _eight = F::from(8u32).unwrap();
+
+    let adjacency = Adjacency::new(obj);
+
+    let mut positions = Vec::with_capacity(obj.positions.len() + obj.faces.len() * 2);
+    for v in 0..obj.positions.len() {
+        let p = obj.positions[v];
+        let boundary = adjacency.boundary_neighbors(v);
+        if !boundary.is_empty() {
+            positions.push(boundary_vertex(obj, v, &boundary, F::from(0.25).unwrap()));
+            continue;
+        }
+
+        let neighbors = &adjacency.neighbors[v];
+        let n = neighbors.len();
+        if n == 0 {
+            positions.push(p);
+            continue;
+        }
+        // Warren's weights
+        let beta = if n == 3 {
+            _three / F::from(16u32).unwrap()
+        } else {
+            _three / (_eight * F::from(n).unwrap())
+        };
+        let sum = neighbors.iter().fold(Vector3D::zero(), |sum, &u| sum + obj.positions[u]);
+
+        positions.push(p * (F::one() - beta * F::from(n).unwrap()) + sum * beta);
+    }
+
+    let mut edge_points = HashMap::new();
+    for (a, b) in adjacency.edges() {
+        let faces = &adjacency.edge_faces[&(a, b)];
+        let (pa, pb) = (obj.positions[a], obj.positions[b]);
+        let point = if faces.len() == 2 {
+            // The corners across the edge in either triangle
+            let opposite = |f: usize| obj.faces[f].vertices.iter()
+                .map(|&(p, _)| p)
+                .find(|&p| p != a && p != b)
+                .unwrap_or(a);
+            let (c, d) = (obj.positions[opposite(faces[0])], obj.positions[opposite(faces[1])]);
+
+            (pa + pb) * (_three / _eight) + (c + d) / _eight
+        } else {
+            (pa + pb) / F::from(2u32).unwrap()
+        };
+
+        edge_points.insert((a, b), positions.len());
+        positions.push(point);
+    }
+
+    let mut texcoords = obj.texcoords.clone();
+    let mut faces = Vec::with_capacity(obj.faces.len() * 4);
+    for face in &obj.faces {
+        let uvs = face_texcoords(obj, face);
+        let uv_ids = uvs.map(|uvs| {
+            let first = texcoords.len();
+            for i in 0..3 {
+                texcoords.push(mean_uv(&[uvs[i], uvs[(i + 1) % 3]]));
+            }
+            first
+        });
+
+        let corner = |i: usize| face.vertices[i];
+        let edge = |i: usize| (
+            edge_points[&edge_key(face.vertices[i].0, face.vertices[(i + 1) % 3].0)],
+            uv_ids.map(|first| first + i),
+        );
+
+        for vertices in [
+            vec![corner(0), edge(0), edge(2)],
+            vec![corner(1), edge(1), edge(0)],
+            vec![corner(2), edge(2), edge(1)],
+            vec![edge(0), edge(1), edge(2)],
+        ] {
+            faces.push(ObjFace {
+                vertices,
+                material: face.material.clone(),
+            });
+        }
+    }
+
+    Obj {
+        positions,
+        texcoords,
+        faces,
+        materials: obj.materials.clone(),
+    }
+}
+
+// Moves every vertex along its area weighted normal. Where texture seams give
+// a vertex several texcoords the first face to use it decides
+fn displace<F: Float>(obj: &mut Obj<F>, texture: &dyn Texture<F>, scale: F) {
+    let mut normals = vec![Vector3D::zero(); obj.positions.len()];
+    let mut uvs = vec![None; obj.positions.len()];
+    for face in &obj.faces {
+        // Newell's method, fine for non-planar polygons too
+        let n = face.vertices.len();
+        let mut normal = Vector3D::zero();
+        for i in 0..n {
+            let a = obj.positions[face.vertices[i].0];
+            let b = obj.positions[face.vertices[(i + 1) % n].0];
+            normal += a.cross(b);
+        }
+
+        for &(p, t) in &face.vertices {
+            normals[p] += normal;
+            if uvs[p].is_none() {
+                uvs[p] = t.map(|t| obj.texcoords[t]);
+            }
+        }
+    }
+
+    let _third = F::from(3u32).unwrap().recip();
+    for (p, position) in obj.positions.iter_mut().enumerate() {
+        let normal = normals[p];
+        if normal.magnitude() <= F::zero() {
+            continue;
+        }
+
+        let uv = uvs[p].unwrap_or((F::zero(), F::zero()));
+        let value = texture.value(uv, *position);
+        *position += normal.norm() * ((value.x + value.y + value.z) * _third * scale);
+    }
+}
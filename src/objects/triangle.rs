@@ -1,4 +1,4 @@
-use crate::raytrace::{Incident, Ray, to_world};
+use crate::raytrace::{gamma, Incident, Ray, to_world};
 use crate::types::Float;
 use crate::vector::Vector3D;
 
@@ -8,8 +8,7 @@ pub struct Triangle<F: Float> {
     v1: Vector3D<F>,
     v2: Vector3D<F>,
 
-    e1: Vector3D<F>,
-    e2: Vector3D<F>,
+    uvs: [(F, F); 3],
 
     area: F,
 
@@ -35,8 +34,11 @@ impl<F: Float> Triangle<F> {
             v1,
             v2,
 
-            e1,
-            e2,
+            uvs: [
+                (F::zero(), F::zero()),
+                (F::one(), F::zero()),
+                (F::zero(), F::one()),
+            ],
 
             area,
 
@@ -44,6 +46,11 @@ impl<F: Float> Triangle<F> {
         }
     }
 
+    pub fn with_uvs(mut self, uv0: (F, F), uv1: (F, F), uv2: (F, F)) -> Self {
+        self.uvs = [uv0, uv1, uv2];
+        self
+    }
+
     pub fn vertices(&self) -> (Vector3D<F>, Vector3D<F>, Vector3D<F>) {
         (self.v0.clone(), self.v1.clone(), self.v2.clone())
     }
@@ -55,76 +62,144 @@ impl<F: Float> Triangle<F> {
     pub fn area(&self) -> F {
         self.area
     }
+
+    // Texture coordinates at barycentric (u, v), weights of v1 and v2
+    pub fn uv(&self, u: F, v: F) -> (F, F) {
+        let w = F::one() - u - v;
+        let [uv0, uv1, uv2] = self.uvs;
+
+        (
+            uv0.0 * w + uv1.0 * u + uv2.0 * v,
+            uv0.1 * w + uv1.1 * u + uv2.1 * v,
+        )
+    }
+}
+
+// Ray direction's largest axis last, so the shear never divides by a small number
+fn permutation<F: Float>(direction: Vector3D<F>) -> [usize; 3] {
+    let d = direction.abs();
+    let z = if d.x > d.y && d.x > d.z {
+        0
+    } else if d.y > d.z {
+        1
+    } else {
+        2
+    };
+
+    [(z + 1) % 3, (z + 2) % 3, z]
+}
+
+fn permute<F: Float>(v: Vector3D<F>, axes: [usize; 3]) -> Vector3D<F> {
+    let v = [v.x, v.y, v.z];
+
+    Vector3D::new(v[axes[0]], v[axes[1]], v[axes[2]])
+}
+
+// Twice the signed area of the sheared triangle (0, a, b) seen down the ray
+fn edge<F: Float>(a: Vector3D<F>, b: Vector3D<F>) -> F {
+    let e = a.x * b.y - a.y * b.x;
+    if e != F::zero() {
+        return e;
+    }
+
+    // Exactly on an edge in F, settle it in double so neighbours agree
+    let (ax, ay) = (a.x.to_f64().unwrap(), a.y.to_f64().unwrap());
+    let (bx, by) = (b.x.to_f64().unwrap(), b.y.to_f64().unwrap());
+    F::from(ax * by - ay * bx).unwrap()
 }
 
 impl<F: Float> Triangle<F> {
-    fn hit_impl(&self, ray: &Ray<F>, inv: bool) -> Option<Incident<F>> {
-        if ray.direction().dot(self.normal) > F::zero() { // Hit from inside
-            return None;
-        }
+    // Watertight, rays through a shared edge hit exactly one of its triangles.
+    // Back faces are hit too, with the normal flipped toward the ray
+    pub fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        let _two = F::from(2u32).unwrap();
+        let _three = F::from(3u32).unwrap();
 
-        let p_vec = ray.direction().cross(self.e2);
-        let det = self.e1.dot(p_vec);
-        let epsilon = F::from(0.01f32).unwrap();
-        if det.abs() < epsilon {
-            return None;
-        }
+        let axes = permutation(ray.direction());
+        let d = permute(ray.direction(), axes);
+        let shear = Vector3D::new(-d.x / d.z, -d.y / d.z, F::one() / d.z);
 
-        let det_inv: F = F::one() / det;
-        let t_vec = ray.origin() - self.v0;
-        let u = t_vec.dot(p_vec) * det_inv;
-        if u < F::zero() || u > F::one() {
+        // Ray from the origin down +z
+        let transform = |v: Vector3D<F>| {
+            let p = permute(v - ray.origin(), axes);
+            Vector3D::new(p.x + shear.x * p.z, p.y + shear.y * p.z, p.z)
+        };
+        let p0 = transform(self.v0);
+        let p1 = transform(self.v1);
+        let p2 = transform(self.v2);
+
+        let e0 = edge(p1, p2);
+        let e1 = edge(p2, p0);
+        let e2 = edge(p0, p1);
+        if (e0 < F::zero() || e1 < F::zero() || e2 < F::zero())
+            && (e0 > F::zero() || e1 > F::zero() || e2 > F::zero()) {
             return None;
         }
-
-        let q_vec = t_vec.cross(self.e1);
-        let v = ray.direction().dot(q_vec) * det_inv;
-        if v < F::zero() || u + v > F::one() {
+        let det = e0 + e1 + e2;
+        if det == F::zero() { // Edge on
             return None;
         }
 
-        let t_tmp = self.e2.dot(q_vec) * det_inv;
-        if t_tmp < F::zero() { // Never hitting
-            // println!("distance negative: {}", t_tmp.to_f64().unwrap());
+        let (z0, z1, z2) = (p0.z * shear.z, p1.z * shear.z, p2.z * shear.z);
+        let t_scaled = e0 * z0 + e1 * z1 + e2 * z2;
+        if (det < F::zero() && t_scaled >= F::zero()) || (det > F::zero() && t_scaled <= F::zero()) {
+            return None; // Behind the origin
+        }
+        let det_inv = F::one() / det;
+        let t = t_scaled * det_inv;
+
+        // Rounding in t, hits closer than that might be behind the origin
+        let max_x = p0.x.abs().max(p1.x.abs()).max(p2.x.abs());
+        let max_y = p0.y.abs().max(p1.y.abs()).max(p2.y.abs());
+        let max_z = z0.abs().max(z1.abs()).max(z2.abs());
+        let max_e = e0.abs().max(e1.abs()).max(e2.abs());
+        let delta_x = gamma::<F>(5) * (max_x + max_z);
+        let delta_y = gamma::<F>(5) * (max_y + max_z);
+        let delta_z = gamma::<F>(3) * max_z;
+        let delta_e = _two * (gamma::<F>(2) * max_x * max_y + delta_y * max_x + delta_x * max_y);
+        let delta_t = _three * (gamma::<F>(3) * max_e * max_z + delta_e * max_z + delta_z * max_e) * det_inv.abs();
+        if t <= delta_t {
             return None;
         }
 
+        let (b0, b1, b2) = (e0 * det_inv, e1 * det_inv, e2 * det_inv);
+        let coords = self.v0 * b0 + self.v1 * b1 + self.v2 * b2;
+        let error = ((self.v0 * b0).abs() + (self.v1 * b1).abs() + (self.v2 * b2).abs()) * gamma::<F>(7);
+
+        let back = ray.direction().dot(self.normal) > F::zero();
         Some(
             Incident::new(
-                ray.origin() + ray.direction() * t_tmp,
-                self.normal,
-                t_tmp,
+                coords,
+                if back { -self.normal } else { self.normal },
+                t,
                 -ray.direction(),
-                inv,
-            )
+                back,
+            ).with_uv(self.uv(b1, b2)).with_error(error)
         )
     }
 
-    pub fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
-        // TODO: Fix this!!! Somehow caused all my issues.
-        if ray.inside() { // Hit from inside
-            let inv_tri = Self::new(self.v0, self.v2, self.v1);
-            return inv_tri.hit_impl(ray, true);
-        }
+    pub fn sample_location(&self) -> (Vector3D<F>, F) { // normal is known
+        let (coords, _, pdf) = self.sample_location_uv();
 
-        self.hit_impl(ray, false)
+        (coords, pdf)
     }
 
-    pub fn sample_location(&self) -> (Vector3D<F>, F) { // normal is known
+    pub fn sample_location_uv(&self) -> (Vector3D<F>, (F, F), F) {
         let x = F::sample_rand().sqrt();
         let y = F::sample_rand();
 
-        let coords = self.v0 * (F::one() - x) + self.v1 * (x * (F::one() - y)) + self.v2 * (x * y);
+        let (u, v) = (x * (F::one() - y), x * y);
+        let coords = self.v0 * (F::one() - x) + self.v1 * u + self.v2 * v;
         let pdf = F::one() / self.area;
 
-        (coords, pdf)
+        (coords, self.uv(u, v), pdf)
     }
 
     pub fn sample_direction(&self) -> (Vector3D<F>, F) {
         let local_direction = {
             let x_1 = F::sample_rand();
             let x_2 = F::sample_rand();
-            let z = F::one().abs_sub(x_1 * F::from(2u32).unwrap());
+            let z = x_1; // Uniform over the hemisphere
             let r = (F::one() - z * z).sqrt();
             let phi: F = F::from(2u32).unwrap() * F::PI() * x_2;
 
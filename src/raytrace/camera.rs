@@ -0,0 +1,109 @@
+use crate::raytrace::Ray;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Pinhole at eye_pos looking down +z, with x flipped on screen
+#[derive(Debug, Clone, Copy)]
+pub struct Camera<F: Float> {
+    eye_pos: Vector3D<F>,
+
+    // Half height of the image plane at distance 1
+    scale: F,
+
+    width: u32,
+    height: u32,
+}
+
+impl<F: Float> Camera<F> {
+    pub fn new(eye_pos: Vector3D<F>, fov: u32, width: u32, height: u32) -> Self {
+        let fov = F::from(fov).unwrap();
+        let scale = (fov * F::from(0.5f32).unwrap()).to_radians().tan();
+
+        Self {
+            eye_pos,
+            scale,
+            width,
+            height,
+        }
+    }
+}
+
+impl<F: Float> Camera<F> {
+    pub fn eye_pos(&self) -> Vector3D<F> {
+        self.eye_pos
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn aspect_ratio(&self) -> F {
+        F::from(self.width).unwrap() / F::from(self.height).unwrap()
+    }
+
+    // Area of the image plane at distance 1
+    pub fn film_area(&self) -> F {
+        let _two = F::from(2u32).unwrap();
+
+        _two * self.aspect_ratio() * self.scale * _two * self.scale
+    }
+
+    // Ray through pixel (w, h), offset by (dx, dy) within it
+    pub fn ray(&self, w: u32, h: u32, dx: F, dy: F) -> Ray<F> {
+        let width = F::from(self.width).unwrap();
+        let height = F::from(self.height).unwrap();
+        let _two = F::from(2u32).unwrap();
+
+        let y = (F::one() - _two * (F::from(h).unwrap() + dy) / height) * self.scale;
+        let x = (_two * (F::from(w).unwrap() + dx) / width - F::one()) * self.aspect_ratio() * self.scale;
+
+        let lookat_pos: Vector3D<F> = Vector3D::new(
+            self.eye_pos.x - x,
+            self.eye_pos.y + y,
+            self.eye_pos.z + F::one());
+
+        Ray::new(self.eye_pos, lookat_pos - self.eye_pos)
+    }
+
+    // Pixel that coords is seen through, if any
+    pub fn raster(&self, coords: Vector3D<F>) -> Option<(u32, u32)> {
+        let offset = coords - self.eye_pos;
+        if offset.z <= F::zero() { // Behind the camera
+            return None;
+        }
+
+        let width = F::from(self.width).unwrap();
+        let height = F::from(self.height).unwrap();
+        let _half = F::from(0.5f32).unwrap();
+
+        let x = -offset.x / offset.z;
+        let y = offset.y / offset.z;
+        let w = (x / (self.aspect_ratio() * self.scale) + F::one()) * width * _half;
+        let h = (F::one() - y / self.scale) * height * _half;
+        if w < F::zero() || w >= width || h < F::zero() || h >= height {
+            return None;
+        }
+
+        Some((w.to_u32().unwrap(), h.to_u32().unwrap()))
+    }
+
+    // Solid angle density of ray picking direction, for a uniformly chosen pixel
+    pub fn pdf(&self, direction: Vector3D<F>) -> F {
+        let cos_theta = direction.z;
+        if cos_theta <= F::zero() {
+            return F::zero();
+        }
+
+        let x = direction.x / cos_theta;
+        let y = direction.y / cos_theta;
+        if x.abs() > self.aspect_ratio() * self.scale || y.abs() > self.scale {
+            return F::zero();
+        }
+
+        F::one() / (self.film_area() * cos_theta * cos_theta * cos_theta)
+    }
+}
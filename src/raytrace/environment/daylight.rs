@@ -0,0 +1,65 @@
+use crate::raytrace::environment::{Environment, Sky, Sun};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Analytic sky dome plus the matching solar disk
+#[derive(Debug, Clone, Copy)]
+pub struct Daylight<F: Float> {
+    sky: Sky<F>,
+    sun: Sun<F>,
+
+    scale: F,
+}
+
+impl<F: Float> Daylight<F> {
+    pub fn new(sun_direction: Vector3D<F>, turbidity: F, ground_albedo: Vector3D<F>) -> Self {
+        Self {
+            sky: Sky::new(sun_direction, turbidity, ground_albedo),
+            sun: Sun::new(sun_direction, turbidity),
+
+            scale: F::one(),
+        }
+    }
+
+    // Radiance is in kcd/m^2 by default, scale it down to scene units
+    pub fn scaled(mut self, scale: F) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+impl<F: Float> Daylight<F> {
+    fn sun_weight(&self) -> F {
+        if self.sun.visible() {
+            F::from(0.5).unwrap()
+        } else {
+            F::zero()
+        }
+    }
+}
+
+impl<F: Float> Environment<F> for Daylight<F> {
+    fn name(&self) -> String {
+        "daylight".to_string()
+    }
+
+    fn radiance(&self, direction: Vector3D<F>) -> Vector3D<F> {
+        (self.sky.radiance(direction) + self.sun.radiance(direction)) * self.scale
+    }
+
+    fn sample_direction(&self, normal: Vector3D<F>) -> (Vector3D<F>, F) {
+        let direction = if F::sample_rand() < self.sun_weight() {
+            self.sun.sample_direction(normal).0
+        } else {
+            self.sky.sample_direction(normal).0
+        };
+
+        (direction, self.pdf(direction, normal))
+    }
+
+    fn pdf(&self, direction: Vector3D<F>, normal: Vector3D<F>) -> F {
+        let w = self.sun_weight();
+
+        self.sun.pdf(direction, normal) * w + self.sky.pdf(direction, normal) * (F::one() - w)
+    }
+}
@@ -0,0 +1,55 @@
+mod sky;
+mod sun;
+mod daylight;
+
+pub use sky::Sky;
+pub use sun::Sun;
+pub use daylight::Daylight;
+
+use crate::raytrace::to_world;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Emitter at infinity, queried for every ray that leaves the scene
+pub trait Environment<F: Float> {
+    fn name(&self) -> String;
+
+    fn radiance(&self, direction: Vector3D<F>) -> Vector3D<F>;
+
+    fn sample_direction(&self, normal: Vector3D<F>) -> (Vector3D<F>, F);
+    fn pdf(&self, direction: Vector3D<F>, normal: Vector3D<F>) -> F;
+}
+
+fn sample_cosine<F: Float>(normal: Vector3D<F>) -> (Vector3D<F>, F) {
+    let x_1 = F::sample_rand();
+    let x_2 = F::sample_rand();
+
+    let r = x_1.sqrt();
+    let phi = F::from(2u32).unwrap() * F::PI() * x_2;
+    let z = (F::one() - x_1).sqrt();
+
+    let direction = to_world(Vector3D::new(r * phi.cos(), r * phi.sin(), z), normal);
+
+    (direction, z * F::FRAC_1_PI())
+}
+
+fn pdf_cosine<F: Float>(direction: Vector3D<F>, normal: Vector3D<F>) -> F {
+    direction.dot(normal).max(F::zero()) * F::FRAC_1_PI()
+}
+
+fn sample_cone<F: Float>(axis: Vector3D<F>, cos_max: F) -> (Vector3D<F>, F) {
+    let x_1 = F::sample_rand();
+    let x_2 = F::sample_rand();
+
+    let z = F::one() - x_1 * (F::one() - cos_max);
+    let r = (F::one() - z * z).max(F::zero()).sqrt();
+    let phi = F::from(2u32).unwrap() * F::PI() * x_2;
+
+    let direction = to_world(Vector3D::new(r * phi.cos(), r * phi.sin(), z), axis);
+
+    (direction, pdf_cone(cos_max))
+}
+
+fn pdf_cone<F: Float>(cos_max: F) -> F {
+    F::one() / (F::from(2u32).unwrap() * F::PI() * (F::one() - cos_max))
+}
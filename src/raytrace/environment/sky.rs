@@ -0,0 +1,202 @@
+use crate::color::{xyy_to_xyz, xyz_to_rgb};
+use crate::raytrace::environment::{Environment, Sun, pdf_cosine, sample_cosine};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Preetham, Shirley and Smits, "A Practical Analytic Model for Daylight", 1999.
+// Scene is y-up, radiance is expressed in kcd/m^2.
+#[derive(Debug, Clone, Copy)]
+pub struct Sky<F: Float> {
+    sun_direction: Vector3D<F>,
+
+    perez_x: Perez<F>,
+    perez_y: Perez<F>,
+    perez_lum: Perez<F>,
+
+    zenith_x: F,
+    zenith_y: F,
+    zenith_lum: F,
+
+    ground: Vector3D<F>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Perez<F: Float> {
+    a: F,
+    b: F,
+    c: F,
+    d: F,
+    e: F,
+}
+
+impl<F: Float> Perez<F> {
+    fn new(turbidity: F, coeffs: [(f64, f64); 5]) -> Self {
+        let c = |i: usize| F::from(coeffs[i].0).unwrap() * turbidity + F::from(coeffs[i].1).unwrap();
+
+        Self {
+            a: c(0),
+            b: c(1),
+            c: c(2),
+            d: c(3),
+            e: c(4),
+        }
+    }
+
+    fn eval(&self, cos_theta: F, gamma: F) -> F {
+        let cos_theta = cos_theta.max(F::from(1e-3).unwrap());
+        let cos_gamma = gamma.cos();
+
+        (F::one() + self.a * (self.b / cos_theta).exp())
+            * (F::one() + self.c * (self.d * gamma).exp() + self.e * cos_gamma * cos_gamma)
+    }
+}
+
+fn zenith_chromaticity<F: Float>(turbidity: F, theta_s: F, m: [[f64; 4]; 3]) -> F {
+    let t = [turbidity * turbidity, turbidity, F::one()];
+    let s = [theta_s * theta_s * theta_s, theta_s * theta_s, theta_s, F::one()];
+
+    let mut res = F::zero();
+    for (i, row) in m.iter().enumerate() {
+        for (j, v) in row.iter().enumerate() {
+            res = res + t[i] * F::from(*v).unwrap() * s[j];
+        }
+    }
+
+    res
+}
+
+impl<F: Float> Sky<F> {
+    pub fn new(sun_direction: Vector3D<F>, turbidity: F, ground_albedo: Vector3D<F>) -> Self {
+        let c = |v: f64| F::from(v).unwrap();
+
+        let sun_direction = sun_direction.norm();
+        let theta_s = sun_direction.y.max(F::zero()).min(F::one()).acos();
+
+        let perez_lum = Perez::new(turbidity, [
+            (0.1787, -1.4630),
+            (-0.3554, 0.4275),
+            (-0.0227, 5.3251),
+            (0.1206, -2.5771),
+            (-0.0670, 0.3703),
+        ]);
+        let perez_x = Perez::new(turbidity, [
+            (-0.0193, -0.2592),
+            (-0.0665, 0.0008),
+            (-0.0004, 0.2125),
+            (-0.0641, -0.8989),
+            (-0.0033, 0.0452),
+        ]);
+        let perez_y = Perez::new(turbidity, [
+            (-0.0167, -0.2608),
+            (-0.0950, 0.0092),
+            (-0.0079, 0.2102),
+            (-0.0441, -1.6537),
+            (-0.0109, 0.0529),
+        ]);
+
+        let chi = (c(4.0 / 9.0) - turbidity / c(120.0)) * (F::PI() - c(2.0) * theta_s);
+        let zenith_lum = ((c(4.0453) * turbidity - c(4.9710)) * chi.tan()
+            - c(0.2155) * turbidity + c(2.4192)).max(F::zero());
+        let zenith_x = zenith_chromaticity(turbidity, theta_s, [
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = zenith_chromaticity(turbidity, theta_s, [
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        // Perez functions are normalized against the zenith
+        let zenith_x = zenith_x / perez_x.eval(F::one(), theta_s);
+        let zenith_y = zenith_y / perez_y.eval(F::one(), theta_s);
+        let zenith_lum = zenith_lum / perez_lum.eval(F::one(), theta_s);
+
+        let mut sky = Self {
+            sun_direction,
+
+            perez_x,
+            perez_y,
+            perez_lum,
+
+            zenith_x,
+            zenith_y,
+            zenith_lum,
+
+            ground: Vector3D::zero(),
+        };
+
+        // Lambertian ground lit by the sky dome and the sun
+        let irradiance = sky.irradiance() + Sun::new(sun_direction, turbidity).irradiance();
+        sky.ground = ground_albedo * irradiance * F::FRAC_1_PI();
+
+        sky
+    }
+}
+
+impl<F: Float> Sky<F> {
+    pub fn sun_direction(&self) -> Vector3D<F> {
+        self.sun_direction
+    }
+
+    fn sky_radiance(&self, direction: Vector3D<F>) -> Vector3D<F> {
+        let cos_theta = direction.y;
+        let gamma = direction.dot(self.sun_direction).max(-F::one()).min(F::one()).acos();
+
+        let x = self.zenith_x * self.perez_x.eval(cos_theta, gamma);
+        let y = self.zenith_y * self.perez_y.eval(cos_theta, gamma);
+        let lum = self.zenith_lum * self.perez_lum.eval(cos_theta, gamma);
+
+        xyz_to_rgb(xyy_to_xyz(x, y, lum)).max(Vector3D::zero())
+    }
+
+    // Horizontal irradiance from the sky dome, midpoint rule
+    fn irradiance(&self) -> Vector3D<F> {
+        let theta_steps = 32;
+        let phi_steps = 64;
+
+        let d_theta = F::FRAC_PI_2() / F::from(theta_steps).unwrap();
+        let d_phi = F::from(2u32).unwrap() * F::PI() / F::from(phi_steps).unwrap();
+        let _half = F::from(0.5).unwrap();
+
+        let mut res = Vector3D::zero();
+        for i in 0..theta_steps {
+            let theta = (F::from(i).unwrap() + _half) * d_theta;
+            for j in 0..phi_steps {
+                let phi = (F::from(j).unwrap() + _half) * d_phi;
+                let direction = Vector3D::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+
+                res += self.sky_radiance(direction) * (theta.cos() * theta.sin() * d_theta * d_phi);
+            }
+        }
+
+        res
+    }
+}
+
+impl<F: Float> Environment<F> for Sky<F> {
+    fn name(&self) -> String {
+        "sky".to_string()
+    }
+
+    fn radiance(&self, direction: Vector3D<F>) -> Vector3D<F> {
+        if direction.y <= F::zero() {
+            return self.ground;
+        }
+
+        self.sky_radiance(direction)
+    }
+
+    fn sample_direction(&self, normal: Vector3D<F>) -> (Vector3D<F>, F) {
+        sample_cosine(normal)
+    }
+
+    fn pdf(&self, direction: Vector3D<F>, normal: Vector3D<F>) -> F {
+        pdf_cosine(direction, normal)
+    }
+}
@@ -0,0 +1,94 @@
+use crate::raytrace::environment::{Environment, pdf_cone, sample_cone};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Luminance of the sun outside the atmosphere, in kcd/m^2
+const SOLAR_LUMINANCE: f64 = 2.0e6;
+// Angular radius of the solar disk, in radians
+const SOLAR_RADIUS: f64 = 0.004653;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sun<F: Float> {
+    direction: Vector3D<F>,
+    cos_max: F,
+
+    radiance: Vector3D<F>,
+}
+
+impl<F: Float> Sun<F> {
+    pub fn new(direction: Vector3D<F>, turbidity: F) -> Self {
+        let direction = direction.norm();
+        let cos_max = F::from(SOLAR_RADIUS).unwrap().cos();
+
+        let radiance = if direction.y > F::zero() {
+            transmittance(direction.y.acos(), turbidity) * F::from(SOLAR_LUMINANCE).unwrap()
+        } else { // Below the horizon
+            Vector3D::zero()
+        };
+
+        Self {
+            direction,
+            cos_max,
+            radiance,
+        }
+    }
+}
+
+impl<F: Float> Sun<F> {
+    pub fn direction(&self) -> Vector3D<F> {
+        self.direction
+    }
+
+    pub fn visible(&self) -> bool {
+        self.direction.y > F::zero()
+    }
+
+    pub fn irradiance(&self) -> Vector3D<F> {
+        let solid_angle = F::from(2u32).unwrap() * F::PI() * (F::one() - self.cos_max);
+        self.radiance * solid_angle * self.direction.y.max(F::zero())
+    }
+}
+
+// Rayleigh and aerosol extinction along the optical path, per RGB channel
+fn transmittance<F: Float>(theta: F, turbidity: F) -> Vector3D<F> {
+    let c = |v: f64| F::from(v).unwrap();
+
+    let theta_deg = theta.to_degrees();
+    let mass = F::one() / (theta.cos() + c(0.15) * (c(93.885) - theta_deg).powf(c(-1.253)));
+    let beta = c(0.04608) * turbidity - c(0.04586);
+
+    let channel = |lambda: f64| {
+        let lambda = c(lambda); // micrometers
+        let rayleigh = (c(-0.008735) * lambda.powf(c(-4.08)) * mass).exp();
+        let aerosol = (-beta * lambda.powf(c(-1.3)) * mass).exp();
+        rayleigh * aerosol
+    };
+
+    Vector3D::new(channel(0.610), channel(0.550), channel(0.465))
+}
+
+impl<F: Float> Environment<F> for Sun<F> {
+    fn name(&self) -> String {
+        "sun".to_string()
+    }
+
+    fn radiance(&self, direction: Vector3D<F>) -> Vector3D<F> {
+        if direction.dot(self.direction) >= self.cos_max {
+            return self.radiance;
+        }
+
+        Vector3D::zero()
+    }
+
+    fn sample_direction(&self, _normal: Vector3D<F>) -> (Vector3D<F>, F) {
+        sample_cone(self.direction, self.cos_max)
+    }
+
+    fn pdf(&self, direction: Vector3D<F>, _normal: Vector3D<F>) -> F {
+        if direction.dot(self.direction) >= self.cos_max {
+            return pdf_cone(self.cos_max);
+        }
+
+        F::zero()
+    }
+}
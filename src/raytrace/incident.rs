@@ -1,4 +1,4 @@
-use crate::raytrace::Ray;
+use crate::raytrace::{gamma, DielectricStack, Ray};
 use crate::types::Float;
 use crate::vector::Vector3D;
 
@@ -11,7 +11,20 @@ pub struct Incident<F: Float> {
     w_i: Vector3D<F>,
     from_inside: bool,
 
+    // Bound on how far rounding may have put coords off the surface
+    error: Vector3D<F>,
+
+    uv: (F, F),
+    // Direction along a curve, zero on surfaces
+    tangent: Vector3D<F>,
+    primitive: usize,
+
     emit: Vector3D<F>,
+
+    // What the path was inside when it got here
+    stack: DielectricStack<F>,
+    // Of the path that got here, in nm
+    wavelength: Option<F>,
 }
 
 impl<F: Float> Incident<F> {
@@ -26,9 +39,46 @@ impl<F: Float> Incident<F> {
             distance,
             w_i,
             from_inside,
+            // Evaluated along the ray, shapes that know better override it
+            error: (coords.abs() + (w_i * distance).abs()) * gamma::<F>(7),
+            uv: (F::zero(), F::zero()),
+            tangent: Vector3D::zero(),
+            primitive: 0,
             emit: Vector3D::zero(),
+            stack: DielectricStack::new(),
+            wavelength: None,
         }
     }
+
+    pub fn with_uv(mut self, uv: (F, F)) -> Self {
+        self.uv = uv;
+        self
+    }
+
+    pub fn with_tangent(mut self, tangent: Vector3D<F>) -> Self {
+        self.tangent = tangent;
+        self
+    }
+
+    pub fn with_error(mut self, error: Vector3D<F>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn with_primitive(mut self, primitive: usize) -> Self {
+        self.primitive = primitive;
+        self
+    }
+
+    pub fn with_stack(mut self, stack: DielectricStack<F>) -> Self {
+        self.stack = stack;
+        self
+    }
+
+    pub fn with_wavelength(mut self, wavelength: Option<F>) -> Self {
+        self.wavelength = wavelength;
+        self
+    }
 }
 
 impl<F: Float> Incident<F> {
@@ -50,6 +100,60 @@ impl<F: Float> Incident<F> {
     pub fn inside(&self) -> bool {
         self.from_inside
     }
+
+    pub fn uv(&self) -> (F, F) {
+        self.uv
+    }
+
+    pub fn tangent(&self) -> Vector3D<F> {
+        self.tangent
+    }
+
+    pub fn error(&self) -> Vector3D<F> {
+        self.error
+    }
+
+    // Origin for a ray leaving toward w, just far enough off the surface
+    // along the normal that it can't hit it again
+    pub fn spawn_origin(&self, w: Vector3D<F>) -> Vector3D<F> {
+        let distance = self.normal.abs().dot(self.error);
+        let offset = if w.dot(self.normal) < F::zero() {
+            self.normal * -distance
+        } else {
+            self.normal * distance
+        };
+
+        // Adding the offset rounds too, so step a little further still
+        let away = |p: F, o: F| {
+            let step = p.abs().max(F::min_positive_value()) * F::epsilon();
+            if o > F::zero() {
+                p + step
+            } else if o < F::zero() {
+                p - step
+            } else {
+                p
+            }
+        };
+        let origin = self.coords + offset;
+
+        Vector3D::new(
+            away(origin.x, offset.x),
+            away(origin.y, offset.y),
+            away(origin.z, offset.z),
+        )
+    }
+
+    pub fn primitive(&self) -> usize {
+        self.primitive
+    }
+
+    pub fn stack(&self) -> DielectricStack<F> {
+        self.stack
+    }
+
+    pub fn wavelength(&self) -> Option<F> {
+        self.wavelength
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -67,6 +171,9 @@ pub struct BRDFIncident<F: Float> {
 pub struct RefractIncident<F: Float> {
     pub w_r: Vector3D<F>,
     pub flip: bool,
+
+    // Absorbed on the way through the inside, one for clear surfaces
+    pub attenuation: Vector3D<F>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -95,13 +202,18 @@ impl<F: Float> ProcessedIncident<F> {
         Vector3D::zero()
     }
 
+    // Sampled a single direction, f_r and pdf don't apply
+    pub fn specular(&self) -> bool {
+        matches!(self.interact, InteractIncident::Refract(_))
+    }
+
     pub fn multiplier(&self) -> Vector3D<F> {
         match self.interact {
             InteractIncident::Reflect(brdf) => {
                 brdf.multiplier
             }
-            InteractIncident::Refract(_) => {
-                Vector3D::one() // TODO: does russian roulette ensure this?
+            InteractIncident::Refract(refract) => {
+                refract.attenuation // TODO: does russian roulette ensure this?
             }
         }
     }
@@ -111,44 +223,26 @@ impl<F: Float> ProcessedIncident<F> {
             InteractIncident::Reflect(brdf) => {
                 brdf.rev_multiplier
             }
-            InteractIncident::Refract(_) => {
-                Vector3D::one() // TODO: does russian roulette ensure this?
+            InteractIncident::Refract(refract) => {
+                refract.attenuation // TODO: does russian roulette ensure this?
             }
         }
     }
 
     pub fn next_ray(&self) -> Ray<F> {
-        let epsilon = F::from(0.1).unwrap();
+        let (w_r, inside) = match self.interact {
+            InteractIncident::Reflect(brdf) => (brdf.w_r, self.inner.from_inside), // Same side
+            InteractIncident::Refract(refract) => (refract.w_r, self.inner.from_inside ^ refract.flip),
+        };
 
-        match self.interact {
-            InteractIncident::Reflect(brdf) => {
-                if self.inner.from_inside { // Still inside
-                    Ray::from_inside(
-                        self.inner.coords() + brdf.w_r * epsilon,
-                        brdf.w_r,
-                    )
-                } else { // Still outside
-                    Ray::new(
-                        self.inner.coords() + brdf.w_r * epsilon,
-                        brdf.w_r,
-                    )
-                }
-            }
-            InteractIncident::Refract(refract) => {
-                let still_inside = self.inner.from_inside ^ refract.flip;
-                if still_inside { // Flipped to inside
-                    Ray::from_inside(
-                        self.inner.coords() + refract.w_r * epsilon,
-                        refract.w_r,
-                    )
-                } else { // Still outside
-                    Ray::new(
-                        self.inner.coords() + refract.w_r * epsilon,
-                        refract.w_r,
-                    )
-                }
-            }
-        }
+        let origin = self.inner.spawn_origin(w_r);
+        let ray = if inside {
+            Ray::from_inside(origin, w_r)
+        } else {
+            Ray::new(origin, w_r)
+        };
+
+        ray.with_stack(self.inner.stack).with_wavelength(self.inner.wavelength)
     }
 }
 
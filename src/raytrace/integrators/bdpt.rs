@@ -0,0 +1,612 @@
+use crate::raytrace::{Incident, Ray};
+use crate::raytrace::integrators::{Integrator, SceneContext};
+use crate::raytrace::integrators::context::SHADOW_EPSILON;
+use crate::raytrace::objects::RayTraceable;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VertexKind {
+    Camera,
+    Light,
+    Surface,
+}
+
+// Which end a subpath was traced from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Walk {
+    Camera,
+    Light,
+}
+
+#[derive(Clone)]
+struct Vertex<F: Float> {
+    kind: VertexKind,
+
+    coords: Vector3D<F>,
+    normal: Vector3D<F>,
+
+    // Surfaces only, w_i points back along the subpath
+    incident: Option<Incident<F>>,
+    // Object hit, or the light itself
+    object: Option<Arc<dyn RayTraceable<F>>>,
+
+    beta: Vector3D<F>,
+    // The walk scattered specularly here, so no other strategy makes this path
+    delta: bool,
+    // Picked the lobe when the walk scattered here, connections reuse it
+    seed: F,
+
+    // Area densities of generating this vertex from either side
+    pdf_fwd: F,
+    pdf_rev: F,
+}
+
+fn remap0<F: Float>(v: F) -> F {
+    if v != F::zero() { v } else { F::one() }
+}
+
+impl<F: Float> Vertex<F> {
+    fn camera(eye_pos: Vector3D<F>) -> Self {
+        Self {
+            kind: VertexKind::Camera,
+            coords: eye_pos,
+            normal: Vector3D::zero(),
+            incident: None,
+            object: None,
+            beta: Vector3D::one(),
+            delta: false,
+            seed: F::zero(),
+            pdf_fwd: F::one(),
+            pdf_rev: F::zero(),
+        }
+    }
+
+    fn light(
+        light: Arc<dyn RayTraceable<F>>,
+        coords: Vector3D<F>,
+        normal: Vector3D<F>,
+        beta: Vector3D<F>,
+        pdf_fwd: F,
+    ) -> Self {
+        Self {
+            kind: VertexKind::Light,
+            coords,
+            normal,
+            incident: None,
+            object: Some(light),
+            beta,
+            delta: false,
+            seed: F::zero(),
+            pdf_fwd,
+            pdf_rev: F::zero(),
+        }
+    }
+
+    fn surface(object: Arc<dyn RayTraceable<F>>, incident: Incident<F>, beta: Vector3D<F>) -> Self {
+        Self {
+            kind: VertexKind::Surface,
+            coords: incident.coords(),
+            normal: incident.normal(),
+            incident: Some(incident),
+            object: Some(object),
+            beta,
+            delta: false,
+            seed: F::sample_rand(),
+            pdf_fwd: F::zero(),
+            pdf_rev: F::zero(),
+        }
+    }
+
+    fn delta_light(&self) -> bool {
+        self.kind == VertexKind::Light && self.object.as_ref().is_some_and(|light| light.delta())
+    }
+
+    // Has a non-specular part a connection can go through, a mix can be
+    // connectible at a vertex the walk left through its specular child
+    fn connectible(&self) -> bool {
+        match (&self.object, &self.incident) {
+            (Some(object), Some(incident)) => !object.focus_at(incident),
+            _ => true,
+        }
+    }
+
+    // Foreshortening toward w, points have none
+    fn cos(&self, w: Vector3D<F>) -> F {
+        if self.kind == VertexKind::Camera || self.delta_light() {
+            return F::one();
+        }
+
+        self.normal.dot(w).abs()
+    }
+
+    // Solid angle density from this vertex toward next, to area density at next
+    fn convert_density(&self, pdf: F, next: &Vertex<F>) -> F {
+        let offset = next.coords - self.coords;
+        let d_2 = offset.dot(offset);
+        if d_2 == F::zero() {
+            return F::zero();
+        }
+
+        pdf * next.cos(offset.norm()) / d_2
+    }
+
+    // Light source this vertex emits from, a single face for meshes
+    fn emitter(&self) -> Option<Arc<dyn RayTraceable<F>>> {
+        let object = self.object.as_ref()?;
+        if self.kind == VertexKind::Light {
+            return Some(object.clone());
+        }
+
+        let incident = self.incident.as_ref()?;
+        match object.emitter(incident) {
+            Some(emitter) => Some(emitter),
+            None if object.emit().is_some() => Some(object.clone()),
+            None => None,
+        }
+    }
+
+    fn emitted(&self) -> Vector3D<F> {
+        match (&self.object, &self.incident) {
+            (Some(object), Some(incident)) => object.emit_at(incident),
+            _ => Vector3D::zero(),
+        }
+    }
+
+    // BRDF from the previous vertex toward w_r, as the walk would have scattered it
+    fn f(&self, w_r: Vector3D<F>) -> Vector3D<F> {
+        match (&self.object, &self.incident) {
+            (Some(object), Some(incident)) => {
+                let pdf = object.pdf(incident, w_r);
+                object.interact_predetermined(*incident, w_r, pdf, self.seed).f_r()
+            }
+            _ => Vector3D::zero(),
+        }
+    }
+
+    // Area density of this vertex, reached from prev, sampling next
+    fn pdf(&self, context: &SceneContext<F>, prev: Option<&Vertex<F>>, next: &Vertex<F>) -> F {
+        if self.kind == VertexKind::Light {
+            return self.pdf_light(next);
+        }
+
+        let w_n = (next.coords - self.coords).norm();
+        let pdf = match self.kind {
+            VertexKind::Camera => context.camera().pdf(w_n),
+            _ => {
+                let (object, incident, prev) = match (&self.object, &self.incident, prev) {
+                    (Some(object), Some(incident), Some(prev)) => (object, incident, prev),
+                    _ => return F::zero(),
+                };
+                let w_p = (prev.coords - self.coords).norm();
+                let incident = Incident::new(
+                    incident.coords(),
+                    incident.normal(),
+                    incident.distance(),
+                    w_p,
+                    incident.inside(),
+                );
+
+                object.pdf(&incident, w_n)
+            }
+        };
+
+        self.convert_density(pdf, next)
+    }
+
+    // Area density at next of this vertex emitting toward it
+    fn pdf_light(&self, next: &Vertex<F>) -> F {
+        let light = match self.emitter() {
+            Some(light) => light,
+            None => return F::zero(),
+        };
+
+        let w_n = (next.coords - self.coords).norm();
+        let pdf = light.pdf_direction(self.coords, self.normal, w_n);
+
+        self.convert_density(pdf, next)
+    }
+
+    // Area density of a light subpath starting at this vertex
+    fn pdf_light_origin(&self, context: &SceneContext<F>) -> F {
+        let light = match self.emitter() {
+            Some(light) => light,
+            None => return F::zero(),
+        };
+
+        let position_pdf = if light.delta() { F::one() } else { F::one() / light.area() };
+
+        context.light_sampler().pdf(&light) * position_pdf
+    }
+}
+
+// Bidirectional path tracing, every camera subpath prefix is joined with
+// every light subpath prefix and the strategies are weighed with MIS
+pub struct Bdpt {
+    max_depth: usize,
+}
+
+impl Bdpt {
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+        }
+    }
+}
+
+impl<F: Float> Integrator<F> for Bdpt {
+    fn name(&self) -> String {
+        "bdpt".to_string()
+    }
+
+    fn radiance(&self, ray: &Ray<F>, context: &SceneContext<F>) -> Vector3D<F> {
+        let mut l_x: Vector3D<F> = Vector3D::zero();
+
+        let mut camera_path = vec![Vertex::camera(ray.origin())];
+        let camera_pdf = context.camera().pdf(ray.direction());
+        if let Some((beta, direction)) = random_walk(
+            context,
+            *ray,
+            Vector3D::one(),
+            camera_pdf,
+            self.max_depth,
+            Walk::Camera,
+            &mut camera_path,
+        ) { // The environment is only found by escaping
+            l_x += beta * context.environment_radiance(direction);
+        }
+
+        let light_path = light_subpath(context, self.max_depth, ray.wavelength());
+
+        for t in 1..=camera_path.len() {
+            for s in 0..=light_path.len() {
+                if s + t < 2 || s + t > self.max_depth + 2 {
+                    continue;
+                }
+                if s == 1 && t == 1 { // Lights seen directly are left to the camera subpath
+                    continue;
+                }
+
+                let (l_path, pixel) = connect(context, &light_path, &camera_path, s, t);
+                if l_path == Vector3D::zero() {
+                    continue;
+                }
+
+                match pixel {
+                    Some(pixel) => context.splat(pixel, l_path, ray.wavelength()),
+                    None => l_x += l_path,
+                }
+            }
+        }
+
+        l_x
+    }
+}
+
+// Traced at the wavelength of the camera subpath it is joined with
+fn light_subpath<F: Float>(context: &SceneContext<F>, max_depth: usize, wavelength: Option<F>) -> Vec<Vertex<F>> {
+    let mut light_path = Vec::new();
+
+    let (light, select_pdf) = match context.light_sampler().sample(F::sample_rand()) {
+        Some(sampled) => sampled,
+        None => return light_path,
+    };
+    let light_sample = light.sample_light();
+    let pdf_position = select_pdf * light_sample.position_pdf;
+    if pdf_position == F::zero() || light_sample.direction_pdf == F::zero() {
+        return light_path;
+    }
+
+    let ray = light_sample.ray;
+    let vertex = Vertex::light(
+        light,
+        ray.origin(),
+        light_sample.normal,
+        light_sample.emit / pdf_position,
+        pdf_position,
+    );
+    let beta = light_sample.emit * vertex.cos(ray.direction()) / (pdf_position * light_sample.direction_pdf);
+    light_path.push(vertex);
+
+    random_walk(
+        context,
+        light_sample.spawn_ray().with_wavelength(wavelength),
+        beta,
+        light_sample.direction_pdf,
+        max_depth,
+        Walk::Light,
+        &mut light_path,
+    );
+
+    light_path
+}
+
+// Extends path by up to max_depth bounces, returns the throughput and
+// direction of a ray that escaped the scene
+fn random_walk<F: Float>(
+    context: &SceneContext<F>,
+    ray: Ray<F>,
+    beta: Vector3D<F>,
+    pdf: F,
+    max_depth: usize,
+    walk: Walk,
+    path: &mut Vec<Vertex<F>>,
+) -> Option<(Vector3D<F>, Vector3D<F>)> {
+    let mut ray = ray;
+    let mut beta = beta;
+    let mut pdf_fwd = pdf;
+
+    for _ in 0..max_depth {
+        let (object, incident) = match context.intersect(&ray) {
+            Some(hit) => hit,
+            None => return Some((beta, ray.direction())),
+        };
+
+        let prev = path.len() - 1;
+        let mut vertex = Vertex::surface(object.clone(), incident, beta);
+        vertex.pdf_fwd = path[prev].convert_density(pdf_fwd, &vertex);
+        path.push(vertex);
+        let current = prev + 1;
+
+        let processed = object.interact(incident, path[current].seed);
+        let next_ray = processed.next_ray();
+
+        let pdf_rev = if processed.specular() {
+            path[current].delta = true;
+            pdf_fwd = F::zero();
+            F::zero()
+        } else {
+            let w_r = next_ray.direction();
+            let reversed = Incident::new(
+                incident.coords(),
+                incident.normal(),
+                incident.distance(),
+                w_r,
+                incident.inside(),
+            );
+            pdf_fwd = object.pdf(&incident, w_r);
+            object.pdf(&reversed, incident.w_i())
+        };
+        path[prev].pdf_rev = path[current].convert_density(pdf_rev, &path[prev]);
+
+        // Light paths carry flux, which scatters with the directions swapped
+        beta = match walk {
+            Walk::Camera => beta * processed.multiplier(),
+            Walk::Light => beta * processed.rev_multiplier(),
+        };
+        if beta == Vector3D::zero() {
+            break;
+        }
+
+        ray = next_ray;
+    }
+
+    None
+}
+
+// Contribution of joining s light vertices with t camera vertices, and the
+// pixel it lands on when it has to be splatted
+fn connect<F: Float>(
+    context: &SceneContext<F>,
+    light_path: &[Vertex<F>],
+    camera_path: &[Vertex<F>],
+    s: usize,
+    t: usize,
+) -> (Vector3D<F>, Option<(u32, u32)>) {
+    let none = (Vector3D::zero(), None);
+
+    let mut sampled = None;
+    let mut pixel = None;
+    let l_path = if s == 0 { // Camera subpath hit a light
+        let pt = &camera_path[t - 1];
+        pt.beta * pt.emitted()
+    } else if t == 1 { // Light subpath seen by the camera
+        let qs = &light_path[s - 1];
+        if !qs.connectible() || qs.kind != VertexKind::Surface {
+            return none;
+        }
+
+        let camera = context.camera();
+        let eye_pos = camera.eye_pos();
+        pixel = match camera.raster(qs.coords) {
+            Some(pixel) => Some(pixel),
+            None => return none,
+        };
+
+        let offset = eye_pos - qs.coords;
+        let w_c = offset.norm();
+        let cos_theta = -w_c.z;
+        let importance = F::one() / (camera.film_area() * cos_theta * cos_theta * cos_theta * offset.dot(offset));
+
+        let l_path = qs.beta * qs.f(w_c) * (qs.cos(w_c) * importance);
+        if l_path == Vector3D::zero() || !context.visible(qs.coords, eye_pos) {
+            return none;
+        }
+
+        sampled = Some(Vertex::camera(eye_pos));
+        l_path
+    } else if s == 1 { // Camera subpath joined to a fresh point on a light
+        let pt = &camera_path[t - 1];
+        if !pt.connectible() {
+            return none;
+        }
+
+        let (light, select_pdf) = match context.light_sampler().sample(F::sample_rand()) {
+            Some(sampled) => sampled,
+            None => return none,
+        };
+        let reference = match &pt.incident {
+            Some(incident) => incident,
+            None => return none,
+        };
+        let (vertex, emit) = match sample_light_vertex(context, light, select_pdf, reference) {
+            Some(sampled) => sampled,
+            None => return none,
+        };
+
+        let offset = vertex.coords - pt.coords;
+        let w_l = offset.norm();
+        let g = pt.cos(w_l) * vertex.cos(w_l) / offset.dot(offset);
+
+        let l_path = pt.beta * pt.f(w_l) * emit * (g / vertex.pdf_fwd);
+        sampled = Some(vertex);
+        l_path
+    } else {
+        let qs = &light_path[s - 1];
+        let pt = &camera_path[t - 1];
+        if !qs.connectible() || !pt.connectible() {
+            return none;
+        }
+
+        let offset = pt.coords - qs.coords;
+        let w = offset.norm();
+        let g = qs.cos(w) * pt.cos(w) / offset.dot(offset);
+
+        let l_path = qs.beta * qs.f(w) * pt.f(-w) * pt.beta * g;
+        if l_path == Vector3D::zero() || !context.visible(qs.coords, pt.coords) {
+            return none;
+        }
+
+        l_path
+    };
+    if l_path == Vector3D::zero() {
+        return none;
+    }
+
+    let weight = mis_weight(context, light_path, camera_path, sampled, s, t);
+
+    (l_path * weight, pixel)
+}
+
+// Point on light visible from reference, as a light vertex, with the radiance it sends there
+fn sample_light_vertex<F: Float>(
+    context: &SceneContext<F>,
+    light: Arc<dyn RayTraceable<F>>,
+    select_pdf: F,
+    reference: &Incident<F>,
+) -> Option<(Vertex<F>, Vector3D<F>)> {
+    let (coords, normal, position_pdf) = light.sample_position();
+
+    let offset = coords - reference.coords();
+    let distance = offset.magnitude();
+    let w_l = offset / distance;
+
+    if light.delta() {
+        if !context.visible(reference.coords(), coords) {
+            return None;
+        }
+
+        let light_incident = Incident::new(
+            coords,
+            -w_l,
+            distance,
+            -w_l, // Toward the receiver
+            false,
+        );
+        let emit = light.emit_at(&light_incident);
+
+        let vertex = Vertex::light(light, coords, normal, emit / select_pdf, select_pdf);
+        return Some((vertex, emit));
+    }
+
+    // Trace to the light to find the face and texture coordinates hit
+    let origin = reference.spawn_origin(w_l);
+    let ray = Ray::new(origin, w_l);
+
+    let distance = (coords - origin).magnitude();
+    let (next_object, next_incident) = context.intersect(&ray)?;
+    if (next_incident.coords() - coords).magnitude() >= distance * F::from(SHADOW_EPSILON).unwrap() { // Occluded
+        return None;
+    }
+
+    let emit = next_object.emit_at(&next_incident);
+    let pdf_fwd = select_pdf * position_pdf;
+
+    let vertex = Vertex::light(light, coords, next_incident.normal(), emit / pdf_fwd, pdf_fwd);
+    Some((vertex, emit))
+}
+
+fn mis_weight<F: Float>(
+    context: &SceneContext<F>,
+    light_path: &[Vertex<F>],
+    camera_path: &[Vertex<F>],
+    sampled: Option<Vertex<F>>,
+    s: usize,
+    t: usize,
+) -> F {
+    if s + t == 2 {
+        return F::one();
+    }
+
+    let mut light = light_path[..s].to_vec();
+    let mut camera = camera_path[..t].to_vec();
+    if let Some(sampled) = sampled {
+        if s == 1 {
+            light[0] = sampled;
+        } else if t == 1 {
+            camera[0] = sampled;
+        }
+    }
+
+    // Densities of reaching the connected vertices from the other side
+    let pt_pdf_rev = if s > 0 {
+        light[s - 1].pdf(context, if s > 1 { Some(&light[s - 2]) } else { None }, &camera[t - 1])
+    } else {
+        camera[t - 1].pdf_light_origin(context)
+    };
+    let pt_minus_pdf_rev = if t > 1 {
+        if s > 0 {
+            Some(camera[t - 1].pdf(context, Some(&light[s - 1]), &camera[t - 2]))
+        } else {
+            Some(camera[t - 1].pdf_light(&camera[t - 2]))
+        }
+    } else {
+        None
+    };
+    let qs_pdf_rev = if s > 0 {
+        Some(camera[t - 1].pdf(context, if t > 1 { Some(&camera[t - 2]) } else { None }, &light[s - 1]))
+    } else {
+        None
+    };
+    let qs_minus_pdf_rev = if s > 1 {
+        Some(light[s - 1].pdf(context, Some(&camera[t - 1]), &light[s - 2]))
+    } else {
+        None
+    };
+
+    camera[t - 1].pdf_rev = pt_pdf_rev;
+    if let Some(pdf_rev) = pt_minus_pdf_rev {
+        camera[t - 2].pdf_rev = pdf_rev;
+    }
+    if let Some(pdf_rev) = qs_pdf_rev {
+        light[s - 1].pdf_rev = pdf_rev;
+    }
+    if let Some(pdf_rev) = qs_minus_pdf_rev {
+        light[s - 2].pdf_rev = pdf_rev;
+    }
+
+    // Ratios of every other strategy that could have built this path
+    let mut sum_ri = F::zero();
+
+    let mut ri = F::one();
+    for i in (1..t).rev() {
+        ri = ri * remap0(camera[i].pdf_rev) / remap0(camera[i].pdf_fwd);
+        let skipped = s + t - i == 1 && i == 1; // Lights seen directly through the camera
+        if !camera[i].delta && !camera[i - 1].delta && !skipped {
+            sum_ri = sum_ri + ri;
+        }
+    }
+
+    let mut ri = F::one();
+    for i in (0..s).rev() {
+        ri = ri * remap0(light[i].pdf_rev) / remap0(light[i].pdf_fwd);
+        let delta_light = if i > 0 { light[i - 1].delta } else { light[0].delta_light() };
+        if !light[i].delta && !delta_light {
+            sum_ri = sum_ri + ri;
+        }
+    }
+
+    F::one() / (F::one() + sum_ri)
+}
@@ -0,0 +1,315 @@
+use std::sync::Arc;
+
+use crate::raytrace::{Incident, Ray, Scene, SceneGenerator};
+use crate::raytrace::integrators::context::crossed_medium;
+use crate::raytrace::lights::LightSampler;
+use crate::raytrace::media::Medium;
+use crate::raytrace::objects::RayTraceable;
+use crate::raytrace::spectral;
+use crate::raytrace::tree::{Photon, TheTree};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+fn sample_focus<F: Float>(
+    focuses: Vec<Arc<dyn RayTraceable<F>>>,
+    seed: F,
+) -> Arc<dyn RayTraceable<F>> {
+    let sample_count = focuses.len();
+    let mut chosen_sample = F::from(sample_count).unwrap() * seed;
+    for focus in focuses {
+        chosen_sample = chosen_sample - F::one();
+        if chosen_sample <= F::zero() { // The chosen one
+            return focus;
+        }
+    }
+
+    panic!("faulty seed or focus")
+}
+
+// Photons that reached a diffuse surface through a refractive one
+pub fn gen_photon_map<F: Float>(
+    rr: F,
+    photon_count: u32,
+    scene_gen: Arc<dyn SceneGenerator<F>>,
+    thread_count: u32,
+) -> TheTree<F> {
+    let (photons, _) = gen_map(rr, photon_count, scene_gen, thread_count, false);
+    photons
+}
+
+// Photons at every diffuse surface they reached, directly lit ones included,
+// and the map of every scattering in media
+pub fn gen_global_photon_map<F: Float>(
+    rr: F,
+    photon_count: u32,
+    scene_gen: Arc<dyn SceneGenerator<F>>,
+    thread_count: u32,
+) -> (TheTree<F>, TheTree<F>) {
+    gen_map(rr, photon_count, scene_gen, thread_count, true)
+}
+
+fn gen_map<F: Float>(
+    rr: F,
+    photon_count: u32,
+    scene_gen: Arc<dyn SceneGenerator<F>>,
+    thread_count: u32,
+    global: bool,
+) -> (TheTree<F>, TheTree<F>) {
+    let scene = scene_gen.gen_scene();
+
+    let light_sampler = LightSampler::new(&scene.objects);
+    println!("Total light power: {}", light_sampler.total_power().to_f64().unwrap());
+
+    let mut focuses = Vec::new();
+    for object in scene.objects.clone() {
+        if object.focus() { // Is light source
+            focuses.push(object);
+        }
+    }
+    println!("Total focus objects: {}", focuses.len());
+
+    if light_sampler.is_empty() || (focuses.is_empty() && !global) { // Nothing to cast caustics with
+        return (TheTree::new(Vec::new()), TheTree::new(Vec::new()));
+    }
+
+    let photon_per_thread = photon_count / thread_count;
+    let mut thread_handle_vec = Vec::new();
+
+    for _ in 0..thread_count {
+        let scene_gen = scene_gen.clone();
+
+        let handle = std::thread::spawn(move || {
+            let scene = scene_gen.gen_scene();
+
+            cast_thread(
+                rr,
+                scene,
+                photon_count,
+                photon_per_thread,
+                global,
+            )
+        });
+
+        thread_handle_vec.push(handle);
+    }
+
+    let mut photons: Vec<Photon<F>> = Vec::with_capacity(
+        photon_count as usize
+    );
+    let mut volume_photons: Vec<Photon<F>> = Vec::new();
+    for thread in thread_handle_vec {
+        let (mut _photons, mut _volume_photons) = thread.join().expect("general error");
+        photons.append(&mut _photons);
+        volume_photons.append(&mut _volume_photons);
+    }
+
+    println!("{} photons registered", photons.len());
+    if !volume_photons.is_empty() {
+        println!("{} volume photons registered", volume_photons.len());
+    }
+    if let Some(photon) = photons.first() {
+        println!("({}, {}, {})",
+                 photon.coords().x.to_f64().unwrap(),
+                 photon.coords().y.to_f64().unwrap(),
+                 photon.coords().z.to_f64().unwrap(),
+        );
+    }
+
+    (TheTree::new(photons), TheTree::new(volume_photons))
+}
+
+fn cast_thread<F: Float>(
+    rr: F,
+    scene: Scene<F>,
+    photon_count: u32,
+    photon_per_thread: u32,
+    global: bool,
+) -> (Vec<Photon<F>>, Vec<Photon<F>>) {
+    let light_sampler = LightSampler::new(&scene.objects);
+
+    let mut focuses = Vec::new();
+    for object in scene.objects.clone() {
+        if object.focus() { // Is light source
+            focuses.push(object);
+        }
+    }
+
+    let spectral = scene.spectral;
+    let cast_thread = CastThread {
+        rr,
+        global,
+        objects: scene.objects,
+        medium: scene.medium,
+    };
+
+    let mut photons: Vec<Photon<F>> = Vec::with_capacity(
+        photon_per_thread as usize
+    );
+    let mut volume_photons: Vec<Photon<F>> = Vec::new();
+    for _ in 0..photon_per_thread {
+        let seed = F::sample_rand();
+
+        let (lightsource, select_pdf) = match light_sampler.sample(seed) {
+            Some(sampled) => sampled,
+            None => break,
+        };
+        let light_sample = lightsource.sample_light();
+        let ray = light_sample.ray;
+        if !global { // Only photons aimed at a focus can become caustics
+            let focus = sample_focus(
+                focuses.clone(),
+                seed,
+            );
+            if !focus.partial_hit(&ray) {
+                continue;
+            }
+        }
+
+        let pdf = select_pdf * light_sample.position_pdf * light_sample.direction_pdf;
+        let normal = light_sample.normal;
+
+        let diff = light_sample.emit;
+
+        let diff = diff / pdf;
+        let diff = diff / F::from(photon_count).unwrap();
+        let diff = diff * ray.direction().dot(normal).abs();
+        // Each photon carries its own wavelength
+        let wavelength = if spectral { Some(spectral::sample_wavelength()) } else { None };
+        let diff = match wavelength {
+            Some(wavelength) => spectral::project(diff, wavelength),
+            None => diff,
+        };
+
+        let ray = light_sample.spawn_ray().with_wavelength(wavelength);
+        cast_thread.cast_ray(&ray, diff, &mut photons, &mut volume_photons);
+    }
+
+    (photons, volume_photons)
+}
+
+struct CastThread<F: Float> {
+    pub rr: F,
+    // Keep every diffuse hit, not just caustics
+    pub global: bool,
+
+    pub objects: Vec<Arc<dyn RayTraceable<F>>>,
+    // Outside every closed object
+    pub medium: Option<Arc<dyn Medium<F>>>,
+}
+
+impl<F: Float> CastThread<F> {
+    fn intersect(&self, ray: &Ray<F>) -> Option<(Arc<dyn RayTraceable<F>>, Incident<F>)> {
+        let mut min_distance = F::max_value();
+        let mut min_incident: Option<Incident<F>> = None;
+        let mut min_object: Option<Arc<dyn RayTraceable<F>>> = None;
+        for object in &self.objects {
+            if object.partial_hit(ray) {
+                if let Some(incident) = object.hit(ray) {
+                    // println!("hit object: {}, dist={}", object.name(), incident.distance().to_f64().unwrap());
+                    if incident.distance() < min_distance {
+                        min_distance = incident.distance();
+                        min_object = Some(object.clone());
+                        min_incident = Some(incident);
+                    }
+                }
+            }
+        }
+
+        let min_object = min_object?;
+        let min_incident = min_incident?.with_stack(ray.stack()).with_wavelength(ray.wavelength());
+
+        Some((min_object, min_incident))
+    }
+
+    fn cast_ray(
+        &self,
+        ray: &Ray<F>,
+        diff: Vector3D<F>,
+        photons: &mut Vec<Photon<F>>,
+        volume_photons: &mut Vec<Photon<F>>,
+    ) {
+        let mut ray = *ray;
+        let mut diff = diff;
+        let mut medium = self.medium.clone();
+        let mut prev_focus = false;
+
+        loop {
+            if !self.global && diff.magnitude() < F::from(0.1).unwrap() {
+                // Too small to be counted
+                return;
+            }
+
+            let hit = self.intersect(&ray);
+            if let Some(current) = medium.clone() {
+                let t_max = match &hit {
+                    Some((_, incident)) => incident.distance(),
+                    None => F::max_value(),
+                };
+                let sample = current.sample(&ray, t_max);
+                diff = diff * sample.weight;
+
+                if sample.scattered {
+                    let coords = ray.origin() + ray.direction() * sample.t;
+                    if self.global {
+                        volume_photons.push(Photon::new(
+                            coords,
+                            -ray.direction(), // Inverse of incoming direction
+                            diff,
+                        ));
+                    }
+
+                    let seed = F::sample_rand();
+                    if seed >= self.rr {
+                        return;
+                    }
+
+                    let (w_r, _) = current.phase().sample(-ray.direction());
+                    ray = if ray.inside() {
+                        Ray::from_inside(coords, w_r)
+                    } else {
+                        Ray::new(coords, w_r)
+                    }.with_stack(ray.stack()).with_wavelength(ray.wavelength());
+                    diff = diff * sample.sigma_s / self.rr;
+                    prev_focus = false;
+                    continue;
+                }
+            }
+
+            let (object, incident) = match hit {
+                Some(hit) => hit,
+                None => return,
+            };
+
+            if object.interface(&incident) { // Only the medium changes
+                let next_ray = object.interact(incident, F::sample_rand()).next_ray();
+                medium = crossed_medium(&object, &incident, &next_ray, medium, &self.medium);
+                ray = next_ray;
+                continue;
+            }
+
+            let photon = Photon::new(
+                incident.coords(),
+                incident.w_i(), // Inverse of incoming direction
+                diff, // Do not multiply f_r
+            );
+            if (prev_focus || self.global) && !object.focus_at(&incident) {
+                // Transitioned from refract to diffuse
+                photons.push(photon);
+            }
+
+            let seed = F::sample_rand();
+            if seed >= self.rr { // Might just stop
+                return;
+            }
+
+            let processed = object.interact(incident, seed / self.rr);
+            let next_ray = processed.next_ray();
+            medium = crossed_medium(&object, &incident, &next_ray, medium, &self.medium);
+
+            // Flux leaves along the sampled direction, so weigh by its cosine
+            diff = diff * processed.rev_multiplier() / self.rr;
+            prev_focus = object.focus_at(&incident);
+            ray = next_ray;
+        }
+    }
+}
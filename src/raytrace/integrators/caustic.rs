@@ -0,0 +1,212 @@
+use crate::raytrace::{Incident, ProcessedIncident, Ray, SceneGenerator};
+use crate::raytrace::integrators::{cast, Integrator, SceneContext};
+use crate::raytrace::objects::RayTraceable;
+use crate::raytrace::tree::TheTree;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::Arc;
+
+// Path tracing with a caustic photon map gathered at diffuse surfaces
+pub struct CausticPath<F: Float> {
+    rr: F,
+
+    photon_count: u32,
+    k: u32,
+    max_radius: F,
+
+    the_tree: TheTree<F>,
+}
+
+impl<F: Float> CausticPath<F> {
+    pub fn new(rr: F, photon_count: u32, k: u32, max_radius: F) -> Self {
+        Self {
+            rr,
+            photon_count,
+            k,
+            max_radius,
+            the_tree: TheTree::new(Vec::new()),
+        }
+    }
+}
+
+impl<F: Float> Integrator<F> for CausticPath<F> {
+    fn name(&self) -> String {
+        "caustic".to_string()
+    }
+
+    fn sample_clamp(&self) -> Option<F> {
+        Some(F::from(1.2f32).unwrap())
+    }
+
+    fn preprocess(&mut self, scene_gen: Arc<dyn SceneGenerator<F>>, thread_count: u32) {
+        let start = std::time::Instant::now();
+        self.the_tree = cast::gen_photon_map(
+            self.rr,
+            self.photon_count,
+            scene_gen,
+            thread_count,
+        );
+        let duration = start.elapsed();
+        println!("Time elapsed in gen_photon_map() is: {:?}", duration);
+    }
+
+    fn radiance(&self, ray: &Ray<F>, context: &SceneContext<F>) -> Vector3D<F> {
+        if let Some((object, incident)) = context.intersect(ray) {
+            return self.calc(context, object, incident);
+        }
+
+        context.environment_radiance(ray.direction())
+    }
+}
+
+impl<F: Float> CausticPath<F> {
+    fn calc_direct_brdf(
+        &self,
+        processed: &ProcessedIncident<F>,
+        next_object: Arc<dyn RayTraceable<F>>,
+        next_incident: &Incident<F>,
+    ) -> Vector3D<F> {
+        next_object.emit_at(next_incident) * processed.multiplier()
+    }
+
+    fn calc_environment_brdf(
+        &self,
+        context: &SceneContext<F>,
+        processed: &ProcessedIncident<F>,
+        next_ray: &Ray<F>,
+    ) -> Vector3D<F> {
+        context.environment_radiance(next_ray.direction()) * processed.multiplier()
+    }
+
+    fn calc_indirect(
+        &self,
+        context: &SceneContext<F>,
+        processed: &ProcessedIncident<F>,
+        next_object: Arc<dyn RayTraceable<F>>,
+        next_incident: Incident<F>,
+    ) -> Vector3D<F> {
+        processed.multiplier() * self.calc(context, next_object, next_incident)
+    }
+
+    fn calc_caustics(
+        &self,
+        object: Arc<dyn RayTraceable<F>>,
+        incident: &Incident<F>,
+        seed: F,
+    ) -> Vector3D<F> {
+        let coords = incident.coords();
+        // Do k-NN on the tree
+        if !self.the_tree.within_radius(coords, self.max_radius) {
+            return Vector3D::zero();
+        }
+
+        let (photons, r) = self.the_tree.knn(coords, self.k);
+        if r > self.max_radius {
+            return Vector3D::zero();
+        }
+
+        let mut l_x: Vector3D<F> = Vector3D::zero();
+
+        for photon in photons {
+            let diff = photon.diff();
+            if diff.magnitude() < F::from(0.1f32).unwrap() {
+                println!("diff incredibly small {}", diff.magnitude().to_f64().unwrap());
+            }
+
+            let incident = Incident::new(
+                photon.coords(),
+                incident.normal(),
+                F::zero(),
+                incident.w_i(),
+                false,
+            ).with_wavelength(incident.wavelength());
+            let pdf = F::PI() * r * r;
+            let processed = object.interact_predetermined(
+                incident,
+                photon.w_i(), // Outgoing
+                pdf,
+                seed);
+
+            let f_r = processed.f_r();
+            if f_r == Vector3D::zero() { // Somehow
+                continue; // Pass to next photon
+            }
+
+            // TODO: Can we use multiplier directly?
+            let local_irr = processed.multiplier() * diff;
+            l_x = local_irr + l_x;
+        }
+
+        l_x
+    }
+
+    fn calc(
+        &self,
+        context: &SceneContext<F>,
+        object: Arc<dyn RayTraceable<F>>,
+        incident: Incident<F>,
+    ) -> Vector3D<F> {
+        let diff = object.emit_at(&incident);
+        if diff != Vector3D::zero() {
+            return diff;
+        }
+
+        let seed = F::sample_rand();
+
+        let w_0 = F::from(0.7f32).unwrap();
+        let w_1 = F::from(0.3f32).unwrap();
+        let w_2 = F::from(0.5f32).unwrap();
+
+        let processed = object.interact(incident, seed);
+        let mut l_x: Vector3D<F> = Vector3D::zero();
+
+        l_x = l_x + context.direct_light(
+            object.clone(),
+            &incident,
+            seed,
+        ) * w_0;
+
+        l_x += context.direct_environment(
+            object.clone(),
+            &incident,
+            seed,
+        ) * w_0;
+
+        l_x = l_x + self.calc_caustics(
+            object.clone(),
+            &incident,
+            seed,
+        ) * w_2;
+
+        let next_ray = processed.next_ray();
+        if let Some((
+                        next_object,
+                        next_incident,
+                    )) = context.intersect(&next_ray) {
+            l_x = l_x + self.calc_direct_brdf(
+                &processed,
+                next_object.clone(),
+                &next_incident,
+            ) * w_1;
+
+            let _thresh = F::from(1.2f32).unwrap();
+            if l_x.x > _thresh || l_x.y > _thresh || l_x.z > _thresh {
+                return l_x;
+            }
+
+            if next_object.emit_at(&next_incident) == Vector3D::zero() && seed < self.rr {
+                let indirect = self.calc_indirect(context, &processed, next_object, next_incident);
+                l_x = l_x + (indirect / self.rr);
+            }
+        } else { // Escaped to the environment
+            l_x += self.calc_environment_brdf(
+                context,
+                &processed,
+                &next_ray,
+            ) * w_1;
+        }
+
+        l_x
+    }
+}
@@ -0,0 +1,519 @@
+use crate::raytrace::{Camera, Incident, Ray, Scene};
+use crate::raytrace::environment::Environment;
+use crate::raytrace::lights::{LightSampler, LightTree};
+use crate::raytrace::media::Medium;
+use crate::raytrace::objects::RayTraceable;
+use crate::raytrace::spectral;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::cell::RefCell;
+use std::sync::Arc;
+
+// Relative to the length of a shadow ray, how close to its end a hit still counts as the end
+pub(super) const SHADOW_EPSILON: f32 = 1e-4;
+
+// Light arriving at a shading point from a sampled direction
+#[derive(Debug, Clone, Copy)]
+pub struct DirectSample<F: Float> {
+    pub w_r: Vector3D<F>,
+    pub emit: Vector3D<F>,
+
+    // Per solid angle, including the choice of light
+    pub pdf: F,
+    pub delta: bool,
+}
+
+// Per-thread view of a scene, objects can't be shared between threads
+pub struct SceneContext<F: Float> {
+    objects: Vec<Arc<dyn RayTraceable<F>>>,
+    light_tree: LightTree<F>,
+    light_sampler: LightSampler<F>,
+    environment: Option<Arc<dyn Environment<F>>>,
+    medium: Option<Arc<dyn Medium<F>>>,
+    spectral: bool,
+
+    camera: Camera<F>,
+
+    // Radiance added to arbitrary pixels, allocated on the first splat
+    splats: RefCell<Vec<Vector3D<F>>>,
+}
+
+impl<F: Float> SceneContext<F> {
+    pub fn new(scene: Scene<F>, camera: Camera<F>) -> Self {
+        let light_tree = LightTree::new(&scene.objects);
+        let light_sampler = LightSampler::new(&scene.objects);
+
+        Self {
+            objects: scene.objects,
+            light_tree,
+            light_sampler,
+            environment: scene.environment,
+            medium: scene.medium,
+            spectral: scene.spectral,
+            camera,
+            splats: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl<F: Float> SceneContext<F> {
+    pub fn objects(&self) -> &[Arc<dyn RayTraceable<F>>] {
+        &self.objects
+    }
+
+    pub fn light_tree(&self) -> &LightTree<F> {
+        &self.light_tree
+    }
+
+    pub fn light_sampler(&self) -> &LightSampler<F> {
+        &self.light_sampler
+    }
+
+    pub fn camera(&self) -> &Camera<F> {
+        &self.camera
+    }
+
+    // Adds to a pixel as if one camera sample of it, at wavelength, had seen radiance
+    pub fn splat(&self, (w, h): (u32, u32), radiance: Vector3D<F>, wavelength: Option<F>) {
+        let mut splats = self.splats.borrow_mut();
+        if splats.is_empty() {
+            let pixel_count = (self.camera.width() * self.camera.height()) as usize;
+            splats.resize(pixel_count, Vector3D::zero());
+        }
+
+        let radiance = match wavelength {
+            Some(wavelength) => spectral::project(radiance, wavelength),
+            None => radiance,
+        };
+        splats[(w * self.camera.height() + h) as usize] += radiance;
+    }
+
+    // Splatted radiance per pixel, empty if nothing was splatted
+    pub fn take_splats(&self) -> Vec<Vector3D<F>> {
+        self.splats.take()
+    }
+
+    pub fn environment(&self) -> Option<&Arc<dyn Environment<F>>> {
+        self.environment.as_ref()
+    }
+
+    pub fn spectral(&self) -> bool {
+        self.spectral
+    }
+
+    // Medium outside every closed object
+    pub fn medium(&self) -> Option<&Arc<dyn Medium<F>>> {
+        self.medium.as_ref()
+    }
+
+    pub fn environment_radiance(&self, direction: Vector3D<F>) -> Vector3D<F> {
+        match &self.environment {
+            Some(environment) => environment.radiance(direction),
+            None => Vector3D::zero(),
+        }
+    }
+
+    pub fn intersect(&self, ray: &Ray<F>) -> Option<(Arc<dyn RayTraceable<F>>, Incident<F>)> {
+        let mut min_distance = F::max_value();
+        let mut min_incident: Option<Incident<F>> = None;
+        let mut min_object: Option<Arc<dyn RayTraceable<F>>> = None;
+        for object in &self.objects {
+            if object.partial_hit(ray) {
+                if let Some(incident) = object.hit(ray) {
+                    if incident.distance() < min_distance {
+                        min_distance = incident.distance();
+                        min_object = Some(object.clone());
+                        min_incident = Some(incident);
+                    }
+                }
+            }
+        }
+
+        let min_object = min_object?;
+        let min_incident = min_incident?.with_stack(ray.stack()).with_wavelength(ray.wavelength());
+
+        Some((min_object, min_incident))
+    }
+
+    // Whether nothing blocks the segment between two points
+    pub fn visible(&self, from: Vector3D<F>, to: Vector3D<F>) -> bool {
+        let offset = to - from;
+        let distance = offset.magnitude();
+        if distance == F::zero() {
+            return true;
+        }
+        let epsilon = distance * F::from(SHADOW_EPSILON).unwrap();
+
+        let w_r = offset / distance;
+        let ray = Ray::new(from + w_r * epsilon, w_r);
+        match self.intersect(&ray) {
+            Some((_, incident)) => incident.distance() >= distance - epsilon * F::from(2u32).unwrap(),
+            None => true,
+        }
+    }
+
+    // Medium a ray is in after crossing object, only refraction switches media
+    pub fn next_medium(
+        &self,
+        object: &Arc<dyn RayTraceable<F>>,
+        incident: &Incident<F>,
+        next_ray: &Ray<F>,
+        medium: Option<Arc<dyn Medium<F>>>,
+    ) -> Option<Arc<dyn Medium<F>>> {
+        crossed_medium(object, incident, next_ray, medium, &self.medium)
+    }
+
+    // Transmittance along ray for distance, through media and interfaces. None if
+    // anything else blocks it, otherwise also what was hit right at the end
+    #[allow(clippy::type_complexity)]
+    pub fn transmittance(
+        &self,
+        ray: Ray<F>,
+        distance: F,
+        medium: Option<Arc<dyn Medium<F>>>,
+    ) -> Option<(Vector3D<F>, Option<(Arc<dyn RayTraceable<F>>, Incident<F>)>)> {
+        let epsilon = distance * F::from(SHADOW_EPSILON).unwrap();
+
+        let mut transmittance: Vector3D<F> = Vector3D::one();
+        let mut ray = ray;
+        let mut distance = distance;
+        let mut medium = medium;
+        loop {
+            let hit = self.intersect(&ray);
+            let (object, incident) = match hit {
+                Some((object, incident)) if incident.distance() < distance - epsilon => (object, incident),
+                _ => {
+                    if let Some(medium) = &medium {
+                        transmittance = transmittance * medium.transmittance(&ray, distance);
+                    }
+                    // Whatever sits at the end, such as the light itself
+                    let end = hit.filter(|(_, incident)| incident.distance() < distance + epsilon);
+
+                    return Some((transmittance, end));
+                }
+            };
+            if !object.interface(&incident) { // Occluded
+                return None;
+            }
+
+            if let Some(medium) = &medium {
+                transmittance = transmittance * medium.transmittance(&ray, incident.distance());
+            }
+
+            let next_ray = object.interact(incident, F::sample_rand()).next_ray();
+            medium = self.next_medium(&object, &incident, &next_ray, medium);
+            distance = distance - incident.distance();
+            ray = next_ray;
+        }
+    }
+
+    // Light from one sampled light reflected off object, through media
+    pub fn surface_light(
+        &self,
+        object: &Arc<dyn RayTraceable<F>>,
+        incident: &Incident<F>,
+        inside: bool,
+        medium: Option<Arc<dyn Medium<F>>>,
+    ) -> Vector3D<F> {
+        let seed = F::sample_rand();
+
+        self.scattered_light(
+            incident.coords(),
+            |w_r| incident.spawn_origin(w_r),
+            inside,
+            medium,
+            |w_r| {
+                let processed = object.interact_predetermined(*incident, w_r, F::one(), seed);
+                processed.f_r() * w_r.dot(incident.normal()).abs()
+            },
+        )
+    }
+
+    // Light from one sampled light scattered at coords, already divided by its pdf.
+    // spawn gives where a shadow ray toward w_r starts, scatter the BRDF or phase
+    // function toward the light, cosine included
+    pub fn scattered_light(
+        &self,
+        coords: Vector3D<F>,
+        spawn: impl Fn(Vector3D<F>) -> Vector3D<F>,
+        inside: bool,
+        medium: Option<Arc<dyn Medium<F>>>,
+        scatter: impl Fn(Vector3D<F>) -> Vector3D<F>,
+    ) -> Vector3D<F> {
+        let (lightsource, select_pdf) = match self.light_sampler().sample(F::sample_rand()) {
+            Some(sampled) => sampled,
+            None => return Vector3D::zero(),
+        };
+        let (light_coords, _, pdf_area) = if lightsource.delta() {
+            lightsource.sample_position()
+        } else {
+            lightsource.sample_position_from(coords)
+        };
+
+        let offset = light_coords - coords;
+        let distance = offset.magnitude();
+        if distance == F::zero() {
+            return Vector3D::zero();
+        }
+        let w_r = offset / distance;
+        let epsilon = distance * F::from(SHADOW_EPSILON).unwrap();
+
+        let f = scatter(w_r);
+        if f == Vector3D::zero() {
+            return Vector3D::zero();
+        }
+
+        let origin = spawn(w_r);
+        let shadow_ray = if inside {
+            Ray::from_inside(origin, w_r)
+        } else {
+            Ray::new(origin, w_r)
+        };
+        let (transmittance, end) = match self.transmittance(
+            shadow_ray,
+            (light_coords - origin).magnitude(),
+            medium,
+        ) {
+            Some(unblocked) => unblocked,
+            None => return Vector3D::zero(),
+        };
+
+        if lightsource.delta() {
+            let light_incident = Incident::new(
+                light_coords,
+                -w_r,
+                distance,
+                -w_r, // Toward the receiver
+                false,
+            );
+            // Intensity falls off with the squared distance, there is no area to convert
+            let pdf = select_pdf * distance * distance;
+
+            return f * lightsource.emit_at(&light_incident) * transmittance / pdf;
+        }
+
+        // The light itself has to be what the shadow ray ends on
+        let (light_object, light_incident) = match end {
+            Some(end) if (end.1.coords() - light_coords).magnitude() < epsilon => end,
+            _ => return Vector3D::zero(),
+        };
+        let cos_light = w_r.dot(light_incident.normal()).abs();
+        if cos_light == F::zero() {
+            return Vector3D::zero();
+        }
+        let pdf = select_pdf * pdf_area * distance * distance / cos_light;
+
+        f * light_object.emit_at(&light_incident) * transmittance / pdf
+    }
+
+    // Radiance reflected from one sampled light, already divided by its pdf
+    pub fn direct_light(
+        &self,
+        object: Arc<dyn RayTraceable<F>>,
+        incident: &Incident<F>,
+        seed: F,
+    ) -> Vector3D<F> {
+        if object.focus_at(incident) { // Skip direct light on transparent object for now
+            return Vector3D::zero();
+        }
+        let sample = match self.sample_light(incident, seed) {
+            Some(sample) => sample,
+            None => return Vector3D::zero(),
+        };
+
+        let processed = object.interact_predetermined(
+            *incident,
+            sample.w_r, // Outgoing
+            sample.pdf,
+            seed);
+
+        sample.emit * processed.multiplier()
+    }
+
+    // Radiance reflected from a sampled environment direction, already divided by its pdf
+    pub fn direct_environment(
+        &self,
+        object: Arc<dyn RayTraceable<F>>,
+        incident: &Incident<F>,
+        seed: F,
+    ) -> Vector3D<F> {
+        if object.focus_at(incident) { // Skip direct light on transparent object for now
+            return Vector3D::zero();
+        }
+        let sample = match self.sample_environment(incident) {
+            Some(sample) => sample,
+            None => return Vector3D::zero(),
+        };
+
+        let processed = object.interact_predetermined(
+            *incident,
+            sample.w_r, // Outgoing
+            sample.pdf,
+            seed);
+
+        sample.emit * processed.multiplier()
+    }
+
+    // Picks a light and a point on it that is visible from incident
+    pub fn sample_light(&self, incident: &Incident<F>, seed: F) -> Option<DirectSample<F>> {
+        let (lightsource, select_pdf) = self.light_tree.sample(
+            incident.coords(),
+            incident.normal(),
+            seed,
+        )?; // None if no light faces this point
+        if lightsource.delta() {
+            return self.sample_delta(lightsource, select_pdf, incident);
+        }
+        let (coords, _, light_pdf_area) = lightsource.sample_position_from(incident.coords());
+
+        if coords == incident.coords() { // The light at the very point being lit
+            return None;
+        }
+        let w_r = (coords - incident.coords()).norm();
+        if w_r.dot(incident.normal()) < F::zero() {
+            return None;
+        }
+
+        let origin = incident.spawn_origin(w_r);
+        let light_ray = Ray::new(origin, w_r);
+
+        let distance = (coords - origin).magnitude();
+        let (next_object, next_incident) = self.intersect(&light_ray)?;
+        if (next_incident.coords() - coords).magnitude() >= distance * F::from(SHADOW_EPSILON).unwrap() { // Occluded
+            return None;
+        }
+
+        let x_diff = incident.coords() - coords;
+        let _cos = x_diff.norm().dot(next_incident.normal());
+
+        Some(DirectSample {
+            w_r,
+            emit: next_object.emit_at(&next_incident),
+            pdf: select_pdf * light_pdf_area * x_diff.dot(x_diff) / _cos,
+            delta: false,
+        })
+    }
+
+    fn sample_delta(
+        &self,
+        lightsource: Arc<dyn RayTraceable<F>>,
+        select_pdf: F,
+        incident: &Incident<F>,
+    ) -> Option<DirectSample<F>> {
+        let (coords, _, _) = lightsource.sample_position();
+
+        let x_diff = coords - incident.coords();
+        let distance = x_diff.magnitude();
+        let w_r = x_diff.norm();
+        if w_r.dot(incident.normal()) < F::zero() {
+            return None;
+        }
+
+        let origin = incident.spawn_origin(w_r);
+        let light_ray = Ray::new(origin, w_r);
+        let remaining = (coords - origin).magnitude();
+        if let Some((_, next_incident)) = self.intersect(&light_ray) {
+            if next_incident.distance() < remaining * (F::one() - F::from(SHADOW_EPSILON).unwrap()) { // Occluded
+                return None;
+            }
+        }
+
+        let light_incident = Incident::new(
+            coords,
+            -w_r,
+            distance,
+            -w_r, // Toward the receiver
+            false,
+        );
+
+        Some(DirectSample {
+            w_r,
+            emit: lightsource.emit_at(&light_incident),
+            // Intensity falls off with the squared distance, there is no area to convert
+            pdf: select_pdf * x_diff.dot(x_diff),
+            delta: true,
+        })
+    }
+
+    // Solid angle density of sample_light reaching light_incident on object from incident
+    pub fn light_pdf(
+        &self,
+        incident: &Incident<F>,
+        object: &Arc<dyn RayTraceable<F>>,
+        light_incident: &Incident<F>,
+    ) -> F {
+        // sample_light never looks behind the surface, even where light passes through
+        if (light_incident.coords() - incident.coords()).dot(incident.normal()) < F::zero() {
+            return F::zero();
+        }
+        let light = object.emitter(light_incident).unwrap_or_else(|| object.clone());
+
+        let select_pdf = self.light_tree.pdf(incident.coords(), incident.normal(), &light);
+        if select_pdf == F::zero() {
+            return F::zero();
+        }
+
+        let x_diff = incident.coords() - light_incident.coords();
+        let _cos = x_diff.norm().dot(light_incident.normal()).abs();
+        if _cos == F::zero() {
+            return F::zero();
+        }
+        let light_pdf_area = light.pdf_position_from(incident.coords(), light_incident.coords());
+
+        select_pdf * light_pdf_area * x_diff.dot(x_diff) / _cos
+    }
+
+    // Picks an unoccluded direction toward the environment
+    pub fn sample_environment(&self, incident: &Incident<F>) -> Option<DirectSample<F>> {
+        let environment = self.environment.as_ref()?;
+
+        let (w_r, env_pdf) = environment.sample_direction(incident.normal());
+        if env_pdf == F::zero() || w_r.dot(incident.normal()) <= F::zero() {
+            return None;
+        }
+
+        let env_ray = Ray::new(incident.spawn_origin(w_r), w_r);
+        if self.intersect(&env_ray).is_some() { // Occluded
+            return None;
+        }
+
+        Some(DirectSample {
+            w_r,
+            emit: environment.radiance(w_r),
+            pdf: env_pdf,
+            delta: false,
+        })
+    }
+
+    // Solid angle density of sample_environment picking direction from incident
+    pub fn environment_pdf(&self, incident: &Incident<F>, direction: Vector3D<F>) -> F {
+        if direction.dot(incident.normal()) <= F::zero() { // Behind, as sample_environment
+            return F::zero();
+        }
+
+        match &self.environment {
+            Some(environment) => environment.pdf(direction, incident.normal()),
+            None => F::zero(),
+        }
+    }
+}
+
+// Medium after object turned a ray into next_ray, outside is the one filling the scene
+pub(crate) fn crossed_medium<F: Float>(
+    object: &Arc<dyn RayTraceable<F>>,
+    incident: &Incident<F>,
+    next_ray: &Ray<F>,
+    medium: Option<Arc<dyn Medium<F>>>,
+    outside: &Option<Arc<dyn Medium<F>>>,
+) -> Option<Arc<dyn Medium<F>>> {
+    if next_ray.inside() == incident.inside() { // Stayed on the same side
+        return medium;
+    }
+
+    if next_ray.inside() {
+        object.medium(incident)
+    } else {
+        outside.clone()
+    }
+}
@@ -0,0 +1,57 @@
+use crate::raytrace::Ray;
+use crate::raytrace::integrators::{Integrator, SceneContext};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Direct lighting only, followed through chains of transparent surfaces
+pub struct DirectLighting {
+    max_depth: u32,
+}
+
+impl DirectLighting {
+    pub fn new(max_depth: u32) -> Self {
+        Self {
+            max_depth,
+        }
+    }
+}
+
+impl<F: Float> Integrator<F> for DirectLighting {
+    fn name(&self) -> String {
+        "direct".to_string()
+    }
+
+    fn radiance(&self, ray: &Ray<F>, context: &SceneContext<F>) -> Vector3D<F> {
+        let mut l_x: Vector3D<F> = Vector3D::zero();
+        let mut throughput: Vector3D<F> = Vector3D::one();
+        let mut ray = *ray;
+
+        for depth in 0..=self.max_depth {
+            let (object, incident) = match context.intersect(&ray) {
+                Some(hit) => hit,
+                None => {
+                    l_x += throughput * context.environment_radiance(ray.direction());
+                    break;
+                }
+            };
+
+            l_x += throughput * object.emit_at(&incident);
+
+            let seed = F::sample_rand();
+            if !object.focus_at(&incident) {
+                l_x += throughput * context.direct_light(object.clone(), &incident, seed);
+                l_x += throughput * context.direct_environment(object, &incident, seed);
+                break;
+            }
+            if depth == self.max_depth {
+                break;
+            }
+
+            let processed = object.interact(incident, seed);
+            throughput = throughput * processed.multiplier();
+            ray = processed.next_ray();
+        }
+
+        l_x
+    }
+}
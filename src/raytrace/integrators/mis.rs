@@ -0,0 +1,108 @@
+use crate::raytrace::{Incident, Ray};
+use crate::raytrace::integrators::{power_heuristic, Integrator, SceneContext};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Unbiased path tracing that samples lights at every diffuse vertex and
+// weighs them against BRDF sampling with the power heuristic
+pub struct MisPathTracer<F: Float> {
+    rr: F,
+}
+
+impl<F: Float> MisPathTracer<F> {
+    pub fn new(rr: F) -> Self {
+        Self {
+            rr,
+        }
+    }
+}
+
+impl<F: Float> Integrator<F> for MisPathTracer<F> {
+    fn name(&self) -> String {
+        "mis".to_string()
+    }
+
+    fn radiance(&self, ray: &Ray<F>, context: &SceneContext<F>) -> Vector3D<F> {
+        let mut l_x: Vector3D<F> = Vector3D::zero();
+        let mut throughput: Vector3D<F> = Vector3D::one();
+        let mut ray = *ray;
+
+        // Previous vertex and the pdf its BRDF picked the current ray with,
+        // None for camera rays and specular bounces where light sampling can't compete
+        let mut prev: Option<(Incident<F>, F)> = None;
+
+        loop {
+            let (object, incident) = match context.intersect(&ray) {
+                Some(hit) => hit,
+                None => { // Escaped to the environment
+                    let weight = match prev {
+                        Some((prev_incident, brdf_pdf)) => power_heuristic(
+                            brdf_pdf,
+                            context.environment_pdf(&prev_incident, ray.direction()),
+                        ),
+                        None => F::one(),
+                    };
+
+                    l_x += throughput * context.environment_radiance(ray.direction()) * weight;
+                    break;
+                }
+            };
+
+            let emit = object.emit_at(&incident);
+            if emit != Vector3D::zero() {
+                let weight = match prev {
+                    Some((prev_incident, brdf_pdf)) => power_heuristic(
+                        brdf_pdf,
+                        context.light_pdf(&prev_incident, &object, &incident),
+                    ),
+                    None => F::one(),
+                };
+
+                l_x += throughput * emit * weight;
+            }
+
+            if !object.focus_at(&incident) {
+                let seed = F::sample_rand();
+                if let Some(sample) = context.sample_light(&incident, seed) {
+                    let processed = object.interact_predetermined(incident, sample.w_r, sample.pdf, seed);
+                    let weight = if sample.delta {
+                        F::one()
+                    } else {
+                        power_heuristic(sample.pdf, object.pdf(&incident, sample.w_r))
+                    };
+
+                    l_x += throughput * sample.emit * processed.multiplier() * weight;
+                }
+
+                if let Some(sample) = context.sample_environment(&incident) {
+                    let processed = object.interact_predetermined(incident, sample.w_r, sample.pdf, seed);
+                    let weight = power_heuristic(sample.pdf, object.pdf(&incident, sample.w_r));
+
+                    l_x += throughput * sample.emit * processed.multiplier() * weight;
+                }
+            }
+
+            if F::sample_rand() >= self.rr {
+                break;
+            }
+
+            let processed = object.interact(incident, F::sample_rand());
+            let next_ray = processed.next_ray();
+
+            prev = if processed.specular() {
+                None
+            } else {
+                Some((incident, object.pdf(&incident, next_ray.direction())))
+            };
+
+            throughput = throughput * processed.multiplier() / self.rr;
+            if throughput == Vector3D::zero() {
+                break;
+            }
+
+            ray = next_ray;
+        }
+
+        l_x
+    }
+}
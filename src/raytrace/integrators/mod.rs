@@ -0,0 +1,136 @@
+use crate::raytrace::{Camera, Ray, SceneGenerator};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+mod cast;
+mod context;
+mod caustic;
+mod path;
+mod direct;
+mod mis;
+mod bdpt;
+mod sppm;
+mod photon;
+mod volpath;
+
+pub use context::{DirectSample, SceneContext};
+pub use caustic::CausticPath;
+pub use path::PathTracer;
+pub use direct::DirectLighting;
+pub use mis::MisPathTracer;
+pub use bdpt::Bdpt;
+pub use sppm::Sppm;
+pub use photon::PhotonMap;
+pub use volpath::VolumetricPathTracer;
+
+// Light transport algorithm, estimates the radiance arriving along a camera ray
+pub trait Integrator<F: Float>: Send + Sync {
+    fn name(&self) -> String;
+
+    // Scene-wide work done once before any pixel, such as shooting photons
+    fn preprocess(&mut self, scene_gen: Arc<dyn SceneGenerator<F>>, thread_count: u32) {
+        let _ = (scene_gen, thread_count);
+    }
+
+    fn radiance(&self, ray: &Ray<F>, context: &SceneContext<F>) -> Vector3D<F>;
+
+    // Brightest a single sample may be, biased but hides fireflies
+    fn sample_clamp(&self) -> Option<F> {
+        None
+    }
+
+    // Whole image at once for integrators refining per-pixel state over passes,
+    // linear radiance indexed by w * height + h. None renders sample by sample
+    fn render(
+        &self,
+        camera: Camera<F>,
+        scene_gen: Arc<dyn SceneGenerator<F>>,
+        thread_count: u32,
+    ) -> Option<Vec<Vector3D<F>>> {
+        let _ = (camera, scene_gen, thread_count);
+        None
+    }
+}
+
+// Weight for a sample drawn with pdf f_pdf that could also have come from g_pdf
+pub fn power_heuristic<F: Float>(f_pdf: F, g_pdf: F) -> F {
+    let f_2 = f_pdf * f_pdf;
+    let g_2 = g_pdf * g_pdf;
+    if f_2 + g_2 == F::zero() {
+        return F::zero();
+    }
+
+    f_2 / (f_2 + g_2)
+}
+
+// Every name from_name knows, sppm also takes a time budget as in sppm:60
+pub const NAMES: [&str; 9] = ["caustic", "path", "mis", "volpath", "direct", "bdpt", "photon", "gather", "sppm"];
+
+// Integrators with their default settings, by the name they report
+pub fn from_name<F: Float>(name: &str) -> Option<Box<dyn Integrator<F>>> {
+    let rr = F::from(0.8f32).unwrap();
+    let sppm = || Sppm::new(
+        64,
+        200000,
+        F::from(10u32).unwrap(),
+    );
+
+    if let Some(seconds) = name.strip_prefix("sppm:") { // Iterates for that long instead
+        let seconds = seconds.parse::<f64>().ok().filter(|s| s.is_finite() && *s > 0.0)?;
+        return Some(Box::new(sppm().with_time_budget(Duration::from_secs_f64(seconds))));
+    }
+
+    match name {
+        "caustic" => Some(Box::new(CausticPath::new(
+            rr,
+            10000000, // 10m photon, a portion wasted
+            8,
+            F::from(10u32).unwrap(),
+        ))),
+        "path" => Some(Box::new(PathTracer::new(rr))),
+        "mis" => Some(Box::new(MisPathTracer::new(rr))),
+        "volpath" => Some(Box::new(VolumetricPathTracer::new(rr))),
+        "direct" => Some(Box::new(DirectLighting::new(8))),
+        "bdpt" => Some(Box::new(Bdpt::new(8))),
+        "photon" => Some(Box::new(PhotonMap::new(
+            rr,
+            1000000,
+            64,
+            F::from(20u32).unwrap(),
+        ))),
+        "gather" => Some(Box::new(PhotonMap::new(
+            rr,
+            1000000,
+            64,
+            F::from(20u32).unwrap(),
+        ).with_final_gather(4, 1000000))),
+        "sppm" => Some(Box::new(sppm())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_name_resolves() {
+        for name in NAMES {
+            assert!(from_name::<f64>(name).is_some(), "{}", name);
+        }
+        assert!(from_name::<f64>("nope").is_none());
+    }
+
+    #[test]
+    fn sppm_time_budget() {
+        assert!(from_name::<f64>("sppm:90").is_some());
+        assert!(from_name::<f64>("sppm:0.5").is_some());
+
+        for name in ["sppm:", "sppm:0", "sppm:-3", "sppm:inf", "sppm:soon"] {
+            assert!(from_name::<f64>(name).is_none(), "{}", name);
+        }
+    }
+}
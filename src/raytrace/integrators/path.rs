@@ -0,0 +1,55 @@
+use crate::raytrace::Ray;
+use crate::raytrace::integrators::{Integrator, SceneContext};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Unbiased path tracing, light is only found by following sampled directions
+pub struct PathTracer<F: Float> {
+    rr: F,
+}
+
+impl<F: Float> PathTracer<F> {
+    pub fn new(rr: F) -> Self {
+        Self {
+            rr,
+        }
+    }
+}
+
+impl<F: Float> Integrator<F> for PathTracer<F> {
+    fn name(&self) -> String {
+        "path".to_string()
+    }
+
+    fn radiance(&self, ray: &Ray<F>, context: &SceneContext<F>) -> Vector3D<F> {
+        let mut l_x: Vector3D<F> = Vector3D::zero();
+        let mut throughput: Vector3D<F> = Vector3D::one();
+        let mut ray = *ray;
+
+        loop {
+            let (object, incident) = match context.intersect(&ray) {
+                Some(hit) => hit,
+                None => { // Escaped to the environment
+                    l_x += throughput * context.environment_radiance(ray.direction());
+                    break;
+                }
+            };
+
+            l_x += throughput * object.emit_at(&incident);
+
+            if F::sample_rand() >= self.rr {
+                break;
+            }
+
+            let processed = object.interact(incident, F::sample_rand());
+            throughput = throughput * processed.multiplier() / self.rr;
+            if throughput == Vector3D::zero() {
+                break;
+            }
+
+            ray = processed.next_ray();
+        }
+
+        l_x
+    }
+}
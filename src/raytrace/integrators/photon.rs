@@ -0,0 +1,252 @@
+use crate::raytrace::{Incident, Ray, SceneGenerator};
+use crate::raytrace::integrators::{cast, Integrator, SceneContext};
+use crate::raytrace::media::{HenyeyGreenstein, Medium};
+use crate::raytrace::objects::RayTraceable;
+use crate::raytrace::tree::TheTree;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::Arc;
+
+// Jensen style photon mapping with a global map of every diffuse hit. With
+// final gathering the map is only read one bounce away from the camera,
+// otherwise it is read where the camera ray lands, as a quick preview.
+// Media are always read from the map of volume photons
+pub struct PhotonMap<F: Float> {
+    rr: F,
+
+    photon_count: u32,
+    k: u32,
+    max_radius: F,
+
+    // Rays per shading point, zero reads the global map directly
+    gather_rays: u32,
+    caustic_count: u32,
+
+    global_tree: TheTree<F>,
+    caustic_tree: TheTree<F>,
+    volume_tree: TheTree<F>,
+
+    max_depth: usize,
+}
+
+impl<F: Float> PhotonMap<F> {
+    pub fn new(rr: F, photon_count: u32, k: u32, max_radius: F) -> Self {
+        Self {
+            rr,
+            photon_count,
+            k,
+            max_radius,
+            gather_rays: 0,
+            caustic_count: 0,
+            global_tree: TheTree::new(Vec::new()),
+            caustic_tree: TheTree::new(Vec::new()),
+            volume_tree: TheTree::new(Vec::new()),
+            max_depth: 8,
+        }
+    }
+
+    // Direct light is sampled and indirect diffuse gathered from the map
+    // through gather_rays bounces, caustics come from a separate map
+    pub fn with_final_gather(mut self, gather_rays: u32, caustic_count: u32) -> Self {
+        self.gather_rays = gather_rays;
+        self.caustic_count = caustic_count;
+        self
+    }
+}
+
+impl<F: Float> Integrator<F> for PhotonMap<F> {
+    fn name(&self) -> String {
+        if self.gather_rays > 0 {
+            return "gather".to_string();
+        }
+
+        "photon".to_string()
+    }
+
+    fn preprocess(&mut self, scene_gen: Arc<dyn SceneGenerator<F>>, thread_count: u32) {
+        let start = std::time::Instant::now();
+        (self.global_tree, self.volume_tree) = cast::gen_global_photon_map(
+            self.rr,
+            self.photon_count,
+            scene_gen.clone(),
+            thread_count,
+        );
+        if self.gather_rays > 0 && self.caustic_count > 0 {
+            self.caustic_tree = cast::gen_photon_map(
+                self.rr,
+                self.caustic_count,
+                scene_gen,
+                thread_count,
+            );
+        }
+        let duration = start.elapsed();
+        println!("Time elapsed in gen_photon_map() is: {:?}", duration);
+    }
+
+    fn radiance(&self, ray: &Ray<F>, context: &SceneContext<F>) -> Vector3D<F> {
+        self.trace(context, *ray, context.medium().cloned(), true)
+    }
+}
+
+impl<F: Float> PhotonMap<F> {
+    // Follows ray past refraction to the first diffuse surface or scattering
+    // in a medium. Camera rays shade that surface, gather rays read the map
+    fn trace(
+        &self,
+        context: &SceneContext<F>,
+        ray: Ray<F>,
+        medium: Option<Arc<dyn Medium<F>>>,
+        camera: bool,
+    ) -> Vector3D<F> {
+        let mut l_x: Vector3D<F> = Vector3D::zero();
+        let mut beta: Vector3D<F> = Vector3D::one();
+        let mut ray = ray;
+        let mut medium = medium;
+
+        for _ in 0..self.max_depth {
+            let hit = context.intersect(&ray);
+
+            if let Some(current) = medium.clone() {
+                let t_max = match &hit {
+                    Some((_, incident)) => incident.distance(),
+                    None => F::max_value(),
+                };
+                let sample = current.sample(&ray, t_max);
+                beta = beta * sample.weight;
+
+                if sample.scattered {
+                    let coords = ray.origin() + ray.direction() * sample.t;
+                    let in_scattered = self.estimate_volume(coords, -ray.direction(), current.phase());
+
+                    return l_x + beta * sample.sigma_s * in_scattered;
+                }
+            }
+
+            let (object, incident) = match hit {
+                Some(hit) => hit,
+                None => {
+                    if camera { // Otherwise counted as direct light
+                        l_x += beta * context.environment_radiance(ray.direction());
+                    }
+                    break;
+                }
+            };
+
+            if camera {
+                l_x += beta * object.emit_at(&incident);
+            }
+            if !object.focus_at(&incident) {
+                if camera {
+                    return l_x + beta * self.shade(context, object, &incident, medium);
+                }
+
+                return beta * self.estimate(&self.global_tree, object, &incident);
+            }
+
+            let processed = object.interact(incident, F::sample_rand());
+            let next_ray = processed.next_ray();
+            medium = context.next_medium(&object, &incident, &next_ray, medium);
+
+            beta = beta * processed.multiplier();
+            ray = next_ray;
+        }
+
+        l_x
+    }
+
+    // Reflected light at the first diffuse surface seen
+    fn shade(
+        &self,
+        context: &SceneContext<F>,
+        object: Arc<dyn RayTraceable<F>>,
+        incident: &Incident<F>,
+        medium: Option<Arc<dyn Medium<F>>>,
+    ) -> Vector3D<F> {
+        if self.gather_rays == 0 {
+            return self.estimate(&self.global_tree, object, incident);
+        }
+
+        let seed = F::sample_rand();
+        let mut l_x: Vector3D<F> = Vector3D::zero();
+
+        if medium.is_some() { // Attenuated on the way from the light
+            l_x += context.surface_light(&object, incident, incident.inside(), medium.clone());
+        } else {
+            l_x += context.direct_light(object.clone(), incident, seed);
+            l_x += context.direct_environment(object.clone(), incident, seed);
+        }
+        l_x += self.estimate(&self.caustic_tree, object.clone(), incident);
+
+        let _1_n = F::one() / F::from(self.gather_rays).unwrap();
+        for _ in 0..self.gather_rays {
+            let processed = object.interact(*incident, F::sample_rand());
+            let next_ray = processed.next_ray();
+            let gathered = self.trace(context, next_ray, medium.clone(), false);
+
+            l_x += processed.multiplier() * gathered * _1_n;
+        }
+
+        l_x
+    }
+
+    // In-scattered radiance toward w_i from the volume photons around coords
+    fn estimate_volume(
+        &self,
+        coords: Vector3D<F>,
+        w_i: Vector3D<F>,
+        phase: HenyeyGreenstein<F>,
+    ) -> Vector3D<F> {
+        let (photons, r) = self.volume_tree.knn(coords, self.k);
+        let (photons, r) = if r > self.max_radius {
+            (self.volume_tree.within(coords, self.max_radius), self.max_radius)
+        } else {
+            (photons, r)
+        };
+        if r == F::zero() {
+            return Vector3D::zero();
+        }
+
+        let mut l_x: Vector3D<F> = Vector3D::zero();
+        for photon in photons {
+            l_x += photon.diff() * phase.p(w_i, photon.w_i());
+        }
+
+        let volume = F::from(4u32).unwrap() / F::from(3u32).unwrap() * F::PI() * r * r * r;
+        l_x / volume
+    }
+
+    // Density estimate over the k nearest photons, or all within max_radius
+    fn estimate(
+        &self,
+        the_tree: &TheTree<F>,
+        object: Arc<dyn RayTraceable<F>>,
+        incident: &Incident<F>,
+    ) -> Vector3D<F> {
+        let coords = incident.coords();
+
+        let (photons, r) = the_tree.knn(coords, self.k);
+        let (photons, r) = if r > self.max_radius {
+            (the_tree.within(coords, self.max_radius), self.max_radius)
+        } else {
+            (photons, r)
+        };
+        if r == F::zero() {
+            return Vector3D::zero();
+        }
+
+        let mut l_x: Vector3D<F> = Vector3D::zero();
+        for photon in photons {
+            let processed = object.interact_predetermined(
+                *incident,
+                photon.w_i(), // Outgoing
+                F::one(),
+                F::sample_rand(),
+            );
+
+            l_x += processed.f_r() * photon.diff();
+        }
+
+        l_x / (F::PI() * r * r)
+    }
+}
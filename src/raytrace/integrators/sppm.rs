@@ -0,0 +1,511 @@
+use crate::raytrace::{Camera, Incident, Ray, SceneGenerator};
+use crate::raytrace::integrators::{Integrator, SceneContext};
+use crate::raytrace::objects::RayTraceable;
+use crate::raytrace::spectral;
+use crate::raytrace::tree::{Photon, TheTree};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// Stochastic progressive photon mapping, every iteration traces new visible
+// points and a new photon pass, and shrinks the gather radius of each pixel
+pub struct Sppm<F: Float> {
+    iterations: u32,
+    time_budget: Option<Duration>,
+
+    // Per iteration
+    photon_count: u32,
+    initial_radius: F,
+    // Fraction of new photons kept when shrinking the radius
+    alpha: F,
+
+    max_depth: usize,
+}
+
+impl<F: Float> Sppm<F> {
+    pub fn new(iterations: u32, photon_count: u32, initial_radius: F) -> Self {
+        Self {
+            iterations,
+            time_budget: None,
+            photon_count,
+            initial_radius,
+            alpha: F::from(2f32 / 3f32).unwrap(),
+            max_depth: 8,
+        }
+    }
+
+    // Runs iterations until the budget is spent instead of a fixed count
+    pub fn with_time_budget(mut self, time_budget: Duration) -> Self {
+        self.time_budget = Some(time_budget);
+        self
+    }
+}
+
+// First non-specular surface seen through a pixel
+struct VisiblePoint<F: Float> {
+    object: Arc<dyn RayTraceable<F>>,
+    incident: Incident<F>,
+    beta: Vector3D<F>,
+}
+
+struct SppmPixel<F: Float> {
+    // Emitted and direct light, summed over iterations
+    ld: Vector3D<F>,
+
+    visible: Option<VisiblePoint<F>>,
+    radius: F,
+    n: F,
+    tau: Vector3D<F>,
+
+    // Photons gathered in the current iteration
+    phi: Vector3D<F>,
+    m: u32,
+}
+
+impl<F: Float> SppmPixel<F> {
+    fn new(radius: F) -> Self {
+        Self {
+            ld: Vector3D::zero(),
+            visible: None,
+            radius,
+            n: F::zero(),
+            tau: Vector3D::zero(),
+            phi: Vector3D::zero(),
+            m: 0,
+        }
+    }
+}
+
+// Barrier a panicking thread breaks on its way out, so the others stop
+// instead of waiting for it forever
+struct Rendezvous {
+    count: usize,
+
+    // Threads arrived, iterations passed and whether one has panicked
+    state: Mutex<(usize, usize, bool)>,
+    condvar: Condvar,
+}
+
+impl Rendezvous {
+    fn new(count: usize) -> Self {
+        Self {
+            count,
+            state: Mutex::new((0, 0, false)),
+            condvar: Condvar::new(),
+        }
+    }
+
+    // Whether this is the last thread to arrive, None once broken
+    fn wait(&self) -> Option<bool> {
+        let mut state = self.state.lock().unwrap();
+        if state.2 {
+            return None;
+        }
+
+        let generation = state.1;
+        state.0 += 1;
+        if state.0 == self.count {
+            state.0 = 0;
+            state.1 += 1;
+            self.condvar.notify_all();
+            return Some(true);
+        }
+
+        while state.1 == generation && !state.2 {
+            state = self.condvar.wait(state).unwrap();
+        }
+        if state.1 == generation { None } else { Some(false) }
+    }
+
+    fn abandon(&self) {
+        self.state.lock().unwrap().2 = true;
+        self.condvar.notify_all();
+    }
+}
+
+// Breaks the rendezvous if the thread holding it unwinds
+struct PanicGuard<'a>(&'a Rendezvous);
+
+impl Drop for PanicGuard<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.abandon();
+        }
+    }
+}
+
+// Photons and iteration state handed between threads
+struct Shared<F: Float> {
+    rendezvous: Rendezvous,
+    photons: Mutex<Vec<Photon<F>>>,
+    the_tree: RwLock<TheTree<F>>,
+    done: AtomicBool,
+}
+
+impl<F: Float> Integrator<F> for Sppm<F> {
+    fn name(&self) -> String {
+        "sppm".to_string()
+    }
+
+    // Without photons only emitted and direct light is found
+    fn radiance(&self, ray: &Ray<F>, context: &SceneContext<F>) -> Vector3D<F> {
+        let (ld, _) = self.visible_point(ray, context);
+        ld
+    }
+
+    fn render(
+        &self,
+        camera: Camera<F>,
+        scene_gen: Arc<dyn SceneGenerator<F>>,
+        thread_count: u32,
+    ) -> Option<Vec<Vector3D<F>>> {
+        let shared = Shared {
+            rendezvous: Rendezvous::new(thread_count as usize),
+            photons: Mutex::new(Vec::new()),
+            the_tree: RwLock::new(TheTree::new(Vec::new())),
+            done: AtomicBool::new(false),
+        };
+        let start = Instant::now();
+
+        let res_vec = std::thread::scope(|s| {
+            let mut thread_handle_vec = Vec::new();
+
+            for t in 0..thread_count {
+                let scene_gen = scene_gen.clone();
+                let shared = &shared;
+
+                let handle = s.spawn(move || {
+                    let _guard = PanicGuard(&shared.rendezvous);
+                    let context = SceneContext::new(scene_gen.gen_scene(), camera);
+
+                    self.render_thread(&context, shared, start, t, thread_count)
+                });
+
+                thread_handle_vec.push(handle);
+            }
+
+            let mut res_vec = Vec::with_capacity((camera.width() * camera.height()) as usize);
+            let mut panicked = None;
+            for thread in thread_handle_vec {
+                match thread.join() {
+                    Ok(Some(mut _res_vec)) => res_vec.append(&mut _res_vec),
+                    Ok(None) => {} // Stopped early for the one that panicked
+                    Err(payload) => {
+                        panicked.get_or_insert(payload);
+                    }
+                }
+            }
+            if let Some(payload) = panicked {
+                std::panic::resume_unwind(payload);
+            }
+
+            res_vec
+        });
+
+        let duration = start.elapsed();
+        println!("Time elapsed in sppm iterations is: {:?}", duration);
+
+        Some(res_vec)
+    }
+}
+
+impl<F: Float> Sppm<F> {
+    fn render_thread(
+        &self,
+        context: &SceneContext<F>,
+        shared: &Shared<F>,
+        start: Instant,
+        t: u32,
+        thread_count: u32,
+    ) -> Option<Vec<Vector3D<F>>> {
+        let camera = context.camera();
+        let width = camera.width();
+        let height = camera.height();
+
+        let thread_rows = width / thread_count;
+        let row_start = t * thread_rows;
+        let row_end = if t == thread_count - 1 {
+            width
+        } else {
+            (t + 1) * thread_rows
+        };
+
+        let mut pixels: Vec<SppmPixel<F>> = (row_start..row_end)
+            .flat_map(|_| 0..height)
+            .map(|_| SppmPixel::new(self.initial_radius))
+            .collect();
+        let mut photons_per_thread = self.photon_count / thread_count;
+        if t == thread_count - 1 {
+            photons_per_thread += self.photon_count % thread_count;
+        }
+
+        let mut iteration = 0;
+        loop {
+            // Camera pass
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                let w = row_start + i as u32 / height;
+                let h = i as u32 % height;
+                let ray = camera.ray(
+                    w, h,
+                    F::sample_rand(),
+                    F::sample_rand(),
+                );
+
+                let (ld, visible) = self.visible_point(&ray, context);
+                pixel.ld += ld;
+                pixel.visible = visible;
+            }
+
+            // Photon pass
+            let photons = self.cast_photons(context, photons_per_thread);
+            shared.photons.lock().unwrap().extend(photons);
+
+            iteration += 1;
+            if shared.rendezvous.wait()? {
+                let photons = std::mem::take(&mut *shared.photons.lock().unwrap());
+                *shared.the_tree.write().unwrap() = TheTree::new(photons);
+
+                let done = match self.time_budget {
+                    Some(time_budget) => start.elapsed() >= time_budget,
+                    None => iteration >= self.iterations,
+                };
+                shared.done.store(done, Ordering::SeqCst);
+            }
+            shared.rendezvous.wait()?;
+
+            let the_tree = shared.the_tree.read().unwrap();
+            for pixel in pixels.iter_mut() {
+                self.gather(&the_tree, pixel);
+                self.update(pixel);
+            }
+            drop(the_tree);
+
+            if shared.done.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+
+        let iterations = F::from(iteration).unwrap();
+        let res_vec = pixels.into_iter()
+            .map(|pixel| {
+                let area = F::PI() * pixel.radius * pixel.radius;
+                (pixel.ld + pixel.tau / area) / iterations
+            })
+            .collect();
+
+        Some(res_vec)
+    }
+
+    // Follows specular bounces to the first diffuse surface, returns the
+    // emitted and direct light found on the way
+    fn visible_point(
+        &self,
+        ray: &Ray<F>,
+        context: &SceneContext<F>,
+    ) -> (Vector3D<F>, Option<VisiblePoint<F>>) {
+        let mut ld: Vector3D<F> = Vector3D::zero();
+        let mut beta: Vector3D<F> = Vector3D::one();
+        let mut ray = *ray;
+
+        for _ in 0..self.max_depth {
+            let (object, incident) = match context.intersect(&ray) {
+                Some(hit) => hit,
+                None => {
+                    ld += beta * context.environment_radiance(ray.direction());
+                    break;
+                }
+            };
+
+            ld += beta * object.emit_at(&incident);
+
+            let seed = F::sample_rand();
+            if !object.focus_at(&incident) {
+                ld += beta * context.direct_light(object.clone(), &incident, seed);
+                ld += beta * context.direct_environment(object.clone(), &incident, seed);
+
+                let visible = VisiblePoint {
+                    object,
+                    incident,
+                    beta,
+                };
+                return (ld, Some(visible));
+            }
+
+            let processed = object.interact(incident, seed);
+            beta = beta * processed.multiplier();
+            ray = processed.next_ray();
+        }
+
+        (ld, None)
+    }
+
+    // Photons bounced at least once, direct light is found at visible points.
+    // The environment casts none, light from it only arrives directly
+    fn cast_photons(&self, context: &SceneContext<F>, photon_count: u32) -> Vec<Photon<F>> {
+        let mut photons = Vec::new();
+
+        for _ in 0..photon_count {
+            let (lightsource, select_pdf) = match context.light_sampler().sample(F::sample_rand()) {
+                Some(sampled) => sampled,
+                None => break,
+            };
+            let light_sample = lightsource.sample_light();
+
+            let pdf = select_pdf * light_sample.position_pdf * light_sample.direction_pdf;
+            if pdf == F::zero() {
+                continue;
+            }
+
+            let ray = light_sample.ray;
+            let diff = light_sample.emit * ray.direction().dot(light_sample.normal).abs();
+            let diff = diff / (pdf * F::from(self.photon_count).unwrap());
+            // Each photon carries its own wavelength, camera paths stay RGB
+            let wavelength = if context.spectral() { Some(spectral::sample_wavelength()) } else { None };
+            let diff = match wavelength {
+                Some(wavelength) => spectral::project(diff, wavelength),
+                None => diff,
+            };
+            let ray = light_sample.spawn_ray().with_wavelength(wavelength);
+
+            self.cast_ray(context, ray, diff, &mut photons);
+        }
+
+        photons
+    }
+
+    fn cast_ray(
+        &self,
+        context: &SceneContext<F>,
+        ray: Ray<F>,
+        diff: Vector3D<F>,
+        photons: &mut Vec<Photon<F>>,
+    ) {
+        let mut ray = ray;
+        let mut diff = diff;
+
+        for depth in 0..self.max_depth {
+            let (object, incident) = match context.intersect(&ray) {
+                Some(hit) => hit,
+                None => break,
+            };
+
+            if depth > 0 && !object.focus_at(&incident) {
+                photons.push(Photon::new(
+                    incident.coords(),
+                    incident.w_i(), // Inverse of incoming direction
+                    diff,
+                ));
+            }
+
+            let processed = object.interact(incident, F::sample_rand());
+            let next_diff = diff * processed.rev_multiplier(); // Flux, directions swapped
+            if next_diff == Vector3D::zero() {
+                break;
+            }
+
+            // Roulette on the change in throughput keeps photon powers even
+            let survival = max_component(next_diff) / max_component(diff);
+            if survival < F::one() {
+                if F::sample_rand() >= survival {
+                    break;
+                }
+                diff = next_diff / survival;
+            } else {
+                diff = next_diff;
+            }
+
+            ray = processed.next_ray();
+        }
+    }
+
+    fn gather(&self, the_tree: &TheTree<F>, pixel: &mut SppmPixel<F>) {
+        let visible = match &pixel.visible {
+            Some(visible) => visible,
+            None => return,
+        };
+
+        for photon in the_tree.within(visible.incident.coords(), pixel.radius) {
+            let processed = visible.object.interact_predetermined(
+                visible.incident,
+                photon.w_i(), // Outgoing
+                F::one(),
+                F::sample_rand(),
+            );
+
+            pixel.phi += processed.f_r() * photon.diff();
+            pixel.m += 1;
+        }
+    }
+
+    // Shrinks the radius by the photons found, keeping the flux density
+    fn update(&self, pixel: &mut SppmPixel<F>) {
+        if let Some(visible) = pixel.visible.take() {
+            if pixel.m > 0 {
+                let m = F::from(pixel.m).unwrap();
+                let n = pixel.n + self.alpha * m;
+                let radius = pixel.radius * (n / (pixel.n + m)).sqrt();
+                let ratio = radius * radius / (pixel.radius * pixel.radius);
+
+                pixel.tau = (pixel.tau + visible.beta * pixel.phi) * ratio;
+                pixel.n = n;
+                pixel.radius = radius;
+            }
+        }
+
+        pixel.phi = Vector3D::zero();
+        pixel.m = 0;
+    }
+}
+
+fn max_component<F: Float>(v: Vector3D<F>) -> F {
+    v.x.max(v.y).max(v.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendezvous_leader() {
+        let rendezvous = Rendezvous::new(4);
+
+        let leaders = std::thread::scope(|s| {
+            let handles = (0..4)
+                .map(|_| s.spawn(|| (0..3).map(|_| rendezvous.wait().unwrap()).filter(|&leader| leader).count()))
+                .collect::<Vec<_>>();
+
+            handles.into_iter().map(|handle| handle.join().unwrap()).sum::<usize>()
+        });
+
+        assert_eq!(leaders, 3); // One per round
+    }
+
+    #[test]
+    fn rendezvous_broken_by_panic() {
+        let rendezvous = Rendezvous::new(3);
+
+        let results = std::thread::scope(|s| {
+            let handles = (0..3)
+                .map(|t| {
+                    let rendezvous = &rendezvous;
+                    s.spawn(move || {
+                        let _guard = PanicGuard(rendezvous);
+                        rendezvous.wait()?;
+                        if t == 0 {
+                            panic!("worker failed");
+                        }
+                        rendezvous.wait()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles.into_iter().map(|handle| handle.join()).collect::<Vec<_>>()
+        });
+
+        assert!(results[0].is_err());
+        assert!(results[1..].iter().all(|result| matches!(result, Ok(None))));
+    }
+}
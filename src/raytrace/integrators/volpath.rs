@@ -0,0 +1,123 @@
+use crate::raytrace::Ray;
+use crate::raytrace::integrators::{Integrator, SceneContext};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Path tracing through participating media, distances are sampled in media
+// and lights are sampled at surface and medium vertices alike
+pub struct VolumetricPathTracer<F: Float> {
+    rr: F,
+}
+
+impl<F: Float> VolumetricPathTracer<F> {
+    pub fn new(rr: F) -> Self {
+        Self {
+            rr,
+        }
+    }
+}
+
+impl<F: Float> Integrator<F> for VolumetricPathTracer<F> {
+    fn name(&self) -> String {
+        "volpath".to_string()
+    }
+
+    fn radiance(&self, ray: &Ray<F>, context: &SceneContext<F>) -> Vector3D<F> {
+        let mut l_x: Vector3D<F> = Vector3D::zero();
+        let mut throughput: Vector3D<F> = Vector3D::one();
+        let mut ray = *ray;
+        let mut medium = context.medium().cloned();
+
+        // Emitters are only counted when light sampling couldn't have found them
+        let mut specular = true;
+
+        loop {
+            let hit = context.intersect(&ray);
+            let t_max = match &hit {
+                Some((_, incident)) => incident.distance(),
+                None => F::max_value(),
+            };
+
+            if let Some(current) = medium.clone() {
+                let sample = current.sample(&ray, t_max);
+                throughput = throughput * sample.weight;
+
+                if sample.scattered {
+                    throughput = throughput * sample.sigma_s;
+
+                    let coords = ray.origin() + ray.direction() * sample.t;
+                    let w_i = -ray.direction();
+                    let phase = current.phase();
+
+                    l_x += throughput * context.scattered_light(
+                        coords,
+                        |_| coords, // Nothing to leave
+                        ray.inside(),
+                        medium.clone(),
+                        |w_r| Vector3D::one() * phase.p(w_i, w_r),
+                    );
+
+                    if F::sample_rand() >= self.rr {
+                        break;
+                    }
+
+                    // Sampled proportional to the phase function, so the weight is one
+                    let (w_r, _) = phase.sample(w_i);
+                    ray = if ray.inside() {
+                        Ray::from_inside(coords, w_r)
+                    } else {
+                        Ray::new(coords, w_r)
+                    }.with_stack(ray.stack()).with_wavelength(ray.wavelength());
+                    throughput = throughput / self.rr;
+                    specular = false;
+                    continue;
+                }
+
+                if throughput == Vector3D::zero() {
+                    break;
+                }
+            }
+
+            let (object, incident) = match hit {
+                Some(hit) => hit,
+                None => { // Escaped to the environment
+                    l_x += throughput * context.environment_radiance(ray.direction());
+                    break;
+                }
+            };
+
+            if specular {
+                l_x += throughput * object.emit_at(&incident);
+            }
+
+            if object.interface(&incident) { // Crossed into or out of a medium
+                let next_ray = object.interact(incident, F::sample_rand()).next_ray();
+                medium = context.next_medium(&object, &incident, &next_ray, medium);
+                ray = next_ray;
+                continue;
+            }
+
+            if !object.focus_at(&incident) {
+                l_x += throughput * context.surface_light(&object, &incident, ray.inside(), medium.clone());
+            }
+
+            if F::sample_rand() >= self.rr {
+                break;
+            }
+
+            let processed = object.interact(incident, F::sample_rand());
+            let next_ray = processed.next_ray();
+            medium = context.next_medium(&object, &incident, &next_ray, medium);
+
+            throughput = throughput * processed.multiplier() / self.rr;
+            if throughput == Vector3D::zero() {
+                break;
+            }
+
+            specular = processed.specular();
+            ray = next_ray;
+        }
+
+        l_x
+    }
+}
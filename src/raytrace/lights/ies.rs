@@ -0,0 +1,356 @@
+use crate::raytrace::to_world;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// How the angles of a file are laid out around the luminaire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Photometry {
+    // Polar axis up and down the lamp, for automotive lights
+    A,
+    // Polar axis across the lamp, for floodlights
+    B,
+    // Polar axis along the lamp, for most interior fixtures
+    C,
+}
+
+// Angular intensity distribution from an IES LM-63 photometric file,
+// normalized so that the brightest direction is 1.
+// The luminaire points along its axis, which is the nadir for type C files
+// and the aim for type A and B files.
+#[derive(Debug, Clone)]
+pub struct IesProfile<F: Float> {
+    photometry: Photometry,
+
+    vertical: Vec<F>,
+    horizontal: Vec<F>,
+
+    // candela[h][v]
+    candela: Vec<Vec<F>>,
+
+    max_candela: F,
+    average: F,
+}
+
+impl<F: Float> IesProfile<F> {
+    pub fn new(source: String) -> Self {
+        let ies_source = std::fs::read_to_string(source)
+            .expect("Something went wrong reading the file");
+
+        Self::parse(&ies_source).expect("malformed IES profile")
+    }
+
+    pub fn parse(ies_source: &str) -> Option<Self> {
+        // Keywords come first, numbers start right after TILT=
+        let mut lines = ies_source.lines();
+        let tilt = loop {
+            let line = lines.next()?.trim();
+            if let Some(tilt) = line.strip_prefix("TILT=") {
+                break tilt.trim().to_string();
+            }
+        };
+
+        let mut numbers = lines
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|t| !t.is_empty())
+            .map(|t| t.parse::<f64>().ok());
+        let mut next = || numbers.next().flatten();
+
+        if tilt == "INCLUDE" { // Lamp tilt does not affect the distribution
+            let _geometry = next()?;
+            let pairs = next()? as usize;
+            for _ in 0..pairs * 2 {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens = next()?;
+        let multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        let photometry = match next()? as u32 {
+            1 => Photometry::C,
+            2 => Photometry::B,
+            3 => Photometry::A,
+            _ => return None,
+        };
+        let _units = next()?;
+        let _width = next()?;
+        let _length = next()?;
+        let _height = next()?;
+        let ballast = next()?;
+        let _future = next()?;
+        let _watts = next()?;
+
+        let mut vertical = Vec::with_capacity(vertical_count);
+        for _ in 0..vertical_count {
+            vertical.push(F::from(next()?).unwrap());
+        }
+        let mut horizontal = Vec::with_capacity(horizontal_count);
+        for _ in 0..horizontal_count {
+            horizontal.push(F::from(next()?).unwrap());
+        }
+
+        let scale = multiplier * ballast;
+        let mut candela = Vec::with_capacity(horizontal_count);
+        let mut max_candela = F::zero();
+        for _ in 0..horizontal_count {
+            let mut row = Vec::with_capacity(vertical_count);
+            for _ in 0..vertical_count {
+                let value = F::from(next()? * scale).unwrap();
+                max_candela = max_candela.max(value);
+                row.push(value);
+            }
+            candela.push(row);
+        }
+        if vertical.is_empty() || horizontal.is_empty() || max_candela <= F::zero() {
+            return None;
+        }
+
+        for row in candela.iter_mut() {
+            for value in row.iter_mut() {
+                *value = *value / max_candela;
+            }
+        }
+
+        let mut profile = Self {
+            photometry,
+            vertical,
+            horizontal,
+            candela,
+            max_candela,
+            average: F::zero(),
+        };
+        profile.average = profile.integrate();
+
+        Some(profile)
+    }
+}
+
+fn segment<F: Float>(angles: &[F], angle: F) -> Option<(usize, F)> {
+    if angles.len() == 1 {
+        return Some((0, F::zero()));
+    }
+    if angle < angles[0] || angle > angles[angles.len() - 1] {
+        return None;
+    }
+
+    let i = angles.partition_point(|a| *a <= angle)
+        .max(1)
+        .min(angles.len() - 1) - 1;
+    let span = angles[i + 1] - angles[i];
+    let t = if span > F::zero() { (angle - angles[i]) / span } else { F::zero() };
+
+    Some((i, t))
+}
+
+impl<F: Float> IesProfile<F> {
+    // Candela of the brightest direction, the scale the profile was normalized by
+    pub fn max_candela(&self) -> F {
+        self.max_candela
+    }
+
+    pub fn photometry(&self) -> Photometry {
+        self.photometry
+    }
+
+    // Mean of the normalized intensity over the sphere
+    pub fn average(&self) -> F {
+        self.average
+    }
+
+    // Vertical and horizontal angles in degrees
+    pub fn eval_angles(&self, vertical: F, horizontal: F) -> F {
+        let quarter = F::from(90u32).unwrap();
+        let half_turn = F::from(180u32).unwrap();
+        let full_turn = F::from(360u32).unwrap();
+
+        let last = self.horizontal[self.horizontal.len() - 1];
+        let horizontal = match self.photometry {
+            Photometry::C => {
+                // Fold the horizontal angle into the range the file covers
+                let mut horizontal = horizontal % full_turn;
+                if horizontal < F::zero() {
+                    horizontal = horizontal + full_turn;
+                }
+                if last <= quarter && self.horizontal.len() > 1 { // Quadrant symmetric
+                    if horizontal > half_turn {
+                        horizontal = full_turn - horizontal;
+                    }
+                    if horizontal > quarter {
+                        horizontal = half_turn - horizontal;
+                    }
+                } else if last <= half_turn && horizontal > half_turn { // Bilateral symmetric
+                    horizontal = full_turn - horizontal;
+                }
+                horizontal
+            }
+            // Only one side is given when the file starts at 0
+            Photometry::A | Photometry::B if self.horizontal[0] >= F::zero() => horizontal.abs(),
+            Photometry::A | Photometry::B => horizontal,
+        };
+
+        let (v, t_v) = match segment(&self.vertical, vertical) {
+            Some(found) => found,
+            None => return F::zero(),
+        };
+        let (h, t_h) = match segment(&self.horizontal, horizontal) {
+            Some(found) => found,
+            None => return F::zero(),
+        };
+
+        let v_1 = (v + 1).min(self.vertical.len() - 1);
+        let h_1 = (h + 1).min(self.horizontal.len() - 1);
+
+        let lerp = |row: &Vec<F>| row[v] * (F::one() - t_v) + row[v_1] * t_v;
+
+        lerp(&self.candela[h]) * (F::one() - t_h) + lerp(&self.candela[h_1]) * t_h
+    }
+
+    // Intensity toward a world direction, for a luminaire pointing along axis
+    pub fn eval(&self, direction: Vector3D<F>, axis: Vector3D<F>) -> F {
+        let b = to_world(Vector3D::new(F::one(), F::zero(), F::zero()), axis);
+        let c = to_world(Vector3D::new(F::zero(), F::one(), F::zero()), axis);
+        let (x, y, z) = (direction.dot(b), direction.dot(c), direction.dot(axis));
+
+        let clamp = |s: F| s.max(-F::one()).min(F::one());
+        let asin = |s: F| clamp(s).asin().to_degrees();
+        let (vertical, horizontal) = match self.photometry {
+            Photometry::C => (clamp(z).acos().to_degrees(), y.atan2(x).to_degrees()),
+            // Turning about the polar axis is horizontal for type A and vertical for type B
+            Photometry::A => (asin(x), y.atan2(z).to_degrees()),
+            Photometry::B => (x.atan2(z).to_degrees(), asin(y)),
+        };
+
+        self.eval_angles(vertical, horizontal)
+    }
+
+    fn integrate(&self) -> F {
+        let theta_steps = 64;
+        let phi_steps = 64;
+
+        let _half = F::from(0.5).unwrap();
+        let d_theta = F::PI() / F::from(theta_steps).unwrap();
+        let d_phi = F::from(2u32).unwrap() * F::PI() / F::from(phi_steps).unwrap();
+        let axis = Vector3D::new(F::zero(), F::zero(), F::one());
+
+        let mut total = F::zero();
+        let mut weight = F::zero();
+        for i in 0..theta_steps {
+            let theta = (F::from(i).unwrap() + _half) * d_theta;
+            let sin_theta = theta.sin();
+            for j in 0..phi_steps {
+                let phi = (F::from(j).unwrap() + _half) * d_phi;
+                let direction = Vector3D::new(sin_theta * phi.cos(), sin_theta * phi.sin(), theta.cos());
+                total = total + self.eval(direction, axis) * sin_theta;
+                weight = weight + sin_theta;
+            }
+        }
+
+        total / weight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Type C, quadrant symmetric, with a multiplier of 2
+    const QUADRANT: &str = "IESNA:LM-63-2002
+[TEST] quadrant
+[MANUFAC] none
+TILT=NONE
+1 1000 2 3 2 1 2 0 0 0
+1 1 100
+0 45 90
+0 90
+50 25 0
+100 50 0
+";
+
+    // Type B with the lateral angles on one side only
+    const FLOOD: &str = "IESNA:LM-63-2002
+TILT=INCLUDE
+1
+2
+0 90
+1 0.5
+1 1000 1 3 2 2 2 0 0 0
+1 1 100
+-45 0 45
+0 45
+50 100 50
+0 20 0
+";
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn parse_type_c() {
+        let profile = IesProfile::<f64>::parse(QUADRANT).unwrap();
+
+        assert_eq!(profile.photometry(), Photometry::C);
+        assert!(close(profile.max_candela(), 200.0));
+        assert!(close(profile.eval_angles(0.0, 90.0), 1.0));
+        assert!(close(profile.eval_angles(0.0, 0.0), 0.5));
+        assert!(close(profile.eval_angles(22.5, 0.0), 0.375));
+        assert!(close(profile.eval_angles(0.0, 45.0), 0.75));
+        assert!(close(profile.eval_angles(120.0, 0.0), 0.0));
+
+        // The other quadrants mirror the first
+        assert!(close(profile.eval_angles(0.0, 135.0), 0.75));
+        assert!(close(profile.eval_angles(0.0, 270.0), 1.0));
+        assert!(close(profile.eval_angles(0.0, -45.0), 0.75));
+    }
+
+    #[test]
+    fn parse_type_b() {
+        let profile = IesProfile::<f64>::parse(FLOOD).unwrap();
+        let axis = Vector3D::new(0.0, 0.0, 1.0);
+        let toward = |x: f64, y: f64| Vector3D::new(x, y, (1.0 - x * x - y * y).sqrt());
+
+        assert_eq!(profile.photometry(), Photometry::B);
+        assert!(close(profile.max_candela(), 100.0));
+        assert!(close(profile.eval(axis, axis), 1.0));
+        assert!(close(profile.eval(-axis, axis), 0.0));
+
+        // Tilting about the polar axis is vertical, leaning toward it is horizontal
+        assert!(close(profile.eval(toward(0.5, 0.0), axis), 0.6667));
+        assert!(close(profile.eval(toward(0.0, 0.5), axis), 0.4667));
+        assert!(close(profile.eval(toward(0.0, -0.5), axis), 0.4667));
+        assert!(close(profile.eval(toward(0.5, 0.5), axis), 0.2316));
+    }
+
+    #[test]
+    fn parse_type_a() {
+        let profile = IesProfile::<f64>::parse(&FLOOD.replace("1 1000 1 3 2 2", "1 1000 1 3 2 3")).unwrap();
+        let axis = Vector3D::new(0.0, 0.0, 1.0);
+
+        // Same planes through the aim, turned the other way around it
+        assert_eq!(profile.photometry(), Photometry::A);
+        assert!(close(profile.eval(axis, axis), 1.0));
+        assert!(close(profile.eval(Vector3D::new(0.5, 0.5, 0.5f64.sqrt()), axis), 0.1965));
+    }
+
+    #[test]
+    fn uniform_average() {
+        let profile = IesProfile::<f64>::parse("TILT=NONE\n1 1000 1 1 1 1 2 0 0 0\n1 1 100\n0\n0\n300\n").unwrap();
+
+        assert!(close(profile.max_candela(), 300.0));
+        assert!(close(profile.average(), 1.0));
+    }
+
+    #[test]
+    fn reject_malformed() {
+        // Unknown photometric type
+        assert!(IesProfile::<f64>::parse(&QUADRANT.replace("1 1000 2 3 2 1", "1 1000 2 3 2 4")).is_none());
+        // Missing candela values
+        assert!(IesProfile::<f64>::parse(&QUADRANT[..QUADRANT.len() - 10]).is_none());
+        // No TILT line
+        assert!(IesProfile::<f64>::parse("IESNA:LM-63-2002\n").is_none());
+        // All dark
+        assert!(IesProfile::<f64>::parse("TILT=NONE\n1 1000 1 1 1 1 2 0 0 0\n1 1 100\n0\n0\n0\n").is_none());
+    }
+}
@@ -0,0 +1,26 @@
+mod sampler;
+mod tree;
+mod ies;
+
+pub use sampler::LightSampler;
+pub use tree::LightTree;
+pub use ies::{IesProfile, Photometry};
+
+use crate::raytrace::objects::RayTraceable;
+use crate::types::Float;
+
+use std::sync::Arc;
+
+pub fn collect_lights<F: Float>(objects: &[Arc<dyn RayTraceable<F>>]) -> Vec<Arc<dyn RayTraceable<F>>> {
+    let mut lights = Vec::new();
+    for object in objects {
+        let emitters = object.emitters();
+        if !emitters.is_empty() {
+            lights.extend(emitters);
+        } else if object.emit().is_some() { // Is light source
+            lights.push(object.clone());
+        }
+    }
+
+    lights
+}
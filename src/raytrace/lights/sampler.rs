@@ -0,0 +1,82 @@
+use crate::raytrace::lights::collect_lights;
+use crate::raytrace::objects::RayTraceable;
+use crate::types::Float;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Picks a light source proportional to its emitted power
+pub struct LightSampler<F: Float> {
+    lights: Vec<Arc<dyn RayTraceable<F>>>,
+
+    cdf: Vec<F>,
+    total_power: F,
+
+    index: HashMap<*const (), usize>,
+}
+
+impl<F: Float> LightSampler<F> {
+    pub fn new(objects: &[Arc<dyn RayTraceable<F>>]) -> Self {
+        let mut lights = Vec::new();
+        let mut cdf = Vec::new();
+        let mut total_power = F::zero();
+        let mut index = HashMap::new();
+
+        for light in collect_lights(objects) {
+            let power = light.power();
+            if power <= F::zero() {
+                continue;
+            }
+
+            total_power = total_power + power;
+            index.insert(Arc::as_ptr(&light) as *const (), lights.len());
+            lights.push(light);
+            cdf.push(total_power);
+        }
+
+        Self {
+            lights,
+            cdf,
+            total_power,
+            index,
+        }
+    }
+}
+
+impl<F: Float> LightSampler<F> {
+    pub fn lights(&self) -> &Vec<Arc<dyn RayTraceable<F>>> {
+        &self.lights
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    pub fn total_power(&self) -> F {
+        self.total_power
+    }
+
+    pub fn sample(&self, seed: F) -> Option<(Arc<dyn RayTraceable<F>>, F)> {
+        if self.lights.is_empty() {
+            return None;
+        }
+
+        let target = self.total_power * seed;
+        let i = self.cdf.partition_point(|c| *c < target)
+            .min(self.lights.len() - 1);
+
+        Some((self.lights[i].clone(), self.pdf_index(i)))
+    }
+
+    pub fn pdf(&self, light: &Arc<dyn RayTraceable<F>>) -> F {
+        match self.index.get(&(Arc::as_ptr(light) as *const ())) {
+            Some(i) => self.pdf_index(*i),
+            None => F::zero(),
+        }
+    }
+
+    fn pdf_index(&self, i: usize) -> F {
+        let prev = if i == 0 { F::zero() } else { self.cdf[i - 1] };
+        (self.cdf[i] - prev) / self.total_power
+    }
+}
@@ -0,0 +1,336 @@
+use crate::raytrace::lights::collect_lights;
+use crate::raytrace::objects::RayTraceable;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Conservative bounds on the emission of a set of lights
+#[derive(Debug, Clone, Copy)]
+struct LightBounds<F: Float> {
+    min_pt: Vector3D<F>,
+    max_pt: Vector3D<F>,
+
+    phi: F,
+
+    w: Vector3D<F>,
+    cos_theta_o: F,
+    cos_theta_e: F,
+}
+
+fn safe_sqrt<F: Float>(v: F) -> F {
+    v.max(F::zero()).sqrt()
+}
+
+// cos(max(0, a - b)) given sines and cosines of a and b
+fn cos_sub_clamped<F: Float>(sin_a: F, cos_a: F, sin_b: F, cos_b: F) -> F {
+    if cos_a > cos_b {
+        return F::one();
+    }
+
+    cos_a * cos_b + sin_a * sin_b
+}
+
+// sin(max(0, a - b)) given sines and cosines of a and b
+fn sin_sub_clamped<F: Float>(sin_a: F, cos_a: F, sin_b: F, cos_b: F) -> F {
+    if cos_a > cos_b {
+        return F::zero();
+    }
+
+    sin_a * cos_b - cos_a * sin_b
+}
+
+// Rotates v around the unit axis k by theta
+fn rotate<F: Float>(v: Vector3D<F>, k: Vector3D<F>, theta: F) -> Vector3D<F> {
+    v * theta.cos() + k.cross(v) * theta.sin() + k * (k.dot(v) * (F::one() - theta.cos()))
+}
+
+fn union_cone<F: Float>(
+    (w_a, cos_a): (Vector3D<F>, F),
+    (w_b, cos_b): (Vector3D<F>, F),
+) -> (Vector3D<F>, F) {
+    let theta_a = cos_a.max(-F::one()).min(F::one()).acos();
+    let theta_b = cos_b.max(-F::one()).min(F::one()).acos();
+    let theta_d = w_a.dot(w_b).max(-F::one()).min(F::one()).acos();
+
+    if (theta_d + theta_b).min(F::PI()) <= theta_a { // b inside a
+        return (w_a, cos_a);
+    }
+    if (theta_d + theta_a).min(F::PI()) <= theta_b { // a inside b
+        return (w_b, cos_b);
+    }
+
+    let _half = F::from(0.5).unwrap();
+    let theta_o = (theta_a + theta_d + theta_b) * _half;
+    if theta_o >= F::PI() {
+        return (w_a, -F::one());
+    }
+
+    let w_r = w_a.cross(w_b);
+    if w_r.magnitude() <= F::epsilon() {
+        return (w_a, -F::one());
+    }
+
+    (rotate(w_a, w_r.norm(), theta_o - theta_a), theta_o.cos())
+}
+
+impl<F: Float> LightBounds<F> {
+    fn new(light: &dyn RayTraceable<F>) -> Self {
+        let (min_pt, max_pt) = light.bounds();
+        let (w, cos_theta_o) = light.normal_cone();
+
+        Self {
+            min_pt,
+            max_pt,
+
+            phi: light.power(),
+
+            w,
+            cos_theta_o,
+            cos_theta_e: F::zero(), // Diffuse emitters cover the hemisphere
+        }
+    }
+
+    fn union(&self, op: &Self) -> Self {
+        let (w, cos_theta_o) = union_cone(
+            (self.w, self.cos_theta_o),
+            (op.w, op.cos_theta_o),
+        );
+
+        Self {
+            min_pt: self.min_pt.min(op.min_pt),
+            max_pt: self.max_pt.max(op.max_pt),
+
+            phi: self.phi + op.phi,
+
+            w,
+            cos_theta_o,
+            cos_theta_e: self.cos_theta_e.min(op.cos_theta_e),
+        }
+    }
+
+    fn centroid(&self) -> Vector3D<F> {
+        (self.min_pt + self.max_pt) * F::from(0.5).unwrap()
+    }
+
+    fn importance(&self, coords: Vector3D<F>, normal: Vector3D<F>) -> F {
+        let _half = F::from(0.5).unwrap();
+
+        let center = self.centroid();
+        let radius = (self.max_pt - self.min_pt).magnitude() * _half;
+
+        let offset = coords - center;
+        let d2 = offset.dot(offset).max(radius);
+        let w_i = if offset.magnitude() > F::epsilon() { offset.norm() } else { normal };
+
+        let cos_theta_w = self.w.dot(w_i);
+        let sin_theta_w = safe_sqrt(F::one() - cos_theta_w * cos_theta_w);
+
+        // Angle subtended by the bounding sphere
+        let cos_theta_b = if d2 < radius * radius {
+            -F::one()
+        } else {
+            safe_sqrt(F::one() - radius * radius / d2)
+        };
+        let sin_theta_b = safe_sqrt(F::one() - cos_theta_b * cos_theta_b);
+
+        let sin_theta_o = safe_sqrt(F::one() - self.cos_theta_o * self.cos_theta_o);
+        let cos_theta_x = cos_sub_clamped(sin_theta_w, cos_theta_w, sin_theta_o, self.cos_theta_o);
+        let sin_theta_x = sin_sub_clamped(sin_theta_w, cos_theta_w, sin_theta_o, self.cos_theta_o);
+        let cos_theta_p = cos_sub_clamped(sin_theta_x, cos_theta_x, sin_theta_b, cos_theta_b);
+        if cos_theta_p <= self.cos_theta_e {
+            return F::zero();
+        }
+
+        let cos_theta_i = w_i.dot(normal).abs();
+        let sin_theta_i = safe_sqrt(F::one() - cos_theta_i * cos_theta_i);
+        let cos_theta_i = cos_sub_clamped(sin_theta_i, cos_theta_i, sin_theta_b, cos_theta_b);
+
+        (self.phi * cos_theta_p * cos_theta_i / d2).max(F::zero())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Node {
+    Leaf(usize),
+    Interior(usize, usize),
+}
+
+// Light hierarchy over bounds and orientation cones, for picking lights
+// relative to a shading point
+pub struct LightTree<F: Float> {
+    lights: Vec<Arc<dyn RayTraceable<F>>>,
+
+    nodes: Vec<(Node, LightBounds<F>)>,
+
+    // Path from the root, bit i set for taking the second child at depth i
+    trails: HashMap<*const (), u64>,
+}
+
+impl<F: Float> LightTree<F> {
+    pub fn new(objects: &[Arc<dyn RayTraceable<F>>]) -> Self {
+        let mut lights = Vec::new();
+        let mut bounds = Vec::new();
+        for light in collect_lights(objects) {
+            let light_bounds = LightBounds::new(light.as_ref());
+            if light_bounds.phi <= F::zero() {
+                continue;
+            }
+
+            lights.push(light);
+            bounds.push(light_bounds);
+        }
+
+        let mut tree = Self {
+            lights,
+            nodes: Vec::new(),
+            trails: HashMap::new(),
+        };
+
+        let mut indices = (0..bounds.len()).collect::<Vec<usize>>();
+        if !indices.is_empty() {
+            tree.build(&bounds, &mut indices, 0, 0);
+        }
+
+        tree
+    }
+
+    fn build(
+        &mut self,
+        bounds: &[LightBounds<F>],
+        indices: &mut [usize],
+        trail: u64,
+        depth: u32,
+    ) -> usize {
+        if indices.len() == 1 {
+            let id = indices[0];
+            self.trails.insert(Arc::as_ptr(&self.lights[id]) as *const (), trail);
+            self.nodes.push((Node::Leaf(id), bounds[id]));
+            return self.nodes.len() - 1;
+        }
+
+        // Median split along the widest axis of the centroids
+        let mut min_pt = Vector3D::max_value();
+        let mut max_pt = Vector3D::min_value();
+        for id in indices.iter() {
+            min_pt = min_pt.min(bounds[*id].centroid());
+            max_pt = max_pt.max(bounds[*id].centroid());
+        }
+        let extent = max_pt - min_pt;
+        let axis = |v: Vector3D<F>| if extent.x >= extent.y && extent.x >= extent.z {
+            v.x
+        } else if extent.y >= extent.z {
+            v.y
+        } else {
+            v.z
+        };
+
+        indices.sort_by(|a, b| {
+            axis(bounds[*a].centroid())
+                .partial_cmp(&axis(bounds[*b].centroid()))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let mid = indices.len() / 2;
+        let (left, right) = indices.split_at_mut(mid);
+
+        let node_id = self.nodes.len();
+        self.nodes.push((Node::Leaf(0), bounds[left[0]])); // Placeholder
+
+        let left_id = self.build(bounds, left, trail, depth + 1);
+        let right_id = self.build(bounds, right, trail | (1u64 << depth), depth + 1);
+
+        let node_bounds = self.nodes[left_id].1.union(&self.nodes[right_id].1);
+        self.nodes[node_id] = (Node::Interior(left_id, right_id), node_bounds);
+
+        node_id
+    }
+}
+
+impl<F: Float> LightTree<F> {
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    pub fn sample(
+        &self,
+        coords: Vector3D<F>,
+        normal: Vector3D<F>,
+        seed: F,
+    ) -> Option<(Arc<dyn RayTraceable<F>>, F)> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut seed = seed;
+        let mut pmf = F::one();
+        let mut node_id = 0;
+        loop {
+            match self.nodes[node_id].0 {
+                Node::Leaf(id) => {
+                    if self.nodes[node_id].1.importance(coords, normal) <= F::zero() {
+                        return None;
+                    }
+
+                    return Some((self.lights[id].clone(), pmf));
+                }
+                Node::Interior(left_id, right_id) => {
+                    let left = self.nodes[left_id].1.importance(coords, normal);
+                    let right = self.nodes[right_id].1.importance(coords, normal);
+                    if left + right <= F::zero() {
+                        return None;
+                    }
+
+                    let p_left = left / (left + right);
+                    if seed < p_left {
+                        seed = (seed / p_left).min(F::one());
+                        pmf = pmf * p_left;
+                        node_id = left_id;
+                    } else {
+                        seed = ((seed - p_left) / (F::one() - p_left)).min(F::one());
+                        pmf = pmf * (F::one() - p_left);
+                        node_id = right_id;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn pdf(
+        &self,
+        coords: Vector3D<F>,
+        normal: Vector3D<F>,
+        light: &Arc<dyn RayTraceable<F>>,
+    ) -> F {
+        let mut trail = match self.trails.get(&(Arc::as_ptr(light) as *const ())) {
+            Some(trail) => *trail,
+            None => return F::zero(),
+        };
+
+        let mut pmf = F::one();
+        let mut node_id = 0;
+        while let Node::Interior(left_id, right_id) = self.nodes[node_id].0 {
+            let left = self.nodes[left_id].1.importance(coords, normal);
+            let right = self.nodes[right_id].1.importance(coords, normal);
+            if left + right <= F::zero() {
+                return F::zero();
+            }
+
+            if trail & 1 == 0 {
+                pmf = pmf * left / (left + right);
+                node_id = left_id;
+            } else {
+                pmf = pmf * right / (left + right);
+                node_id = right_id;
+            }
+            trail >>= 1;
+        }
+
+        // Matches sample, which never picks a leaf that cannot light this point
+        if self.nodes[node_id].1.importance(coords, normal) <= F::zero() {
+            return F::zero();
+        }
+
+        pmf
+    }
+}
@@ -0,0 +1,71 @@
+use crate::raytrace::{Incident, ProcessedIncident};
+use crate::raytrace::incident::RefractIncident;
+use crate::raytrace::materials::Material;
+use crate::raytrace::materials::refract::reflect;
+use crate::raytrace::materials::thin_film::{reflectance, ThinFilm};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Polished metal, eta + ik is its complex index per channel
+pub struct Conductor<F: Float> {
+    eta: Vector3D<F>,
+    k: Vector3D<F>,
+
+    coating: Option<ThinFilm<F>>,
+}
+
+impl<F: Float> Conductor<F> {
+    pub fn new(eta: Vector3D<F>, k: Vector3D<F>) -> Self {
+        Self {
+            eta,
+            k,
+            coating: None,
+        }
+    }
+
+    pub fn with_coating(mut self, coating: ThinFilm<F>) -> Self {
+        self.coating = Some(coating);
+        self
+    }
+}
+
+impl<F: Float> Material<F> for Conductor<F> {
+    fn interact(
+        &self,
+        incident: Incident<F>,
+        _seed: F,
+    ) -> ProcessedIncident<F> {
+        let w_i = incident.w_i();
+        let normal = incident.normal();
+
+        let refract = RefractIncident {
+            w_r: reflect(-w_i, normal),
+            flip: false, // Mirrored back out
+            attenuation: reflectance(
+                self.coating.as_ref(),
+                w_i.dot(normal),
+                F::one(),
+                self.eta,
+                self.k,
+                incident.wavelength(),
+            ),
+        };
+
+        ProcessedIncident::from_refract(
+            incident,
+            refract,
+        )
+    }
+
+    fn interact_predetermined(&self, incident: Incident<F>, _w_r: Vector3D<F>, _pdf: F, seed: F) -> ProcessedIncident<F> {
+        self.interact(incident, seed)
+    }
+
+    fn pdf(&self, _incident: &Incident<F>, _w_r: Vector3D<F>) -> F {
+        F::zero() // Specular
+    }
+
+    fn focus(&self) -> bool {
+        true
+    }
+}
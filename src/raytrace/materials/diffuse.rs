@@ -52,7 +52,7 @@ impl<F: Float> Diffuse<F> {
         // let x_2 = F::sample_rand() * F::from(0.2).unwrap() + F::from(0.05).unwrap();
         let x_1 = F::sample_rand();
         let x_2 = F::sample_rand();
-        let z = F::one().abs_sub(x_1 * F::from(2).unwrap());
+        let z = x_1; // Uniform over the hemisphere
         let r = (F::one() - z * z).sqrt();
         let phi: F = F::from(2).unwrap() * F::PI() * x_2;
 
@@ -113,6 +113,10 @@ impl<F: Float> Material<F> for Diffuse<F> {
         )
     }
 
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F {
+        Diffuse::pdf(self, w_r, incident.normal())
+    }
+
     fn focus(&self) -> bool {
         false
     }
@@ -0,0 +1,88 @@
+use crate::raytrace::{Incident, ProcessedIncident};
+use crate::raytrace::materials::{Diffuse, Material};
+use crate::raytrace::textures::Texture;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+pub struct Emissive<F: Float> {
+    texture: Box<dyn Texture<F>>,
+    power: F,
+
+    two_sided: bool,
+
+    surface: Box<dyn Material<F>>,
+}
+
+impl<F: Float> Emissive<F> {
+    pub fn new(texture: Box<dyn Texture<F>>, power: F) -> Self {
+        Self {
+            texture,
+            power,
+
+            two_sided: false,
+
+            surface: Box::new(Diffuse::new(Vector3D::zero())), // Black body
+        }
+    }
+
+    pub fn new_two_sided(texture: Box<dyn Texture<F>>, power: F) -> Self {
+        Self {
+            two_sided: true,
+            ..Self::new(texture, power)
+        }
+    }
+
+    // Material used for light reflected off the emitter
+    pub fn with_surface(mut self, surface: Box<dyn Material<F>>) -> Self {
+        self.surface = surface;
+        self
+    }
+}
+
+impl<F: Float> Material<F> for Emissive<F> {
+    fn interact(
+        &self,
+        incident: Incident<F>,
+        seed: F,
+    ) -> ProcessedIncident<F> {
+        self.surface.interact(incident, seed)
+    }
+
+    fn interact_predetermined(
+        &self,
+        incident: Incident<F>,
+        w_r: Vector3D<F>,
+        pdf: F,
+        seed: F) -> ProcessedIncident<F> {
+        self.surface.interact_predetermined(
+            incident,
+            w_r,
+            pdf,
+            seed,
+        )
+    }
+
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F {
+        self.surface.pdf(incident, w_r)
+    }
+
+    fn focus(&self) -> bool {
+        self.surface.focus()
+    }
+
+    fn emission(&self) -> Option<Vector3D<F>> {
+        Some(self.texture.average() * self.power)
+    }
+
+    fn emit(&self, incident: &Incident<F>) -> Vector3D<F> {
+        if incident.inside() && !self.two_sided { // Back face
+            return Vector3D::zero();
+        }
+
+        self.texture.value(incident.uv(), incident.coords()) * self.power
+    }
+
+    fn two_sided(&self) -> bool {
+        self.two_sided
+    }
+}
@@ -0,0 +1,472 @@
+use crate::color::luminance;
+use crate::raytrace::{Incident, ProcessedIncident, to_world};
+use crate::raytrace::incident::BRDFIncident;
+use crate::raytrace::materials::Material;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Lobes tracked one by one, R, TT and TRT, the rest are lumped together
+const P_MAX: usize = 3;
+
+// Absorption of one unit of each pigment, from d'Eon et al.
+const EUMELANIN: [f64; 3] = [0.419, 0.697, 1.37];
+const PHEOMELANIN: [f64; 3] = [0.187, 0.4, 1.05];
+
+// Dielectric fiber after Chiang et al. 2016, as in pbrt. Meant for curves, the
+// fiber runs along the tangent of the hit and v gives where across it was hit
+pub struct Hair<F: Float> {
+    // Absorbed per unit of the fiber diameter
+    sigma_a: Vector3D<F>,
+    eta: F,
+
+    // Longitudinal variance per lobe
+    v: [F; P_MAX + 1],
+    // Azimuthal logistic scale
+    s: F,
+    // Scales tilt the lobes by alpha, 2 alpha and 4 alpha
+    sin_2k_alpha: [F; P_MAX],
+    cos_2k_alpha: [F; P_MAX],
+}
+
+impl<F: Float> Hair<F> {
+    // beta_m and beta_n are the longitudinal and azimuthal roughness from zero
+    // to one, alpha the scale tilt in degrees
+    pub fn new(sigma_a: Vector3D<F>, beta_m: F, beta_n: F, alpha: F) -> Self {
+        let _four = F::from(4u32).unwrap();
+        let _two = F::from(2u32).unwrap();
+        let c = |x: f64| F::from(x).unwrap();
+
+        let v0 = c(0.726) * beta_m + c(0.812) * beta_m.powi(2) + c(3.7) * beta_m.powi(20);
+        let v0 = v0 * v0;
+        let v = [v0, v0 * c(0.25), v0 * _four, v0 * _four];
+
+        let s = (F::PI() / c(8.0)).sqrt()
+            * (c(0.265) * beta_n + c(1.194) * beta_n.powi(2) + c(5.372) * beta_n.powi(22));
+
+        let mut sin_2k_alpha = [F::zero(); P_MAX];
+        let mut cos_2k_alpha = [F::zero(); P_MAX];
+        sin_2k_alpha[0] = alpha.to_radians().sin();
+        cos_2k_alpha[0] = (F::one() - sin_2k_alpha[0] * sin_2k_alpha[0]).max(F::zero()).sqrt();
+        for i in 1..P_MAX {
+            sin_2k_alpha[i] = _two * cos_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+            cos_2k_alpha[i] = cos_2k_alpha[i - 1].powi(2) - sin_2k_alpha[i - 1].powi(2);
+        }
+
+        Self {
+            sigma_a,
+            eta: c(1.55),
+            v,
+            s,
+            sin_2k_alpha,
+            cos_2k_alpha,
+        }
+    }
+
+    // Human hair by pigment concentration, eumelanin from about 0 for blond
+    // to 8 for black, pheomelanin turns it red
+    pub fn from_melanin(eumelanin: F, pheomelanin: F, beta_m: F, beta_n: F, alpha: F) -> Self {
+        let channel = |i: usize| eumelanin * F::from(EUMELANIN[i]).unwrap()
+            + pheomelanin * F::from(PHEOMELANIN[i]).unwrap();
+
+        Self::new(Vector3D::new(channel(0), channel(1), channel(2)), beta_m, beta_n, alpha)
+    }
+
+    // Absorption that gives roughly the color after many bounces, for dyed fur
+    pub fn from_color(color: Vector3D<F>, beta_m: F, beta_n: F, alpha: F) -> Self {
+        let c = |x: f64| F::from(x).unwrap();
+        let d = c(5.969) - c(0.215) * beta_n + c(2.532) * beta_n.powi(2) - c(10.73) * beta_n.powi(3)
+            + c(5.574) * beta_n.powi(4) + c(0.245) * beta_n.powi(5);
+        let channel = |x: F| (x.max(c(1e-4)).ln() / d).powi(2);
+
+        Self::new(Vector3D::new(channel(color.x), channel(color.y), channel(color.z)), beta_m, beta_n, alpha)
+    }
+
+    pub fn with_eta(mut self, eta: F) -> Self {
+        self.eta = eta;
+        self
+    }
+}
+
+// Fiber frame, x along the tangent, y across and z the normal
+struct Frame<F: Float> {
+    tangent: Vector3D<F>,
+    across: Vector3D<F>,
+    normal: Vector3D<F>,
+}
+
+impl<F: Float> Frame<F> {
+    fn new(incident: &Incident<F>) -> Self {
+        let normal = incident.normal();
+        let tangent = incident.tangent();
+        let tangent = tangent - normal * tangent.dot(normal);
+        let tangent = if tangent.magnitude() > F::epsilon() {
+            tangent.norm()
+        } else { // Not on a curve, any direction in the surface
+            to_world(Vector3D::new(F::one(), F::zero(), F::zero()), normal)
+        };
+
+        Self {
+            tangent,
+            across: normal.cross(tangent),
+            normal,
+        }
+    }
+
+    fn local(&self, w: Vector3D<F>) -> Vector3D<F> {
+        Vector3D::new(w.dot(self.tangent), w.dot(self.across), w.dot(self.normal))
+    }
+
+    fn world(&self, w: Vector3D<F>) -> Vector3D<F> {
+        self.tangent * w.x + self.across * w.y + self.normal * w.z
+    }
+}
+
+fn safe_sqrt<F: Float>(x: F) -> F {
+    x.max(F::zero()).sqrt()
+}
+
+fn safe_asin<F: Float>(x: F) -> F {
+    x.max(-F::one()).min(F::one()).asin()
+}
+
+fn bessel_i0<F: Float>(x: F) -> F {
+    let _four = F::from(4u32).unwrap();
+
+    let mut sum = F::zero();
+    let mut x2i = F::one();
+    let mut fact = F::one();
+    let mut pow4 = F::one();
+    for i in 0..10 {
+        if i > 1 {
+            fact = fact * F::from(i).unwrap();
+        }
+        sum = sum + x2i / (pow4 * fact * fact);
+        x2i = x2i * x * x;
+        pow4 = pow4 * _four;
+    }
+
+    sum
+}
+
+fn log_bessel_i0<F: Float>(x: F) -> F {
+    if x > F::from(12u32).unwrap() {
+        return x + F::from(0.5).unwrap() * (-(F::from(2u32).unwrap() * F::PI()).ln() + (F::one() / x).ln()
+            + F::one() / (F::from(8u32).unwrap() * x));
+    }
+
+    bessel_i0(x).ln()
+}
+
+// Longitudinal scattering
+fn mp<F: Float>(cos_theta_i: F, cos_theta_o: F, sin_theta_i: F, sin_theta_o: F, v: F) -> F {
+    let _two = F::from(2u32).unwrap();
+
+    let a = cos_theta_i * cos_theta_o / v;
+    let b = sin_theta_i * sin_theta_o / v;
+    if v <= F::from(0.1).unwrap() { // Stable for narrow lobes
+        return (log_bessel_i0(a) - b - F::one() / v + F::LN_2() + (F::one() / (_two * v)).ln()).exp();
+    }
+
+    (-b).exp() * bessel_i0(a) / ((F::one() / v).sinh() * _two * v)
+}
+
+// Unpolarized Fresnel reflectance entering from a medium of index one
+fn fresnel<F: Float>(cos_theta_i: F, eta: F) -> F {
+    let cos_theta_i = cos_theta_i.max(-F::one()).min(F::one()).abs();
+    let sin_theta_t = safe_sqrt(F::one() - cos_theta_i * cos_theta_i) / eta;
+    if sin_theta_t >= F::one() {
+        return F::one();
+    }
+    let cos_theta_t = safe_sqrt(F::one() - sin_theta_t * sin_theta_t);
+
+    let parallel = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let perpendicular = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+
+    (parallel * parallel + perpendicular * perpendicular) * F::from(0.5).unwrap()
+}
+
+// Attenuation of each lobe
+fn ap<F: Float>(cos_theta_o: F, eta: F, h: F, transmittance: Vector3D<F>) -> [Vector3D<F>; P_MAX + 1] {
+    let cos_gamma_o = safe_sqrt(F::one() - h * h);
+    let f = fresnel(cos_theta_o * cos_gamma_o, eta);
+
+    let r = Vector3D::one() * f;
+    let tt = transmittance * (F::one() - f) * (F::one() - f);
+    let trt = tt * transmittance * f;
+    let rest = trt * transmittance * f / (Vector3D::one() - transmittance * f);
+
+    [r, tt, trt, rest]
+}
+
+// Azimuth a lobe leaves at
+fn phi<F: Float>(p: usize, gamma_o: F, gamma_t: F) -> F {
+    let p = F::from(p).unwrap();
+
+    F::from(2u32).unwrap() * p * gamma_t - F::from(2u32).unwrap() * gamma_o + p * F::PI()
+}
+
+fn logistic<F: Float>(x: F, s: F) -> F {
+    let x = x.abs();
+    let e = (-x / s).exp();
+
+    e / (s * (F::one() + e) * (F::one() + e))
+}
+
+fn logistic_cdf<F: Float>(x: F, s: F) -> F {
+    F::one() / (F::one() + (-x / s).exp())
+}
+
+// Logistic on -pi to pi, normalized
+fn trimmed_logistic<F: Float>(x: F, s: F) -> F {
+    logistic(x, s) / (logistic_cdf(F::PI(), s) - logistic_cdf(-F::PI(), s))
+}
+
+fn sample_trimmed_logistic<F: Float>(u: F, s: F) -> F {
+    let low = logistic_cdf(-F::PI(), s);
+    let k = logistic_cdf(F::PI(), s) - low;
+    let x = -s * (F::one() / (u * k + low) - F::one()).ln();
+
+    x.max(-F::PI()).min(F::PI())
+}
+
+// Azimuthal scattering
+fn np<F: Float>(dphi: F, p: usize, s: F, gamma_o: F, gamma_t: F) -> F {
+    let _two_pi = F::from(2u32).unwrap() * F::PI();
+
+    let mut dphi = dphi - phi(p, gamma_o, gamma_t);
+    while dphi > F::PI() {
+        dphi = dphi - _two_pi;
+    }
+    while dphi < -F::PI() {
+        dphi = dphi + _two_pi;
+    }
+
+    trimmed_logistic(dphi, s)
+}
+
+// Angles of a direction in the fiber frame
+struct Angles<F: Float> {
+    sin_theta: F,
+    cos_theta: F,
+    phi: F,
+}
+
+impl<F: Float> Angles<F> {
+    fn new(w: Vector3D<F>) -> Self {
+        let sin_theta = w.x.max(-F::one()).min(F::one());
+
+        Self {
+            sin_theta,
+            cos_theta: safe_sqrt(F::one() - sin_theta * sin_theta),
+            phi: w.z.atan2(w.y),
+        }
+    }
+}
+
+impl<F: Float> Hair<F> {
+    // Where light leaving toward w_o crossed the fiber, and the per lobe attenuation
+    fn lobes(&self, w_o: &Angles<F>, h: F) -> ([Vector3D<F>; P_MAX + 1], F, F) {
+        let _two = F::from(2u32).unwrap();
+
+        let eta_p = safe_sqrt(self.eta * self.eta - w_o.sin_theta * w_o.sin_theta) / w_o.cos_theta;
+        let sin_theta_t = w_o.sin_theta / self.eta;
+        let cos_theta_t = safe_sqrt(F::one() - sin_theta_t * sin_theta_t);
+        let sin_gamma_t = h / eta_p;
+        let cos_gamma_t = safe_sqrt(F::one() - sin_gamma_t * sin_gamma_t);
+
+        let path = _two * cos_gamma_t / cos_theta_t;
+        let transmittance = Vector3D::new(
+            (-self.sigma_a.x * path).exp(),
+            (-self.sigma_a.y * path).exp(),
+            (-self.sigma_a.z * path).exp(),
+        );
+
+        (ap(w_o.cos_theta, self.eta, h, transmittance), safe_asin(h), safe_asin(sin_gamma_t))
+    }
+
+    // The outgoing angle tilted by the scales for lobe p
+    fn tilt(&self, w_o: &Angles<F>, p: usize) -> (F, F) {
+        let (sin, cos) = (w_o.sin_theta, w_o.cos_theta);
+        let (sin_theta, cos_theta) = match p {
+            0 => (
+                sin * self.cos_2k_alpha[1] - cos * self.sin_2k_alpha[1],
+                cos * self.cos_2k_alpha[1] + sin * self.sin_2k_alpha[1],
+            ),
+            1 => (
+                sin * self.cos_2k_alpha[0] + cos * self.sin_2k_alpha[0],
+                cos * self.cos_2k_alpha[0] - sin * self.sin_2k_alpha[0],
+            ),
+            2 => (
+                sin * self.cos_2k_alpha[2] + cos * self.sin_2k_alpha[2],
+                cos * self.cos_2k_alpha[2] - sin * self.sin_2k_alpha[2],
+            ),
+            _ => (sin, cos),
+        };
+
+        (sin_theta, cos_theta.abs())
+    }
+
+    // Chance of sampling each lobe, by luminance
+    fn lobe_pdf(&self, w_o: &Angles<F>, h: F) -> [F; P_MAX + 1] {
+        let (ap, _, _) = self.lobes(w_o, h);
+        let total: F = ap.iter().fold(F::zero(), |total, &a| total + luminance(a));
+
+        if total <= F::zero() {
+            return [F::zero(); P_MAX + 1];
+        }
+
+        ap.map(|a| luminance(a) / total)
+    }
+
+    // Both local, light leaves along w_o after arriving from w_i. Divided by the
+    // cosine to the normal, which the integrators multiply back in
+    fn f(&self, w_o: Vector3D<F>, w_i: Vector3D<F>, h: F) -> Vector3D<F> {
+        let (o, i) = (Angles::new(w_o), Angles::new(w_i));
+        let (ap, gamma_o, gamma_t) = self.lobes(&o, h);
+
+        let dphi = i.phi - o.phi;
+        let mut sum = Vector3D::zero();
+        for (p, &a) in ap.iter().enumerate().take(P_MAX) {
+            let (sin_theta_o, cos_theta_o) = self.tilt(&o, p);
+            sum += a * mp(i.cos_theta, cos_theta_o, i.sin_theta, sin_theta_o, self.v[p])
+                * np(dphi, p, self.s, gamma_o, gamma_t);
+        }
+        sum += ap[P_MAX] * mp(i.cos_theta, o.cos_theta, i.sin_theta, o.sin_theta, self.v[P_MAX])
+            / (F::from(2u32).unwrap() * F::PI());
+
+        if w_i.z.abs() > F::zero() {
+            sum = sum / w_i.z.abs();
+        }
+
+        sum
+    }
+
+    fn pdf_local(&self, w_o: Vector3D<F>, w_i: Vector3D<F>, h: F) -> F {
+        let (o, i) = (Angles::new(w_o), Angles::new(w_i));
+        let (_, gamma_o, gamma_t) = self.lobes(&o, h);
+        let lobe_pdf = self.lobe_pdf(&o, h);
+
+        let dphi = i.phi - o.phi;
+        let mut pdf = F::zero();
+        for (p, &chance) in lobe_pdf.iter().enumerate().take(P_MAX) {
+            let (sin_theta_o, cos_theta_o) = self.tilt(&o, p);
+            pdf = pdf + chance * mp(i.cos_theta, cos_theta_o, i.sin_theta, sin_theta_o, self.v[p])
+                * np(dphi, p, self.s, gamma_o, gamma_t);
+        }
+
+        pdf + lobe_pdf[P_MAX] * mp(i.cos_theta, o.cos_theta, i.sin_theta, o.sin_theta, self.v[P_MAX])
+            / (F::from(2u32).unwrap() * F::PI())
+    }
+
+    fn sample_local(&self, w_o: Vector3D<F>, h: F, seed: F) -> Vector3D<F> {
+        let _two_pi = F::from(2u32).unwrap() * F::PI();
+
+        let o = Angles::new(w_o);
+        let (_, gamma_o, gamma_t) = self.lobes(&o, h);
+        let lobe_pdf = self.lobe_pdf(&o, h);
+
+        let mut p = P_MAX;
+        let mut u = seed;
+        for (lobe, &pdf) in lobe_pdf.iter().enumerate().take(P_MAX) {
+            if u < pdf {
+                p = lobe;
+                break;
+            }
+            u = u - pdf;
+        }
+
+        // Longitudinal, around the tilted mirror direction
+        let (sin_theta_o, cos_theta_o) = self.tilt(&o, p);
+        let u = F::sample_rand().max(F::from(1e-5).unwrap());
+        let v = self.v[p];
+        let cos_theta = F::one() + v * (u + (F::one() - u) * (-F::from(2u32).unwrap() / v).exp()).ln();
+        let sin_theta = safe_sqrt(F::one() - cos_theta * cos_theta);
+        let cos_phi = (_two_pi * F::sample_rand()).cos();
+        let sin_theta_i = -cos_theta * sin_theta_o + sin_theta * cos_phi * cos_theta_o;
+        let cos_theta_i = safe_sqrt(F::one() - sin_theta_i * sin_theta_i);
+
+        let dphi = if p < P_MAX {
+            phi(p, gamma_o, gamma_t) + sample_trimmed_logistic(F::sample_rand(), self.s)
+        } else {
+            _two_pi * F::sample_rand()
+        };
+        let phi_i = o.phi + dphi;
+
+        Vector3D::new(sin_theta_i, cos_theta_i * phi_i.cos(), cos_theta_i * phi_i.sin())
+    }
+}
+
+fn offset<F: Float>(incident: &Incident<F>) -> F {
+    let h = incident.uv().1 * F::from(2u32).unwrap() - F::one();
+
+    h.max(-F::one()).min(F::one())
+}
+
+impl<F: Float> Material<F> for Hair<F> {
+    fn interact(
+        &self,
+        incident: Incident<F>,
+        seed: F,
+    ) -> ProcessedIncident<F> {
+        let frame = Frame::new(&incident);
+        let h = offset(&incident);
+
+        let w_r = frame.world(self.sample_local(frame.local(incident.w_i()), h, seed));
+        let pdf = Material::pdf(self, &incident, w_r);
+
+        self.interact_predetermined(incident, w_r, pdf, seed)
+    }
+
+    // Through the fiber counts as well, so the cosine is taken on either side
+    fn interact_predetermined(
+        &self,
+        incident: Incident<F>,
+        w_r: Vector3D<F>,
+        pdf: F,
+        _seed: F) -> ProcessedIncident<F> {
+        let frame = Frame::new(&incident);
+        let h = offset(&incident);
+        let w_i = incident.w_i();
+        let normal = incident.normal();
+
+        let local_i = frame.local(w_i);
+        let local_r = frame.local(w_r);
+        let f_r = self.f(local_i, local_r, h);
+        let rev_f_r = self.f(local_r, local_i, h);
+
+        let (multiplier, rev_multiplier) = if pdf == F::zero() {
+            (Vector3D::one(), Vector3D::one())
+        } else {
+            (
+                f_r * w_r.dot(normal).abs() / pdf,
+                rev_f_r * w_r.dot(normal).abs() / pdf,
+            )
+        };
+
+        ProcessedIncident::from_brdf(
+            incident,
+            BRDFIncident {
+                f_r,
+                w_r,
+                pdf,
+
+                multiplier,
+                rev_multiplier,
+            },
+        )
+    }
+
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F {
+        let frame = Frame::new(incident);
+
+        self.pdf_local(frame.local(incident.w_i()), frame.local(w_r), offset(incident))
+    }
+
+    fn focus(&self) -> bool {
+        false
+    }
+
+    fn two_sided(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,50 @@
+use crate::raytrace::{Incident, ProcessedIncident};
+use crate::raytrace::incident::RefractIncident;
+use crate::raytrace::materials::Material;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Invisible surface that only marks where a medium starts and ends
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Interface {}
+
+impl Interface {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<F: Float> Material<F> for Interface {
+    fn interact(
+        &self,
+        incident: Incident<F>,
+        _seed: F,
+    ) -> ProcessedIncident<F> {
+        let refract = RefractIncident {
+            w_r: -incident.w_i(), // Straight through
+            flip: true,
+            attenuation: Vector3D::one(),
+        };
+
+        ProcessedIncident::from_refract(
+            incident,
+            refract,
+        )
+    }
+
+    fn interact_predetermined(&self, incident: Incident<F>, _w_r: Vector3D<F>, _pdf: F, seed: F) -> ProcessedIncident<F> {
+        self.interact(incident, seed)
+    }
+
+    fn pdf(&self, _incident: &Incident<F>, _w_r: Vector3D<F>) -> F {
+        F::zero() // Specular
+    }
+
+    fn focus(&self) -> bool {
+        true
+    }
+
+    fn interface(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,152 @@
+use crate::raytrace::{Incident, ProcessedIncident};
+use crate::raytrace::incident::BRDFIncident;
+use crate::raytrace::materials::Material;
+use crate::raytrace::media::Medium;
+use crate::raytrace::textures::{Constant, Texture};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::Arc;
+
+// Blend of two materials, weight zero is all first and one all second. Only
+// one of them is sampled, but reflection is evaluated for both
+pub struct Mix<F: Float> {
+    first: Box<dyn Material<F>>,
+    second: Box<dyn Material<F>>,
+
+    // The average of its channels is the weight
+    weight: Box<dyn Texture<F>>,
+}
+
+impl<F: Float> Mix<F> {
+    pub fn new(first: Box<dyn Material<F>>, second: Box<dyn Material<F>>, weight: F) -> Self {
+        Self::new_textured(first, second, Box::new(Constant::new(Vector3D::one() * weight)))
+    }
+
+    // Weight varies over the surface, for rust, wear or dirt
+    pub fn new_textured(
+        first: Box<dyn Material<F>>,
+        second: Box<dyn Material<F>>,
+        weight: Box<dyn Texture<F>>,
+    ) -> Self {
+        Self {
+            first,
+            second,
+            weight,
+        }
+    }
+
+    fn weight(&self, incident: &Incident<F>) -> F {
+        let value = self.weight.value(incident.uv(), incident.coords());
+        let weight = (value.x + value.y + value.z) / F::from(3u32).unwrap();
+
+        weight.max(F::zero()).min(F::one())
+    }
+
+    // Over the whole surface, for the average emission
+    fn average_weight(&self) -> F {
+        let value = self.weight.average();
+        let weight = (value.x + value.y + value.z) / F::from(3u32).unwrap();
+
+        weight.max(F::zero()).min(F::one())
+    }
+}
+
+impl<F: Float> Material<F> for Mix<F> {
+    fn interact(
+        &self,
+        incident: Incident<F>,
+        seed: F,
+    ) -> ProcessedIncident<F> {
+        let weight = self.weight(&incident);
+        let processed = if seed < weight {
+            self.second.interact(incident, seed / weight)
+        } else {
+            self.first.interact(incident, (seed - weight) / (F::one() - weight))
+        };
+
+        // Picking it cancels its share of the blend
+        if processed.specular() {
+            return processed;
+        }
+
+        let w_r = processed.next_ray().direction();
+        let pdf = self.pdf(&incident, w_r);
+        self.interact_predetermined(incident, w_r, pdf, seed)
+    }
+
+    // Reflection of both, with pdf the chance either would have sampled w_r
+    fn interact_predetermined(&self, incident: Incident<F>, w_r: Vector3D<F>, pdf: F, seed: F) -> ProcessedIncident<F> {
+        let weight = self.weight(&incident);
+        let first = self.first.interact_predetermined(incident, w_r, pdf, seed);
+        let second = self.second.interact_predetermined(incident, w_r, pdf, seed);
+
+        let f_r = first.f_r() * (F::one() - weight) + second.f_r() * weight;
+        let multiplier = if pdf == F::zero() {
+            Vector3D::one()
+        } else {
+            f_r * w_r.dot(incident.normal()) / pdf
+        };
+
+        // Specular ones reflect nothing toward a given direction
+        let rev_multiplier = |processed: ProcessedIncident<F>| if processed.specular() {
+            Vector3D::zero()
+        } else {
+            processed.rev_multiplier()
+        };
+        let rev_multiplier = rev_multiplier(first) * (F::one() - weight)
+            + rev_multiplier(second) * weight;
+
+        ProcessedIncident::from_brdf(
+            incident,
+            BRDFIncident {
+                f_r,
+                w_r,
+                pdf,
+
+                multiplier,
+                rev_multiplier,
+            },
+        )
+    }
+
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F {
+        let weight = self.weight(incident);
+
+        self.first.pdf(incident, w_r) * (F::one() - weight) + self.second.pdf(incident, w_r) * weight
+    }
+
+    fn focus(&self) -> bool {
+        self.first.focus() && self.second.focus()
+    }
+
+    fn emission(&self) -> Option<Vector3D<F>> {
+        let weight = self.average_weight();
+        match (self.first.emission(), self.second.emission()) {
+            (None, None) => None,
+            (first, second) => Some(
+                first.unwrap_or(Vector3D::zero()) * (F::one() - weight)
+                    + second.unwrap_or(Vector3D::zero()) * weight
+            ),
+        }
+    }
+
+    fn emit(&self, incident: &Incident<F>) -> Vector3D<F> {
+        let weight = self.weight(incident);
+
+        self.first.emit(incident) * (F::one() - weight) + self.second.emit(incident) * weight
+    }
+
+    fn two_sided(&self) -> bool {
+        self.first.two_sided() || self.second.two_sided()
+    }
+
+    // Anything else in the blend would show
+    fn interface(&self) -> bool {
+        self.first.interface() && self.second.interface()
+    }
+
+    fn medium(&self) -> Option<Arc<dyn Medium<F>>> {
+        self.first.medium().or_else(|| self.second.medium())
+    }
+}
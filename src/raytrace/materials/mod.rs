@@ -1,13 +1,30 @@
 use crate::raytrace::{Incident, ProcessedIncident};
 use crate::raytrace::incident::{BRDFIncident, RefractIncident};
+use crate::raytrace::media::Medium;
 use crate::types::Float;
 use crate::vector::Vector3D;
 
+use std::sync::Arc;
+
 mod diffuse;
 mod refract;
+mod emissive;
+mod interface;
+mod subsurface;
+mod thin_film;
+mod conductor;
+mod mix;
+mod hair;
 
 pub use diffuse::Diffuse;
 pub use refract::Refract;
+pub use emissive::Emissive;
+pub use interface::Interface;
+pub use subsurface::Subsurface;
+pub use thin_film::ThinFilm;
+pub use conductor::Conductor;
+pub use mix::Mix;
+pub use hair::Hair;
 
 pub trait Material<F: Float> {
     fn interact(
@@ -22,8 +39,34 @@ pub trait Material<F: Float> {
         pdf: F,
         seed: F,
     ) -> ProcessedIncident<F>;
+    // Solid angle density of sampling w_r in interact, zero for specular materials
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F;
 
     fn focus(&self) -> bool;
+
+    // Average emitted radiance, None for non-emissive materials
+    fn emission(&self) -> Option<Vector3D<F>> {
+        None
+    }
+
+    fn emit(&self, incident: &Incident<F>) -> Vector3D<F> {
+        let _ = incident;
+        Vector3D::zero()
+    }
+
+    fn two_sided(&self) -> bool {
+        false
+    }
+
+    // Passes light unchanged, only bounds a medium
+    fn interface(&self) -> bool {
+        false
+    }
+
+    // Fills the closed object the material covers, unless the object has its own
+    fn medium(&self) -> Option<Arc<dyn Medium<F>>> {
+        None
+    }
 }
 
 pub trait BRDFReflector<F: Float> {
@@ -57,11 +100,12 @@ pub trait BRDFReflector<F: Float> {
         } else {
             f_r * w_r.dot(normal) / pdf
         };
+        // Light carried from w_i out along w_r, as on paths traced from the lights
         let rev_f_r = self.f_r(coords, w_r, w_i, normal, seed);
         let mut rev_multiplier = if pdf == F::zero() {
             Vector3D::new(F::one(), F::one(), F::one())
         } else {
-            rev_f_r * w_i.dot(normal) / pdf
+            rev_f_r * w_r.dot(normal) / pdf
         };
 
         BRDFIncident {
@@ -94,6 +138,7 @@ pub trait Refractor<F: Float> {
         RefractIncident {
             w_r,
             flip,
+            attenuation: Vector3D::one(),
         }
     }
 }
@@ -1,22 +1,94 @@
-use crate::raytrace::{Incident, ProcessedIncident};
-use crate::raytrace::incident::BRDFIncident;
+use crate::raytrace::{Dielectric, DielectricStack, Incident, ProcessedIncident};
+use crate::raytrace::incident::{BRDFIncident, RefractIncident};
 use crate::raytrace::materials::{Refractor, Material};
+use crate::raytrace::materials::thin_film::{reflectance as film_reflectance, ThinFilm};
 use crate::types::Float;
 use crate::vector::Vector3D;
 
+// How the index changes with wavelength in µm
+#[derive(Debug, Clone, Copy)]
+enum Dispersion<F: Float> {
+    // a + b / λ²
+    Cauchy(F, F),
+    // Square root of 1 + Σ b λ² / (λ² - c)
+    Sellmeier([F; 3], [F; 3]),
+}
+
 pub struct Refract<F: Float> {
     index_of_coin: F,
+    // Per unit of distance travelled inside
+    absorption: Vector3D<F>,
+    // Only used when paths carry a wavelength
+    dispersion: Option<Dispersion<F>>,
+    coating: Option<ThinFilm<F>>,
+    // Where dielectrics overlap, the highest priority one is inside the others
+    priority: u32,
 }
 
 impl<F: Float> Refract<F> {
     pub fn new(index_of_coin: F) -> Self {
         Self {
-            index_of_coin
+            index_of_coin,
+            absorption: Vector3D::zero(),
+            dispersion: None,
+            coating: None,
+            priority: 0,
+        }
+    }
+
+    // Glass around water wants the higher priority, so the water only
+    // shows where the glass is not
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    // Film on the outside, reflectance then differs per channel
+    pub fn with_coating(mut self, coating: ThinFilm<F>) -> Self {
+        self.coating = Some(coating);
+        self
+    }
+
+    pub fn with_cauchy(mut self, a: F, b: F) -> Self {
+        self.dispersion = Some(Dispersion::Cauchy(a, b));
+        self
+    }
+
+    pub fn with_sellmeier(mut self, b: [F; 3], c: [F; 3]) -> Self {
+        self.dispersion = Some(Dispersion::Sellmeier(b, c));
+        self
+    }
+
+    // At the wavelength of a spectral path
+    fn index_of_coin(&self, wavelength: Option<F>) -> F {
+        let (dispersion, wavelength) = match (self.dispersion, wavelength) {
+            (Some(dispersion), Some(wavelength)) => (dispersion, wavelength),
+            _ => return self.index_of_coin,
+        };
+
+        let micrometers = wavelength / F::from(1000u32).unwrap();
+        let squared = micrometers * micrometers;
+        match dispersion {
+            Dispersion::Cauchy(a, b) => a + b / squared,
+            Dispersion::Sellmeier(b, c) => {
+                let mut n_squared = F::one();
+                for i in 0..3 {
+                    n_squared = n_squared + b[i] * squared / (squared - c[i]);
+                }
+
+                n_squared.sqrt()
+            }
         }
     }
+
+    // Tints by Beer-Lambert, thicker parts absorb more
+    pub fn with_absorption(mut self, absorption: Vector3D<F>) -> Self {
+        self.absorption = absorption;
+        self
+    }
 }
 
-fn reflect<F: Float>(v: Vector3D<F>, n: Vector3D<F>) -> Vector3D<F> {
+pub(super) fn reflect<F: Float>(v: Vector3D<F>, n: Vector3D<F>) -> Vector3D<F> {
     let _two = F::from(2u32).unwrap();
 
     v - n * (_two * v.dot(n))
@@ -35,6 +107,93 @@ fn reflectance<F: Float>(cosine: F, ref_idx: F) -> F {
     r0 + (F::one() - r0) * (F::one() - cosine).powi(5)
 }
 
+// Reflects by Fresnel, otherwise refracts
+fn scatter<F: Float>(w_i: Vector3D<F>, normal: Vector3D<F>, refraction_ratio: F, seed: F) -> (bool, Vector3D<F>) {
+    let cos_theta = w_i.dot(normal).min(F::one());
+    let sin_theta = (F::one() - cos_theta * cos_theta).sqrt();
+    let cannot_refract = refraction_ratio * sin_theta > F::one();
+    if cannot_refract || reflectance(cos_theta, refraction_ratio) > seed {
+        (false, reflect(-w_i, normal))
+    } else {
+        (true, refract(-w_i, normal, refraction_ratio))
+    }
+}
+
+impl<F: Float> Refract<F> {
+    fn dielectric(&self, wavelength: Option<F>) -> Dielectric<F> {
+        Dielectric {
+            id: self as *const Self as usize,
+            priority: self.priority,
+            index_of_coin: self.index_of_coin(wavelength),
+            absorption: self.absorption,
+        }
+    }
+
+    // Indices before and past the boundary, the stack past it, and whether
+    // it bends light at all rather than being inside a higher priority one
+    fn nesting(&self, incident: &Incident<F>) -> (F, F, DielectricStack<F>, bool) {
+        let dielectric = self.dielectric(incident.wavelength());
+        let index_of = |top: Option<Dielectric<F>>| top.map_or(F::one(), |top| top.index_of_coin);
+
+        let stack = incident.stack();
+        let stack = if incident.inside() && !stack.contains(dielectric.id) {
+            stack.push(dielectric) // Lost track of entering, say it was last
+        } else {
+            stack
+        };
+
+        let (next, real) = if incident.inside() {
+            let real = stack.top().is_none_or(|top| top.id == dielectric.id);
+            (stack.remove(dielectric.id), real)
+        } else {
+            let real = stack.top().is_none_or(|top| dielectric.priority >= top.priority);
+            (stack.push(dielectric), real)
+        };
+
+        (index_of(stack.top()), index_of(next.top()), next, real)
+    }
+
+    // Reflects with the average reflectance, the attenuation fixes up each channel
+    fn refract_coated(&self, incident: &Incident<F>, coating: &ThinFilm<F>, outer: F, inner: F, seed: F) -> RefractIncident<F> {
+        let w_i = incident.w_i();
+        let normal = incident.normal();
+        let refraction_ratio = outer / inner;
+
+        let cos_theta = w_i.dot(normal).min(F::one());
+        let sin_theta = (F::one() - cos_theta * cos_theta).sqrt();
+        if refraction_ratio * sin_theta > F::one() { // Total internal reflection
+            return RefractIncident {
+                w_r: reflect(-w_i, normal),
+                flip: false,
+                attenuation: Vector3D::one(),
+            };
+        }
+
+        let r = film_reflectance(
+            Some(coating),
+            cos_theta,
+            outer,
+            Vector3D::one() * inner,
+            Vector3D::zero(),
+            incident.wavelength(),
+        );
+        let p = (r.x + r.y + r.z) / F::from(3u32).unwrap();
+        if seed < p {
+            RefractIncident {
+                w_r: reflect(-w_i, normal),
+                flip: false,
+                attenuation: r / p,
+            }
+        } else {
+            RefractIncident {
+                w_r: refract(-w_i, normal, refraction_ratio),
+                flip: true,
+                attenuation: (Vector3D::one() - r) / (F::one() - p),
+            }
+        }
+    }
+}
+
 impl<F: Float> Refractor<F> for Refract<F> {
     fn sample_refracted(
         &self,
@@ -44,20 +203,14 @@ impl<F: Float> Refractor<F> for Refract<F> {
         inside: bool,
         seed: F,
     ) -> (bool, Vector3D<F>) {
+        let index_of_coin = self.index_of_coin(None); // No path to take a wavelength from
         let refraction_ratio = if inside { // Hit from inside
-            self.index_of_coin
+            index_of_coin
         } else { // Hit from outside
-            F::one() / self.index_of_coin
+            F::one() / index_of_coin
         };
 
-        let cos_theta = w_i.dot(normal).min(F::one());
-        let sin_theta = (F::one() - cos_theta * cos_theta).sqrt();
-        let cannot_refract = refraction_ratio * sin_theta > F::one();
-        if cannot_refract || reflectance(cos_theta, refraction_ratio) > seed {
-            (false, reflect(-w_i, normal))
-        } else {
-            (true, refract(-w_i, normal, refraction_ratio))
-        }
+        scatter(w_i, normal, refraction_ratio, seed)
     }
 }
 
@@ -67,10 +220,45 @@ impl<F: Float> Material<F> for Refract<F> {
         incident: Incident<F>,
         seed: F,
     ) -> ProcessedIncident<F> {
-        let refract = self.refract(&incident, seed);
+        let (outer, inner, next, real) = self.nesting(&incident);
+
+        let mut refract = if !real { // Carries on straight, only the stack changes
+            RefractIncident {
+                w_r: -incident.w_i(),
+                flip: true,
+                attenuation: Vector3D::one(),
+            }
+        } else if let Some(coating) = &self.coating {
+            self.refract_coated(&incident, coating, outer, inner, seed)
+        } else {
+            let (flip, w_r) = scatter(incident.w_i(), incident.normal(), outer / inner, seed);
+            RefractIncident {
+                w_r,
+                flip,
+                attenuation: Vector3D::one(),
+            }
+        };
 
+        // Absorbed by whatever the path crossed to get here
+        let stack = incident.stack();
+        let dielectric = self.dielectric(incident.wavelength());
+        let crossed = if incident.inside() && !stack.contains(dielectric.id) {
+            Some(dielectric)
+        } else {
+            stack.top()
+        };
+        if let Some(crossed) = crossed {
+            let optical_depth = crossed.absorption * -incident.distance();
+            refract.attenuation = refract.attenuation * Vector3D::new(
+                optical_depth.x.exp(),
+                optical_depth.y.exp(),
+                optical_depth.z.exp(),
+            );
+        }
+
+        let stack = if refract.flip { next } else { stack };
         ProcessedIncident::from_refract(
-            incident,
+            incident.with_stack(stack),
             refract,
         )
     }
@@ -79,7 +267,46 @@ impl<F: Float> Material<F> for Refract<F> {
         self.interact(incident, seed)
     }
 
+    fn pdf(&self, _incident: &Incident<F>, _w_r: Vector3D<F>) -> F {
+        F::zero() // Specular
+    }
+
     fn focus(&self) -> bool {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Direction a ray at wavelength leaves crown glass in, hitting it at 45°
+    fn bend(glass: &Refract<f64>, wavelength: Option<f64>) -> Vector3D<f64> {
+        let w_i = Vector3D::new(-1.0, 0.0, 1.0).norm();
+        let incident = Incident::new(Vector3D::zero(), Vector3D::new(0.0, 0.0, 1.0), 1.0, w_i, false)
+            .with_wavelength(wavelength);
+
+        let next_ray = glass.interact(incident, 1.0).next_ray(); // Past the Fresnel reflectance
+        assert_eq!(next_ray.wavelength(), wavelength);
+
+        next_ray.direction()
+    }
+
+    #[test]
+    fn dispersion_follows_path_wavelength() {
+        let glass = Refract::<f64>::new(1.5).with_cauchy(1.5046, 0.0042);
+
+        assert!((glass.index_of_coin(None) - 1.5).abs() < 1e-12);
+        assert!(glass.index_of_coin(Some(400.0)) > glass.index_of_coin(Some(700.0)));
+
+        // Blue bends toward the normal more than red
+        let (blue, red) = (bend(&glass, Some(400.0)), bend(&glass, Some(700.0)));
+        assert!(blue.z < 0.0 && red.z < 0.0);
+        assert!(blue.x.abs() < red.x.abs());
+
+        // RGB paths use the plain index
+        let plain = bend(&glass, None);
+        let sin_theta = plain.x.abs() / plain.magnitude();
+        assert!((sin_theta - 0.5f64.sqrt() / 1.5).abs() < 1e-9);
+    }
+}
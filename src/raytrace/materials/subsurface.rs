@@ -0,0 +1,94 @@
+use crate::raytrace::{Incident, ProcessedIncident};
+use crate::raytrace::materials::{Interface, Material, Refract};
+use crate::raytrace::media::{Homogeneous, Medium};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::Arc;
+
+// Skin, wax, marble or milk. Light walks through a scattering medium filling
+// the closed object, so only integrators that handle media see more than glass
+pub struct Subsurface<F: Float> {
+    sigma_a: Vector3D<F>,
+    sigma_s: Vector3D<F>,
+    medium: Arc<dyn Medium<F>>,
+
+    // None when index-matched, then light sampling reaches the inside
+    boundary: Option<Refract<F>>,
+}
+
+impl<F: Float> Subsurface<F> {
+    // Albedo is the color after many bounces, mean free path the average
+    // distance between them, both per channel
+    pub fn new(albedo: Vector3D<F>, mean_free_path: Vector3D<F>) -> Self {
+        let single = Vector3D::new(
+            single_scattering_albedo(albedo.x),
+            single_scattering_albedo(albedo.y),
+            single_scattering_albedo(albedo.z),
+        );
+        let sigma_t = Vector3D::one() / mean_free_path;
+        let sigma_s = sigma_t * single;
+        let sigma_a = sigma_t - sigma_s;
+
+        Self {
+            sigma_a,
+            sigma_s,
+            medium: Arc::new(Homogeneous::new(sigma_a, sigma_s, F::zero())),
+            boundary: None,
+        }
+    }
+
+    pub fn with_index_of_coin(mut self, index_of_coin: F) -> Self {
+        self.boundary = Some(Refract::new(index_of_coin));
+        self
+    }
+
+    pub fn with_anisotropy(mut self, g: F) -> Self {
+        self.medium = Arc::new(Homogeneous::new(self.sigma_a, self.sigma_s, g));
+        self
+    }
+}
+
+// Inverts the multiple scattering albedo of a semi-infinite slab, fitted by
+// Chiang, Kutz and Burley
+fn single_scattering_albedo<F: Float>(albedo: F) -> F {
+    let c = |v: f64| F::from(v).unwrap();
+    let albedo = albedo.max(F::zero()).min(F::one());
+    let root = c(4.09712) + c(4.20863) * albedo
+        - (c(9.59217) + c(41.6808) * albedo + c(17.7126) * albedo * albedo).sqrt();
+
+    F::one() - root * root
+}
+
+impl<F: Float> Material<F> for Subsurface<F> {
+    fn interact(
+        &self,
+        incident: Incident<F>,
+        seed: F,
+    ) -> ProcessedIncident<F> {
+        match &self.boundary {
+            Some(boundary) => boundary.interact(incident, seed),
+            None => Interface::new().interact(incident, seed),
+        }
+    }
+
+    fn interact_predetermined(&self, incident: Incident<F>, _w_r: Vector3D<F>, _pdf: F, seed: F) -> ProcessedIncident<F> {
+        self.interact(incident, seed)
+    }
+
+    fn pdf(&self, _incident: &Incident<F>, _w_r: Vector3D<F>) -> F {
+        F::zero() // Specular
+    }
+
+    fn focus(&self) -> bool {
+        true
+    }
+
+    fn interface(&self) -> bool {
+        self.boundary.is_none()
+    }
+
+    fn medium(&self) -> Option<Arc<dyn Medium<F>>> {
+        Some(self.medium.clone())
+    }
+}
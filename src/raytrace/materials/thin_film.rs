@@ -0,0 +1,108 @@
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use num::complex::Complex64;
+
+// Wavelengths in nm the red, green and blue channels are evaluated at
+const CHANNEL_WAVELENGTHS: [f64; 3] = [650.0, 550.0, 450.0];
+
+// Transparent layer whose reflections interfere, as on soap bubbles, oil
+// slicks and anodised metal
+#[derive(Debug, Clone, Copy)]
+pub struct ThinFilm<F: Float> {
+    // In nm
+    thickness: F,
+    index_of_coin: F,
+}
+
+impl<F: Float> ThinFilm<F> {
+    pub fn new(thickness: F, index_of_coin: F) -> Self {
+        Self {
+            thickness,
+            index_of_coin,
+        }
+    }
+}
+
+// Fresnel reflectance from outside of index outer onto a base of complex
+// index eta + ik, through the film if there is one. Per channel, or the same
+// in every channel at the wavelength of a spectral path
+pub(super) fn reflectance<F: Float>(
+    film: Option<&ThinFilm<F>>,
+    cos_theta: F,
+    outer: F,
+    eta: Vector3D<F>,
+    k: Vector3D<F>,
+    wavelength: Option<F>,
+) -> Vector3D<F> {
+    let f = |v: F| v.to_f64().unwrap();
+    let cos_theta = f(cos_theta.max(F::zero()).min(F::one()));
+    let outer = f(outer);
+    let (thickness, film_index) = match film {
+        Some(film) => (f(film.thickness), f(film.index_of_coin)),
+        None => (0.0, outer), // Matches the outside, so reflects nothing itself
+    };
+
+    let eta = [eta.x, eta.y, eta.z].map(f);
+    let k = [k.x, k.y, k.z].map(f);
+    let at = |channel: usize, wavelength: f64| F::from(airy(
+        cos_theta,
+        outer,
+        film_index,
+        thickness,
+        Complex64::new(eta[channel], k[channel]),
+        wavelength,
+    )).unwrap();
+
+    if let Some(wavelength) = wavelength {
+        let wavelength = f(wavelength);
+        let mut channel = 0;
+        for (i, center) in CHANNEL_WAVELENGTHS.into_iter().enumerate() {
+            if (wavelength - center).abs() < (wavelength - CHANNEL_WAVELENGTHS[channel]).abs() {
+                channel = i;
+            }
+        }
+
+        return Vector3D::one() * at(channel, wavelength);
+    }
+
+    Vector3D::new(
+        at(0, CHANNEL_WAVELENGTHS[0]),
+        at(1, CHANNEL_WAVELENGTHS[1]),
+        at(2, CHANNEL_WAVELENGTHS[2]),
+    )
+}
+
+// Airy summation of every reflection inside the film, averaged over s and p
+// polarization
+fn airy(cos_theta: f64, outer: f64, film: f64, thickness: f64, base: Complex64, wavelength: f64) -> f64 {
+    let sin2 = 1.0 - cos_theta * cos_theta;
+    let n0 = Complex64::new(outer, 0.0);
+    let n1 = Complex64::new(film, 0.0);
+    let n2 = base;
+
+    // Snell's law, complex past the critical angle and inside conductors
+    let cos_in = |n: Complex64| (Complex64::new(1.0, 0.0) - n0 * n0 * sin2 / (n * n)).sqrt();
+    let c0 = Complex64::new(cos_theta, 0.0);
+    let c1 = cos_in(n1);
+    let c2 = cos_in(n2);
+
+    let r_s = |ni: Complex64, ci: Complex64, nj: Complex64, cj: Complex64| {
+        (ni * ci - nj * cj) / (ni * ci + nj * cj)
+    };
+    let r_p = |ni: Complex64, ci: Complex64, nj: Complex64, cj: Complex64| {
+        (nj * ci - ni * cj) / (nj * ci + ni * cj)
+    };
+
+    // Extra path length of each round trip through the film, as phase
+    let delta = n1 * c1 * (4.0 * std::f64::consts::PI * thickness / wavelength);
+    let shift = (Complex64::new(0.0, 1.0) * delta).exp();
+    let total = |r01: Complex64, r12: Complex64| {
+        ((r01 + r12 * shift) / (Complex64::new(1.0, 0.0) + r01 * r12 * shift)).norm_sqr()
+    };
+
+    let s = total(r_s(n0, c0, n1, c1), r_s(n1, c1, n2, c2));
+    let p = total(r_p(n0, c0, n1, c1), r_p(n1, c1, n2, c2));
+
+    (0.5 * (s + p)).min(1.0)
+}
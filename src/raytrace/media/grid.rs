@@ -0,0 +1,394 @@
+use crate::raytrace::Ray;
+use crate::raytrace::media::{HenyeyGreenstein, Medium, MediumSample};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum GridError {
+    Io(PathBuf, std::io::Error),
+    // Ended before the three dimensions
+    Truncated,
+    // A zero dimension, or more voxels than can be addressed
+    BadDims([usize; 3]),
+    // Densities found, against the product of the dimensions
+    WrongCount(usize, usize),
+    // Negative or not finite, and where
+    BadDensity(usize, f64),
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "can't read {}: {}", path.display(), err),
+            Self::Truncated => write!(f, "density grid ends before its dimensions"),
+            Self::BadDims(dims) => write!(f, "bad density grid dimensions {:?}", dims),
+            Self::WrongCount(found, expected) => write!(f, "{} densities where {} were expected", found, expected),
+            Self::BadDensity(i, density) => write!(f, "density {} at voxel {} is negative or not finite", density, i),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
+// Density on a dense grid spanning an axis-aligned box, scaling sigma_a and
+// sigma_s. Nothing outside the box
+pub struct Grid<F: Float> {
+    min: Vector3D<F>,
+    max: Vector3D<F>,
+
+    dims: [usize; 3],
+    density: Vec<F>,
+    max_density: F,
+
+    sigma_a: Vector3D<F>,
+    sigma_s: Vector3D<F>,
+
+    phase: HenyeyGreenstein<F>,
+}
+
+impl<F: Float> Grid<F> {
+    // The raw file holds nx, ny and nz as little-endian u32, then nx * ny * nz
+    // little-endian f32 densities with x varying fastest, then y
+    pub fn new(
+        source: String,
+        min: Vector3D<F>,
+        max: Vector3D<F>,
+        sigma_a: Vector3D<F>,
+        sigma_s: Vector3D<F>,
+        g: F,
+    ) -> Result<Self, GridError> {
+        let raw = std::fs::read(&source)
+            .map_err(|err| GridError::Io(PathBuf::from(source), err))?;
+        let (dims, density) = Self::parse(&raw)?;
+
+        Self::from_density(dims, density, min, max, sigma_a, sigma_s, g)
+    }
+
+    pub fn parse(raw: &[u8]) -> Result<([usize; 3], Vec<F>), GridError> {
+        let mut words = raw.chunks_exact(4)
+            .map(|word| [word[0], word[1], word[2], word[3]]);
+
+        let mut dims = [0; 3];
+        for dim in dims.iter_mut() {
+            *dim = u32::from_le_bytes(words.next().ok_or(GridError::Truncated)?) as usize;
+        }
+
+        let density = words
+            .map(|word| F::from(f32::from_le_bytes(word)).unwrap())
+            .collect::<Vec<F>>();
+        check(dims, &density)?;
+
+        Ok((dims, density))
+    }
+
+    pub fn from_density(
+        dims: [usize; 3],
+        density: Vec<F>,
+        min: Vector3D<F>,
+        max: Vector3D<F>,
+        sigma_a: Vector3D<F>,
+        sigma_s: Vector3D<F>,
+        g: F,
+    ) -> Result<Self, GridError> {
+        check(dims, &density)?;
+
+        let mut max_density = F::zero();
+        for &d in &density {
+            max_density = max_density.max(d);
+        }
+
+        Ok(Self {
+            min,
+            max,
+            dims,
+            density,
+            max_density,
+            sigma_a,
+            sigma_s,
+            phase: HenyeyGreenstein::new(g),
+        })
+    }
+}
+
+// Every dimension at least one voxel, and one density for each voxel
+fn check<F: Float>(dims: [usize; 3], density: &[F]) -> Result<(), GridError> {
+    let count = dims[0].checked_mul(dims[1])
+        .and_then(|count| count.checked_mul(dims[2]))
+        .filter(|&count| count > 0)
+        .ok_or(GridError::BadDims(dims))?;
+    if density.len() != count {
+        return Err(GridError::WrongCount(density.len(), count));
+    }
+
+    match density.iter().position(|d| !d.is_finite() || *d < F::zero()) {
+        Some(i) => Err(GridError::BadDensity(i, density[i].to_f64().unwrap())),
+        None => Ok(()),
+    }
+}
+
+impl<F: Float> Grid<F> {
+    fn sigma_t(&self) -> Vector3D<F> {
+        self.sigma_a + self.sigma_s
+    }
+
+    // Bounds the extinction of every channel anywhere in the grid
+    fn majorant(&self) -> F {
+        let sigma_t = self.sigma_t();
+
+        self.max_density * sigma_t.x.max(sigma_t.y).max(sigma_t.z)
+    }
+
+    fn lookup(&self, x: usize, y: usize, z: usize) -> F {
+        self.density[(z * self.dims[1] + y) * self.dims[0] + x]
+    }
+
+    // Trilinear between voxel centers
+    fn density_at(&self, coords: Vector3D<F>) -> F {
+        let extent = self.max - self.min;
+        let local = (coords - self.min) / extent;
+        if local.x < F::zero() || local.y < F::zero() || local.z < F::zero()
+            || local.x > F::one() || local.y > F::one() || local.z > F::one() {
+            return F::zero();
+        }
+
+        let _half = F::from(0.5f32).unwrap();
+        let local = [local.x, local.y, local.z];
+
+        let mut cell = [(0, 0, F::zero()); 3];
+        for axis in 0..3 {
+            let n = self.dims[axis];
+            let g = (local[axis] * F::from(n).unwrap() - _half).max(F::zero());
+            let i = g.floor().to_usize().unwrap().min(n - 1);
+
+            cell[axis] = (i, (i + 1).min(n - 1), (g - F::from(i).unwrap()).min(F::one()));
+        }
+        let [(x0, x1, dx), (y0, y1, dy), (z0, z1, dz)] = cell;
+
+        let lerp = |a: F, b: F, t: F| a + (b - a) * t;
+        let d00 = lerp(self.lookup(x0, y0, z0), self.lookup(x1, y0, z0), dx);
+        let d10 = lerp(self.lookup(x0, y1, z0), self.lookup(x1, y1, z0), dx);
+        let d01 = lerp(self.lookup(x0, y0, z1), self.lookup(x1, y0, z1), dx);
+        let d11 = lerp(self.lookup(x0, y1, z1), self.lookup(x1, y1, z1), dx);
+
+        lerp(lerp(d00, d10, dy), lerp(d01, d11, dy), dz)
+    }
+
+    // Part of the ray inside the box, up to t_max
+    fn clip(&self, ray: &Ray<F>, t_max: F) -> Option<(F, F)> {
+        let origin = [ray.origin().x, ray.origin().y, ray.origin().z];
+        let direction = [ray.direction().x, ray.direction().y, ray.direction().z];
+        let min = [self.min.x, self.min.y, self.min.z];
+        let max = [self.max.x, self.max.y, self.max.z];
+
+        let mut t0 = F::zero();
+        let mut t1 = t_max;
+        for axis in 0..3 {
+            if direction[axis] == F::zero() {
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+
+            let inv = F::one() / direction[axis];
+            let near = (min[axis] - origin[axis]) * inv;
+            let far = (max[axis] - origin[axis]) * inv;
+            t0 = t0.max(near.min(far));
+            t1 = t1.min(near.max(far));
+        }
+
+        if t0 >= t1 {
+            return None;
+        }
+
+        Some((t0, t1))
+    }
+}
+
+impl<F: Float> Medium<F> for Grid<F> {
+    fn name(&self) -> String {
+        "grid".to_string()
+    }
+
+    // Delta tracking of one channel picked at random, the weight is the balance
+    // heuristic over having tracked any of the three
+    fn sample(&self, ray: &Ray<F>, t_max: F) -> MediumSample<F> {
+        let passed = |weight| MediumSample {
+            t: t_max,
+            scattered: false,
+            weight,
+            sigma_s: Vector3D::zero(),
+        };
+
+        let majorant = self.majorant();
+        if majorant <= F::zero() {
+            return passed(Vector3D::one());
+        }
+        let (t0, t1) = match self.clip(ray, t_max) {
+            Some(clipped) => clipped,
+            None => return passed(Vector3D::one()),
+        };
+
+        let _three = F::from(3u32).unwrap();
+        let channel = (F::sample_rand() * _three).to_usize().unwrap().min(2);
+        let mean = |v: Vector3D<F>| (v.x + v.y + v.z) / _three;
+
+        // Chance of every null collision so far had each channel been tracked
+        let mut pdf: Vector3D<F> = Vector3D::one();
+        let mut t = t0;
+        loop {
+            t = t - (F::one() - F::sample_rand()).ln() / majorant;
+            if t >= t1 {
+                return passed(pdf / mean(pdf));
+            }
+
+            let density = self.density_at(ray.origin() + ray.direction() * t);
+            let sigma_t = self.sigma_t() * density;
+
+            if F::sample_rand() * majorant < [sigma_t.x, sigma_t.y, sigma_t.z][channel] { // Real collision
+                return MediumSample {
+                    t,
+                    scattered: true,
+                    weight: pdf / mean(pdf * sigma_t),
+                    sigma_s: self.sigma_s * density,
+                };
+            }
+
+            pdf = pdf * (Vector3D::one() - sigma_t / majorant);
+        }
+    }
+
+    // Ratio tracking, every null collision keeps the chance it was null
+    fn transmittance(&self, ray: &Ray<F>, t_max: F) -> Vector3D<F> {
+        let majorant = self.majorant();
+        if majorant <= F::zero() {
+            return Vector3D::one();
+        }
+        let (t0, t1) = match self.clip(ray, t_max) {
+            Some(clipped) => clipped,
+            None => return Vector3D::one(),
+        };
+
+        let mut transmittance: Vector3D<F> = Vector3D::one();
+        let mut t = t0;
+        loop {
+            t = t - (F::one() - F::sample_rand()).ln() / majorant;
+            if t >= t1 {
+                break;
+            }
+
+            let density = self.density_at(ray.origin() + ray.direction() * t);
+            let sigma_t = self.sigma_t() * density;
+            transmittance = transmittance * (Vector3D::one() - sigma_t / majorant);
+        }
+
+        transmittance
+    }
+
+    fn phase(&self) -> HenyeyGreenstein<F> {
+        self.phase
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(dims: [u32; 3], density: &[f32]) -> Vec<u8> {
+        dims.iter().flat_map(|d| d.to_le_bytes())
+            .chain(density.iter().flat_map(|d| d.to_le_bytes()))
+            .collect()
+    }
+
+    fn unit_box(dims: [usize; 3], density: Vec<f64>) -> Grid<f64> {
+        Grid::from_density(
+            dims,
+            density,
+            Vector3D::zero(),
+            Vector3D::one(),
+            Vector3D::one(),
+            Vector3D::one(),
+            0.0,
+        ).unwrap()
+    }
+
+    #[test]
+    fn parse_grid() {
+        let (dims, density) = Grid::<f64>::parse(&raw([2, 1, 3], &[0.0, 1.0, 2.0, 3.0, 4.0, 5.5])).unwrap();
+
+        assert_eq!(dims, [2, 1, 3]);
+        assert_eq!(density, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.5]);
+    }
+
+    #[test]
+    fn reject_malformed() {
+        let parse = |raw: &[u8]| Grid::<f64>::parse(raw);
+
+        assert!(matches!(parse(&raw([2, 2, 2], &[])[..8]), Err(GridError::Truncated)));
+        assert!(matches!(parse(&raw([0, 0, 0], &[])), Err(GridError::BadDims(_))));
+        assert!(matches!(parse(&raw([4, 0, 1], &[])), Err(GridError::BadDims(_))));
+        assert!(matches!(parse(&raw([u32::MAX; 3], &[1.0])), Err(GridError::BadDims(_))));
+        assert!(matches!(parse(&raw([2, 1, 1], &[1.0])), Err(GridError::WrongCount(1, 2))));
+        assert!(matches!(parse(&raw([2, 1, 1], &[1.0, 1.0, 1.0])), Err(GridError::WrongCount(3, 2))));
+
+        for bad in [-1.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(parse(&raw([2, 1, 1], &[1.0, bad])), Err(GridError::BadDensity(1, _))));
+        }
+    }
+
+    #[test]
+    fn report_missing_file() {
+        let grid = Grid::<f64>::new(
+            "no/such/grid.raw".to_string(),
+            Vector3D::zero(),
+            Vector3D::one(),
+            Vector3D::one(),
+            Vector3D::one(),
+            0.0,
+        );
+
+        assert!(matches!(grid, Err(GridError::Io(..))));
+    }
+
+    #[test]
+    fn density_between_voxels() {
+        let grid = unit_box([2, 1, 1], vec![1.0, 3.0]);
+        let at = |x: f64| grid.density_at(Vector3D::new(x, 0.5, 0.5));
+
+        // Flat out to the faces, linear between the voxel centers
+        assert_eq!(at(0.0), 1.0);
+        assert_eq!(at(0.25), 1.0);
+        assert_eq!(at(0.5), 2.0);
+        assert_eq!(at(0.75), 3.0);
+        assert_eq!(at(1.0), 3.0);
+
+        assert_eq!(at(-0.1), 0.0);
+        assert_eq!(at(1.1), 0.0);
+        assert_eq!(grid.majorant(), 6.0);
+    }
+
+    #[test]
+    fn density_trilinear() {
+        // x varies fastest, then y
+        let grid = unit_box([2, 2, 2], vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+
+        assert_eq!(grid.density_at(Vector3D::new(0.25, 0.25, 0.25)), 0.0);
+        assert_eq!(grid.density_at(Vector3D::new(0.75, 0.75, 0.75)), 7.0);
+        assert_eq!(grid.density_at(Vector3D::new(0.75, 0.25, 0.25)), 1.0);
+        assert_eq!(grid.density_at(Vector3D::new(0.25, 0.75, 0.25)), 2.0);
+        assert_eq!(grid.density_at(Vector3D::new(0.25, 0.25, 0.75)), 4.0);
+        assert!((grid.density_at(Vector3D::new(0.5, 0.5, 0.5)) - 3.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn single_voxel() {
+        let grid = unit_box([1, 1, 1], vec![0.5]);
+
+        for x in [0.0, 0.3, 1.0] {
+            assert_eq!(grid.density_at(Vector3D::new(x, x, x)), 0.5);
+        }
+    }
+}
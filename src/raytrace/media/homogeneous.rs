@@ -0,0 +1,86 @@
+use crate::raytrace::Ray;
+use crate::raytrace::media::{HenyeyGreenstein, Medium, MediumSample};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Same absorption and scattering everywhere
+pub struct Homogeneous<F: Float> {
+    sigma_a: Vector3D<F>,
+    sigma_s: Vector3D<F>,
+
+    phase: HenyeyGreenstein<F>,
+}
+
+impl<F: Float> Homogeneous<F> {
+    pub fn new(sigma_a: Vector3D<F>, sigma_s: Vector3D<F>, g: F) -> Self {
+        Self {
+            sigma_a,
+            sigma_s,
+            phase: HenyeyGreenstein::new(g),
+        }
+    }
+
+    fn sigma_t(&self) -> Vector3D<F> {
+        self.sigma_a + self.sigma_s
+    }
+}
+
+fn exp<F: Float>(v: Vector3D<F>) -> Vector3D<F> {
+    Vector3D::new(v.x.exp(), v.y.exp(), v.z.exp())
+}
+
+impl<F: Float> Medium<F> for Homogeneous<F> {
+    fn name(&self) -> String {
+        "homogeneous".to_string()
+    }
+
+    fn sample(&self, ray: &Ray<F>, t_max: F) -> MediumSample<F> {
+        let sigma_t = self.sigma_t();
+        // Distances follow the average extinction, the weight fixes up each channel
+        let sigma = (sigma_t.x + sigma_t.y + sigma_t.z) / F::from(3u32).unwrap();
+        if sigma <= F::zero() {
+            return MediumSample {
+                t: t_max,
+                scattered: false,
+                weight: Vector3D::one(),
+                sigma_s: Vector3D::zero(),
+            };
+        }
+
+        let t = -(F::one() - F::sample_rand()).ln() / sigma;
+        if t < t_max {
+            let transmittance = self.transmittance(ray, t);
+            let pdf = sigma * (-sigma * t).exp();
+
+            return MediumSample {
+                t,
+                scattered: true,
+                weight: transmittance / pdf,
+                sigma_s: self.sigma_s,
+            };
+        }
+
+        let transmittance = self.transmittance(ray, t_max);
+        let pdf = (-sigma * t_max).exp();
+        let weight = if pdf > F::zero() { transmittance / pdf } else { Vector3D::zero() };
+
+        MediumSample {
+            t: t_max,
+            scattered: false,
+            weight,
+            sigma_s: Vector3D::zero(),
+        }
+    }
+
+    fn transmittance(&self, _ray: &Ray<F>, t_max: F) -> Vector3D<F> {
+        if t_max == F::max_value() { // Never reaches a surface
+            return Vector3D::zero();
+        }
+
+        exp(self.sigma_t() * -t_max)
+    }
+
+    fn phase(&self) -> HenyeyGreenstein<F> {
+        self.phase
+    }
+}
@@ -0,0 +1,36 @@
+mod phase;
+mod homogeneous;
+mod grid;
+
+pub use phase::HenyeyGreenstein;
+pub use homogeneous::Homogeneous;
+pub use grid::{Grid, GridError};
+
+use crate::raytrace::Ray;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Distance picked along a ray through a medium
+#[derive(Debug, Clone, Copy)]
+pub struct MediumSample<F: Float> {
+    pub t: F,
+    // Whether the ray scattered at t before reaching t_max
+    pub scattered: bool,
+
+    // Transmittance over the pdf of the distance
+    pub weight: Vector3D<F>,
+    // Scattering coefficient at t, zero if not scattered
+    pub sigma_s: Vector3D<F>,
+}
+
+// Participating medium filling the scene or the inside of a closed object
+pub trait Medium<F: Float> {
+    fn name(&self) -> String;
+
+    // Samples where the ray interacts first, up to the surface at t_max
+    fn sample(&self, ray: &Ray<F>, t_max: F) -> MediumSample<F>;
+
+    fn transmittance(&self, ray: &Ray<F>, t_max: F) -> Vector3D<F>;
+
+    fn phase(&self) -> HenyeyGreenstein<F>;
+}
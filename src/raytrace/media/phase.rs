@@ -0,0 +1,55 @@
+use crate::raytrace::to_world;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Henyey-Greenstein phase function, g > 0 scatters forward
+#[derive(Debug, Clone, Copy)]
+pub struct HenyeyGreenstein<F: Float> {
+    g: F,
+}
+
+impl<F: Float> HenyeyGreenstein<F> {
+    pub fn new(g: F) -> Self {
+        Self {
+            g,
+        }
+    }
+}
+
+impl<F: Float> HenyeyGreenstein<F> {
+    // Density of scattering toward w_r, w_i points back along the ray
+    pub fn p(&self, w_i: Vector3D<F>, w_r: Vector3D<F>) -> F {
+        let _two = F::from(2u32).unwrap();
+        let _1_4_pi = F::FRAC_1_PI() / F::from(4u32).unwrap();
+
+        let g = self.g;
+        let cos_theta = w_i.dot(w_r);
+        let denom = F::one() + g * g + _two * g * cos_theta;
+
+        _1_4_pi * (F::one() - g * g) / (denom * denom.max(F::zero()).sqrt())
+    }
+
+    // Picks w_r proportional to p, which is also its pdf
+    pub fn sample(&self, w_i: Vector3D<F>) -> (Vector3D<F>, F) {
+        let _two = F::from(2u32).unwrap();
+
+        let g = self.g;
+        let x_1 = F::sample_rand();
+        let x_2 = F::sample_rand();
+
+        let cos_theta = if g.abs() < F::from(1e-3f32).unwrap() {
+            F::one() - _two * x_1
+        } else {
+            let sqr = (F::one() - g * g) / (F::one() + g - _two * g * x_1);
+            -(F::one() + g * g - sqr * sqr) / (_two * g)
+        };
+        let sin_theta = (F::one() - cos_theta * cos_theta).max(F::zero()).sqrt();
+        let phi = _two * F::PI() * x_2;
+
+        // Measured from w_i, so cos_theta = 1 scatters straight back
+        let local_w_r = Vector3D::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        let w_r = to_world(local_w_r, w_i);
+
+        (w_r, self.p(w_i, w_r))
+    }
+}
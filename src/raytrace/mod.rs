@@ -3,19 +3,27 @@ mod scene;
 mod incident;
 mod renderer;
 mod bvh;
+mod camera;
 
-pub use ray::Ray;
+pub use ray::{Dielectric, DielectricStack, Ray};
 pub use scene::{Scene, SceneGenerator};
 pub use incident::{Incident, ProcessedIncident};
 pub use renderer::Renderer;
 pub use self::bvh::BVH;
+pub use camera::Camera;
 
 use crate::types::Float;
 use crate::vector::Vector3D;
 
 pub mod objects;
 pub mod materials;
+pub mod environment;
+pub mod media;
+pub mod lights;
+pub mod integrators;
+pub mod textures;
 pub mod tree;
+pub mod spectral;
 
 pub fn to_world<F: Float>(w: Vector3D<F>, normal: Vector3D<F>) -> Vector3D<F> {
     if normal.x.abs() > normal.y.abs() {
@@ -30,3 +38,10 @@ pub fn to_world<F: Float>(w: Vector3D<F>, normal: Vector3D<F>) -> Vector3D<F> {
         b * w.x + c * w.y + normal * w.z
     }
 }
+
+// Bounds the relative error after n rounded operations
+pub fn gamma<F: Float>(n: u32) -> F {
+    let n_eps = F::from(n).unwrap() * F::epsilon() / F::from(2u32).unwrap();
+
+    n_eps / (F::one() - n_eps)
+}
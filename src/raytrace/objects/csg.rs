@@ -0,0 +1,151 @@
+use crate::raytrace::{Incident, Ray};
+use crate::raytrace::objects::RayTraceable;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::Arc;
+
+use super::base::Shape;
+
+// Gives up on rays crossing more boundaries than this
+const MAX_CROSSINGS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    // The first without the second
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(&self, a: bool, b: bool) -> bool {
+        match self {
+            Self::Union => a || b,
+            Self::Intersection => a && b,
+            Self::Difference => a && !b,
+        }
+    }
+}
+
+// Boundaries of one closed object along a ray, found one after the other
+struct Crossings<'a, F: Float> {
+    object: &'a dyn RayTraceable<F>,
+    direction: Vector3D<F>,
+
+    ray: Option<Ray<F>>,
+    travelled: F,
+}
+
+impl<'a, F: Float> Crossings<'a, F> {
+    fn new(object: &'a dyn RayTraceable<F>, ray: &Ray<F>) -> Self {
+        Self {
+            object,
+            direction: ray.direction(),
+            ray: Some(Ray::new_unchecked(ray.origin(), ray.direction())),
+            travelled: F::zero(),
+        }
+    }
+}
+
+impl<F: Float> Iterator for Crossings<'_, F> {
+    // Distance along the original ray, and the hit on the object
+    type Item = (F, Incident<F>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let incident = self.object.hit(&self.ray?);
+        let incident = match incident {
+            Some(incident) => incident,
+            None => {
+                self.ray = None;
+                return None;
+            }
+        };
+
+        self.travelled = self.travelled + incident.distance();
+        self.ray = Some(Ray::new_unchecked(incident.spawn_origin(self.direction), self.direction));
+
+        Some((self.travelled, incident))
+    }
+}
+
+// Closed objects combined by where their insides overlap, only used for their shape
+pub struct Csg<F: Float> {
+    op: CsgOp,
+
+    a: Arc<dyn RayTraceable<F>>,
+    b: Arc<dyn RayTraceable<F>>,
+}
+
+impl<F: Float> Csg<F> {
+    pub fn new(op: CsgOp, a: Arc<dyn RayTraceable<F>>, b: Arc<dyn RayTraceable<F>>) -> Self {
+        Self { op, a, b }
+    }
+}
+
+impl<F: Float> Shape<F> for Csg<F> {
+    fn name(&self) -> String {
+        "csg".to_string()
+    }
+
+    // Walks the crossings of both in order until the combined inside flips
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        let mut a = Crossings::new(self.a.as_ref(), ray).peekable();
+        let mut b = Crossings::new(self.b.as_ref(), ray).peekable();
+
+        // Leaving first means the ray started inside
+        let mut in_a = a.peek().is_some_and(|(_, incident)| incident.inside());
+        let mut in_b = b.peek().is_some_and(|(_, incident)| incident.inside());
+
+        for _ in 0..MAX_CROSSINGS {
+            let inside = self.op.inside(in_a, in_b);
+
+            let take_a = match (a.peek(), b.peek()) {
+                (Some((t_a, _)), Some((t_b, _))) => t_a <= t_b,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => return None,
+            };
+            let (t, incident) = if take_a {
+                in_a = !in_a;
+                a.next()?
+            } else {
+                in_b = !in_b;
+                b.next()?
+            };
+
+            if self.op.inside(in_a, in_b) != inside {
+                // The normal already faces the ray, only which side it came from changes
+                return Some(
+                    Incident::new(incident.coords(), incident.normal(), t, -ray.direction(), inside)
+                        .with_uv(incident.uv())
+                        .with_error(incident.error())
+                );
+            }
+        }
+
+        None
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        let (min_a, max_a) = self.a.bounds();
+        let (min_b, max_b) = self.b.bounds();
+
+        match self.op {
+            CsgOp::Union => (min_a.min(min_b), max_a.max(max_b)),
+            CsgOp::Intersection => (min_a.max(min_b), max_a.min(max_b)),
+            CsgOp::Difference => (min_a, max_a),
+        }
+    }
+
+    // Unknown, so it never acts as a light
+    fn area(&self) -> F {
+        F::infinity()
+    }
+
+    fn sample_location(&self) -> (Vector3D<F>, Vector3D<F>, (F, F)) {
+        let (min, max) = self.bounds();
+
+        ((min + max) / F::from(2u32).unwrap(), Vector3D::new(F::zero(), F::zero(), F::one()), (F::zero(), F::zero()))
+    }
+}
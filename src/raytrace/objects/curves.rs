@@ -0,0 +1,207 @@
+use crate::raytrace::{BVH, Incident, ProcessedIncident, Ray, to_world};
+use crate::raytrace::bvh::GenericBound;
+use crate::raytrace::materials::Material;
+use crate::raytrace::media::Medium;
+use crate::raytrace::objects::{hit_box, Bounded, LightInteractable, PartialBounded, RayTraceable};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::Arc;
+
+use super::base;
+use super::base::Shape;
+
+// Many curves under one material, for fur, hair or grass. Like a mesh with its
+// triangles, only the curves the BVH finds along a ray are intersected
+pub struct Curves<F: Float> {
+    curves: Vec<base::Curve<F>>,
+    // Running total of the curve areas, to pick one by area
+    areas: Vec<F>,
+
+    bvh: BVH<usize, F>,
+    min_pt: Vector3D<F>,
+    max_pt: Vector3D<F>,
+
+    material: Box<dyn Material<F>>,
+}
+
+impl<F: Float> Curves<F> {
+    pub fn new(curves: Vec<base::Curve<F>>, material: Box<dyn Material<F>>) -> Self {
+        let mut min_pt = Vector3D::max_value();
+        let mut max_pt = Vector3D::min_value();
+        let mut bound_vec = Vec::with_capacity(curves.len());
+        for (i, curve) in curves.iter().enumerate() {
+            let (min, max) = curve.bounds();
+            min_pt = min_pt.min(min);
+            max_pt = max_pt.max(max);
+
+            bound_vec.push(GenericBound::new(i, min, max));
+        }
+        let bvh = BVH::new(bound_vec);
+
+        let mut total = F::zero();
+        let areas = curves.iter().map(|curve| {
+            total = total + curve.area();
+            total
+        }).collect();
+
+        Self {
+            curves,
+            areas,
+
+            bvh,
+            min_pt,
+            max_pt,
+
+            material,
+        }
+    }
+
+    // Every strand split into segments, width goes from root to tip
+    pub fn from_strands(strands: &[Vec<Vector3D<F>>], width: (F, F), kind: base::CurveKind,
+                        material: Box<dyn Material<F>>) -> Self {
+        let curves = strands.iter()
+            .flat_map(|strand| base::Curve::strand(strand, width, kind))
+            .collect();
+
+        Self::new(curves, material)
+    }
+}
+
+impl<F: Float> Bounded<F> for Curves<F> {
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        let mut min_incident: Option<Incident<F>> = None;
+        let mut min_distance = F::max_value();
+        for bound in self.bvh.hit(ray) {
+            let id = bound.get();
+            if let Some(incident) = self.curves[id].hit(ray) {
+                if incident.distance() < min_distance {
+                    min_distance = incident.distance();
+                    min_incident = Some(incident.with_primitive(id));
+                }
+            }
+        }
+
+        min_incident
+    }
+}
+
+impl<F: Float> PartialBounded<F> for Curves<F> {
+    fn partial_hit(&self, ray: &Ray<F>) -> bool {
+        hit_box(self.min_pt, self.max_pt, ray)
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        (self.min_pt, self.max_pt)
+    }
+}
+
+impl<F: Float> LightInteractable<F> for Curves<F> {
+    fn interact(
+        &self,
+        incident: Incident<F>,
+        seed: F,
+    ) -> ProcessedIncident<F> {
+        self.material.interact(incident, seed)
+    }
+
+    fn interact_predetermined(
+        &self,
+        incident: Incident<F>,
+        w_r: Vector3D<F>,
+        pdf: F,
+        seed: F) -> ProcessedIncident<F> {
+        self.material.interact_predetermined(
+            incident,
+            w_r,
+            pdf,
+            seed,
+        )
+    }
+
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F {
+        self.material.pdf(incident, w_r)
+    }
+}
+
+impl<F: Float> RayTraceable<F> for Curves<F> {
+    fn name(&self) -> String {
+        "curves".to_string()
+    }
+
+    fn area(&self) -> F {
+        self.areas.last().copied().unwrap_or(F::zero())
+    }
+    fn emit(&self) -> Option<Vector3D<F>> {
+        if self.area() <= F::zero() { // Could never be sampled
+            return None;
+        }
+
+        self.material.emission()
+    }
+    fn emit_at(&self, incident: &Incident<F>) -> Vector3D<F> {
+        self.material.emit(incident)
+    }
+
+    fn focus(&self) -> bool {
+        self.material.focus()
+    }
+    fn interface(&self, _incident: &Incident<F>) -> bool {
+        self.material.interface()
+    }
+    fn medium(&self, _incident: &Incident<F>) -> Option<Arc<dyn Medium<F>>> {
+        self.material.medium()
+    }
+
+    // A curve by its share of the area, then uniform over it
+    fn sample_position(&self) -> (Vector3D<F>, Vector3D<F>, F) {
+        let target = F::sample_rand() * self.area();
+        let id = self.areas.partition_point(|&area| area <= target).min(self.curves.len() - 1);
+        let (coords, normal, _) = self.curves[id].sample_location();
+
+        (coords, normal, F::one() / self.area())
+    }
+
+    fn sample_direction(&self, _coords: Vector3D<F>, normal: Vector3D<F>) -> (Vector3D<F>, F) {
+        let x_1 = F::sample_rand();
+        let x_2 = F::sample_rand();
+        let z = x_1; // Uniform over the hemisphere
+        let r = (F::one() - z * z).sqrt();
+        let phi: F = F::from(2u32).unwrap() * F::PI() * x_2;
+
+        let direction = to_world(Vector3D::new(r * phi.cos(), r * phi.sin(), z), normal);
+
+        (direction, F::from(0.5).unwrap() * F::FRAC_1_PI())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytrace::materials::Diffuse;
+
+    #[test]
+    fn nearest_curve() {
+        let strands: Vec<Vec<Vector3D<f64>>> = (0..8).map(|i| {
+            let z = i as f64;
+            vec![Vector3D::new(-1.0, 0.0, z), Vector3D::new(0.0, 0.1, z), Vector3D::new(1.0, 0.0, z)]
+        }).collect();
+        let curves = Curves::from_strands(
+            &strands,
+            (0.2, 0.2),
+            base::CurveKind::Cylinder,
+            Box::new(Diffuse::new(Vector3D::one())),
+        );
+
+        // Down the row of strands, the first one is in front
+        let ray = Ray::new(Vector3D::new(0.0, 0.05, -5.0), Vector3D::new(0.0, 0.0, 1.0));
+        assert!(curves.partial_hit(&ray));
+        let incident = curves.hit(&ray).unwrap();
+        assert!(incident.primitive() < 2);
+        assert!((incident.distance() - 4.9).abs() < 0.05);
+
+        let miss = Ray::new(Vector3D::new(0.0, 5.0, -5.0), Vector3D::new(0.0, 0.0, 1.0));
+        assert!(!curves.partial_hit(&miss));
+        assert!(curves.hit(&miss).is_none());
+    }
+}
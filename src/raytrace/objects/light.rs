@@ -1,12 +1,18 @@
 use crate::raytrace::{Incident, ProcessedIncident, Ray};
+use crate::raytrace::lights::IesProfile;
 use crate::raytrace::objects::{Bounded, LightInteractable, LightSample, PartialBounded, RayTraceable};
+use crate::color::luminance;
 use crate::types::Float;
 use crate::vector::Vector3D;
 
+use std::sync::Arc;
+
 pub struct Light<F: Float> {
     inner: Box<dyn RayTraceable<F>>,
 
     diff: Vector3D<F>,
+
+    profile: Option<(Arc<IesProfile<F>>, Vector3D<F>)>,
 }
 
 impl<F: Float> Light<F> {
@@ -18,6 +24,23 @@ impl<F: Float> Light<F> {
             inner,
 
             diff,
+
+            profile: None,
+        }
+    }
+
+    // Profile nadir points along axis
+    pub fn with_profile(mut self, profile: Arc<IesProfile<F>>, axis: Vector3D<F>) -> Self {
+        self.profile = Some((profile, axis.norm()));
+        self
+    }
+}
+
+impl<F: Float> Light<F> {
+    fn radiance(&self, direction: Vector3D<F>) -> Vector3D<F> {
+        match &self.profile {
+            Some((profile, axis)) => self.diff * profile.eval(direction, *axis),
+            None => self.diff,
         }
     }
 }
@@ -40,6 +63,10 @@ impl<F: Float> LightInteractable<F> for Light<F> {
             seed,
         )
     }
+
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F {
+        self.inner.pdf(incident, w_r)
+    }
 }
 
 impl<F: Float> Bounded<F> for Light<F> {
@@ -52,6 +79,10 @@ impl<F: Float> PartialBounded<F> for Light<F> {
     fn partial_hit(&self, ray: &Ray<F>) -> bool {
         self.inner.partial_hit(ray)
     }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        self.inner.bounds()
+    }
 }
 
 impl<F: Float> RayTraceable<F> for Light<F> {
@@ -65,16 +96,56 @@ impl<F: Float> RayTraceable<F> for Light<F> {
     fn emit(&self) -> Option<Vector3D<F>> {
         Some(self.diff)
     }
+    fn emit_at(&self, incident: &Incident<F>) -> Vector3D<F> {
+        self.radiance(incident.w_i())
+    }
+    fn power(&self) -> F {
+        let average = match &self.profile {
+            Some((profile, _)) => profile.average(),
+            None => F::one(),
+        };
+
+        self.area() * luminance(self.diff).max(F::zero()) * average
+    }
 
     fn focus(&self) -> bool {
         self.inner.focus()
     }
 
+    fn normal_cone(&self) -> (Vector3D<F>, F) {
+        self.inner.normal_cone()
+    }
+
     fn sample_position(&self) -> (Vector3D<F>, Vector3D<F>, F) {
         self.inner.sample_position()
     }
 
+    fn sample_position_from(&self, reference: Vector3D<F>) -> (Vector3D<F>, Vector3D<F>, F) {
+        self.inner.sample_position_from(reference)
+    }
+
+    fn pdf_position_from(&self, reference: Vector3D<F>, coords: Vector3D<F>) -> F {
+        self.inner.pdf_position_from(reference, coords)
+    }
+
     fn sample_direction(&self, coords: Vector3D<F>, normal: Vector3D<F>) -> (Vector3D<F>, F) {
         self.inner.sample_direction(coords, normal)
     }
+
+    fn pdf_direction(&self, coords: Vector3D<F>, normal: Vector3D<F>, direction: Vector3D<F>) -> F {
+        self.inner.pdf_direction(coords, normal, direction)
+    }
+
+    fn sample_light(&self) -> LightSample<F> {
+        let (coords, normal, position_pdf) = self.sample_position();
+        let (direction, direction_pdf) = self.sample_direction(coords, normal);
+
+        LightSample {
+            ray: Ray::new(coords, direction),
+            normal,
+            emit: self.radiance(direction),
+            position_pdf,
+            direction_pdf,
+        }
+    }
 }
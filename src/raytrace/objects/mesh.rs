@@ -1,11 +1,16 @@
 use crate::raytrace::{BVH, Incident, ProcessedIncident, Ray};
 use crate::raytrace::bvh::GenericBound;
-use crate::raytrace::materials::Material;
-use crate::raytrace::objects::{Bounded, LightInteractable, LightSample, PartialBounded, RayTraceable};
+use crate::raytrace::materials::{Diffuse, Emissive, Material};
+use crate::raytrace::media::Medium;
+use crate::raytrace::objects::{hit_box, Bounded, LightInteractable, LightSample, PartialBounded, RayTraceable, Triangle};
+use crate::raytrace::textures::{Constant, Image, Texture};
 
 use crate::types::Float;
 use crate::vector::Vector3D;
 
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use super::base;
 
 pub struct Mesh<F: Float> {
@@ -14,16 +19,76 @@ pub struct Mesh<F: Float> {
     bound: BoundImpl<F>,
     partial_bound: PartialBoundImpl<F>,
 
-    material: Box<dyn Material<F>>,
+    normal_cone: (Vector3D<F>, F),
+
+    // Index into materials for every triangle
+    triangle_materials: Vec<usize>,
+    materials: Vec<Arc<dyn Material<F>>>,
+
+    // Emissive faces as lights, built once so they can be found again when hit
+    triangle_emitters: Vec<Option<usize>>,
+    emitters: Vec<Arc<dyn RayTraceable<F>>>,
+
+    medium: Option<Arc<dyn Medium<F>>>,
 }
 
 impl<F: Float> Mesh<F> {
-    pub fn new(source: String, material: Box<dyn Material<F>>) -> Self {
+    pub fn new(source: String, material: Box<dyn Material<F>>) -> Result<Self, base::ObjError> {
+        let name = source.clone();
+
+        Ok(Self::from_inner(name, base::Mesh::new(source)?, material))
+    }
+
+    // Smoothed and displaced as it is loaded, so the BVH holds the fine triangles
+    pub fn new_subdivided(source: String, material: Box<dyn Material<F>>,
+                          subdivision: base::Subdivision<F>) -> Result<Self, base::ObjError> {
         let name = source.clone();
 
-        let inner = base::Mesh::new(source);
+        Ok(Self::from_inner(name, base::Mesh::new_subdivided(source, &subdivision)?, material))
+    }
+
+    fn from_inner(name: String, inner: base::Mesh<F>, material: Box<dyn Material<F>>) -> Self {
+
+        // Faces whose MTL material has Ke glow, the rest use the given material
+        let mut materials: Vec<Arc<dyn Material<F>>> = vec![Arc::from(material)];
+        let mut emissive_ids = HashMap::new();
+        let mut triangle_materials = Vec::with_capacity(inner.triangles().len());
+        for mtl_name in inner.triangle_materials() {
+            let id = match mtl_name {
+                Some(mtl_name) => match inner.materials().get(mtl_name) {
+                    Some(mtl) if mtl.emissive() => *emissive_ids
+                        .entry(mtl_name.clone())
+                        .or_insert_with(|| {
+                            materials.push(Arc::new(emissive_from_mtl(mtl)));
+                            materials.len() - 1
+                        }),
+                    _ => 0,
+                },
+                None => 0,
+            };
+            triangle_materials.push(id);
+        }
+
+        let mut emitters: Vec<Arc<dyn RayTraceable<F>>> = Vec::new();
+        let mut triangle_emitters = Vec::with_capacity(inner.triangles().len());
+        for (i, triangle) in inner.triangles().iter().enumerate() {
+            let material = &materials[triangle_materials[i]];
+            if material.emission().is_none() {
+                triangle_emitters.push(None);
+                continue;
+            }
+
+            triangle_emitters.push(Some(emitters.len()));
+            emitters.push(Arc::new(Triangle::new(
+                format!("{}_{}", name, i),
+                triangle.clone(),
+                i,
+                material.clone(),
+            )));
+        }
 
         let partial_bound = PartialBoundImpl::new(&inner);
+        let normal_cone = normal_cone(&inner);
         let bound = BoundImpl::new(inner);
 
         Self {
@@ -32,9 +97,65 @@ impl<F: Float> Mesh<F> {
             bound,
             partial_bound,
 
-            material,
+            normal_cone,
+
+            triangle_materials,
+            materials,
+
+            triangle_emitters,
+            emitters,
+
+            medium: None,
         }
     }
+
+    // Fills the closed mesh, pair with Interface for a surface light passes through
+    pub fn with_medium(mut self, medium: Arc<dyn Medium<F>>) -> Self {
+        self.medium = Some(medium);
+        self
+    }
+}
+
+fn emissive_from_mtl<F: Float>(mtl: &base::ObjMaterial<F>) -> Emissive<F> {
+    let texture: Box<dyn Texture<F>> = match &mtl.map_ke {
+        Some(path) => Box::new(Image::new(path.to_string_lossy().to_string())),
+        None => Box::new(Constant::new(mtl.ke)),
+    };
+    // Ke scales the emission map when both are given
+    let power = if mtl.map_ke.is_some() && mtl.ke != Vector3D::zero() {
+        mtl.ke.x.max(mtl.ke.y.max(mtl.ke.z))
+    } else {
+        F::one()
+    };
+
+    Emissive::new(texture, power)
+        .with_surface(Box::new(Diffuse::new(mtl.kd)))
+}
+
+impl<F: Float> Mesh<F> {
+    fn material(&self, incident: &Incident<F>) -> &Arc<dyn Material<F>> {
+        &self.materials[self.triangle_materials[incident.primitive()]]
+    }
+}
+
+fn normal_cone<F: Float>(inner: &base::Mesh<F>) -> (Vector3D<F>, F) {
+    let mut axis = Vector3D::zero();
+    for triangle in inner.triangles() {
+        axis += triangle.normal() * triangle.area();
+    }
+
+    let omnidirectional = (Vector3D::new(F::zero(), F::zero(), F::one()), -F::one());
+    if axis.magnitude() <= F::epsilon() { // Normals cancel out
+        return omnidirectional;
+    }
+
+    let axis = axis.norm();
+    let mut cos_theta = F::one();
+    for triangle in inner.triangles() {
+        cos_theta = cos_theta.min(axis.dot(triangle.normal()));
+    }
+
+    (axis, cos_theta)
 }
 
 #[derive(Clone)]
@@ -99,7 +220,7 @@ impl<F: Float> BoundImpl<F> {
             if let Some(incident) = self.inner.triangles()[id].hit(ray) {
                 if incident.distance() < min_distance {
                     min_distance = incident.distance();
-                    min_incident = Some(incident);
+                    min_incident = Some(incident.with_primitive(id));
                 }
             }
         }
@@ -141,53 +262,7 @@ impl<F: Float> PartialBoundImpl<F> {
 
 impl<F: Float> PartialBoundImpl<F> {
     pub fn partial_hit(&self, ray: &Ray<F>) -> bool {
-        let origin = ray.origin();
-        let w_i = ray.direction();
-        let inv_dir = Vector3D::new(
-            F::one() / w_i.x,
-            F::one() / w_i.y,
-            F::one() / w_i.z,
-        );
-
-        let (tx_min, tx_max) = if w_i.x >= F::zero() {
-            (
-                (self.min_pt.x - origin.x) * inv_dir.x,
-                (self.max_pt.x - origin.x) * inv_dir.x,
-            )
-        } else {
-            (
-                (self.max_pt.x - origin.x) * inv_dir.x,
-                (self.min_pt.x - origin.x) * inv_dir.x,
-            )
-        };
-        let (ty_min, ty_max) = if w_i.y >= F::zero() {
-            (
-                (self.min_pt.y - origin.y) * inv_dir.y,
-                (self.max_pt.y - origin.y) * inv_dir.y,
-            )
-        } else {
-            (
-                (self.max_pt.y - origin.y) * inv_dir.y,
-                (self.min_pt.y - origin.y) * inv_dir.y,
-            )
-        };
-        let (tz_min, tz_max) = if w_i.z >= F::zero() {
-            (
-                (self.min_pt.z - origin.z) * inv_dir.z,
-                (self.max_pt.z - origin.z) * inv_dir.z,
-            )
-        } else {
-            (
-                (self.max_pt.z - origin.z) * inv_dir.z,
-                (self.min_pt.z - origin.z) * inv_dir.z,
-            )
-        };
-
-        let t_enter = tx_min.max(ty_min.max(tz_min));
-        let t_exit = tx_max.min(ty_max.min(tz_max));
-
-        let epsilon = F::from(1e-4f32).unwrap();
-        t_enter < t_exit + epsilon && t_exit > F::zero()
+        hit_box(self.min_pt, self.max_pt, ray)
     }
 }
 
@@ -201,6 +276,10 @@ impl<F: Float> PartialBounded<F> for Mesh<F> {
     fn partial_hit(&self, ray: &Ray<F>) -> bool {
         self.partial_bound.partial_hit(ray)
     }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        (self.partial_bound.min_pt, self.partial_bound.max_pt)
+    }
 }
 
 impl<F: Float> LightInteractable<F> for Mesh<F> {
@@ -209,7 +288,7 @@ impl<F: Float> LightInteractable<F> for Mesh<F> {
         incident: Incident<F>,
         seed: F,
     ) -> ProcessedIncident<F> {
-        self.material.interact(incident, seed)
+        self.material(&incident).interact(incident, seed)
     }
 
     fn interact_predetermined(
@@ -218,13 +297,17 @@ impl<F: Float> LightInteractable<F> for Mesh<F> {
         w_r: Vector3D<F>,
         pdf: F,
         seed: F) -> ProcessedIncident<F> {
-        self.material.interact_predetermined(
+        self.material(&incident).interact_predetermined(
             incident,
             w_r,
             pdf,
             seed,
         )
     }
+
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F {
+        self.material(incident).pdf(incident, w_r)
+    }
 }
 
 impl<F: Float> RayTraceable<F> for Mesh<F> {
@@ -236,11 +319,34 @@ impl<F: Float> RayTraceable<F> for Mesh<F> {
         self.bound.area()
     }
     fn emit(&self) -> Option<Vector3D<F>> {
-        None
+        None // Emissive faces are sampled one by one
+    }
+    fn emit_at(&self, incident: &Incident<F>) -> Vector3D<F> {
+        self.material(incident).emit(incident)
+    }
+    fn emitters(&self) -> Vec<Arc<dyn RayTraceable<F>>> {
+        self.emitters.clone()
+    }
+    fn emitter(&self, incident: &Incident<F>) -> Option<Arc<dyn RayTraceable<F>>> {
+        self.triangle_emitters[incident.primitive()]
+            .map(|id| self.emitters[id].clone())
     }
 
     fn focus(&self) -> bool {
-        self.material.focus()
+        self.materials.iter().any(|material| material.focus())
+    }
+    fn focus_at(&self, incident: &Incident<F>) -> bool {
+        self.material(incident).focus()
+    }
+    fn interface(&self, incident: &Incident<F>) -> bool {
+        self.material(incident).interface()
+    }
+    fn medium(&self, incident: &Incident<F>) -> Option<Arc<dyn Medium<F>>> {
+        self.medium.clone().or_else(|| self.material(incident).medium())
+    }
+
+    fn normal_cone(&self) -> (Vector3D<F>, F) {
+        self.normal_cone
     }
 
     fn sample_position(&self) -> (Vector3D<F>, Vector3D<F>, F) {
@@ -269,6 +375,7 @@ impl<F: Float> RayTraceable<F> for Mesh<F> {
         LightSample {
             ray,
             normal: triangle.normal(),
+            emit: self.emit().unwrap_or(Vector3D::zero()),
             position_pdf,
             direction_pdf,
         }
@@ -1,17 +1,34 @@
 mod sphere;
 mod mesh;
 mod light;
+mod triangle;
+mod point;
+mod spot;
+mod primitive;
+mod csg;
+mod curves;
 
 pub use sphere::Sphere;
 pub use mesh::Mesh;
 pub use light::Light;
+pub use triangle::Triangle;
+pub use point::PointLight;
+pub use spot::SpotLight;
+pub use primitive::{Cone, Csg, Cuboid, Curve, Cylinder, Disk, Plane, Primitive, Quad, Sdf};
+pub use csg::CsgOp;
+pub use curves::Curves;
+pub use base::{CurveKind, SdfNode, Subdivision};
 
 use crate::objects as base;
 
+use crate::color::luminance;
 use crate::raytrace::{Incident, ProcessedIncident, Ray, to_world};
+use crate::raytrace::media::Medium;
 use crate::types::Float;
 use crate::vector::Vector3D;
 
+use std::sync::Arc;
+
 pub trait LightInteractable<F: Float> {
     fn interact(
         &self,
@@ -26,6 +43,8 @@ pub trait LightInteractable<F: Float> {
         pdf: F,
         seed: F
     ) -> ProcessedIncident<F>;
+
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F;
 }
 
 pub trait Bounded<F: Float> {
@@ -34,6 +53,59 @@ pub trait Bounded<F: Float> {
 
 pub trait PartialBounded<F: Float> {
     fn partial_hit(&self, ray: &Ray<F>) -> bool;
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>);
+}
+
+// Slab test against an axis-aligned box, cheap enough to run before any real hit
+pub(crate) fn hit_box<F: Float>(min_pt: Vector3D<F>, max_pt: Vector3D<F>, ray: &Ray<F>) -> bool {
+    let origin = ray.origin();
+    let w_i = ray.direction();
+    let inv_dir = Vector3D::new(
+        F::one() / w_i.x,
+        F::one() / w_i.y,
+        F::one() / w_i.z,
+    );
+
+    let (tx_min, tx_max) = if w_i.x >= F::zero() {
+        (
+            (min_pt.x - origin.x) * inv_dir.x,
+            (max_pt.x - origin.x) * inv_dir.x,
+        )
+    } else {
+        (
+            (max_pt.x - origin.x) * inv_dir.x,
+            (min_pt.x - origin.x) * inv_dir.x,
+        )
+    };
+    let (ty_min, ty_max) = if w_i.y >= F::zero() {
+        (
+            (min_pt.y - origin.y) * inv_dir.y,
+            (max_pt.y - origin.y) * inv_dir.y,
+        )
+    } else {
+        (
+            (max_pt.y - origin.y) * inv_dir.y,
+            (min_pt.y - origin.y) * inv_dir.y,
+        )
+    };
+    let (tz_min, tz_max) = if w_i.z >= F::zero() {
+        (
+            (min_pt.z - origin.z) * inv_dir.z,
+            (max_pt.z - origin.z) * inv_dir.z,
+        )
+    } else {
+        (
+            (max_pt.z - origin.z) * inv_dir.z,
+            (min_pt.z - origin.z) * inv_dir.z,
+        )
+    };
+
+    let t_enter = tx_min.max(ty_min.max(tz_min));
+    let t_exit = tx_max.min(ty_max.min(tz_max));
+
+    let epsilon = F::from(1e-4f32).unwrap();
+    t_enter < t_exit + epsilon && t_exit > F::zero()
 }
 
 pub trait RayTraceable<F: Float>
@@ -41,12 +113,77 @@ pub trait RayTraceable<F: Float>
     fn name(&self) -> String;
 
     fn area(&self) -> F;
+    // Average emitted radiance, None if the object is not a light source itself
     fn emit(&self) -> Option<Vector3D<F>>;
+    fn emit_at(&self, incident: &Incident<F>) -> Vector3D<F> {
+        let _ = incident;
+        self.emit().unwrap_or(Vector3D::zero())
+    }
+    // Emitted power, used to pick between light sources
+    fn power(&self) -> F {
+        match self.emit() {
+            Some(emit) => self.area() * luminance(emit).max(F::zero()),
+            None => F::zero(),
+        }
+    }
+    // Point-like light that can only be reached by sampling it
+    fn delta(&self) -> bool {
+        false
+    }
+    // Parts of the object sampled as separate light sources
+    fn emitters(&self) -> Vec<Arc<dyn RayTraceable<F>>> {
+        Vec::new()
+    }
+    // The one of emitters that was hit
+    fn emitter(&self, incident: &Incident<F>) -> Option<Arc<dyn RayTraceable<F>>> {
+        let _ = incident;
+        None
+    }
 
+    // Has specular parts, caustic photons are aimed at these
     fn focus(&self) -> bool;
+    // Whether the part that was hit is specular
+    fn focus_at(&self, incident: &Incident<F>) -> bool {
+        let _ = incident;
+        self.focus()
+    }
+    // Invisible boundary of a medium, crossed without scattering
+    fn interface(&self, incident: &Incident<F>) -> bool {
+        let _ = incident;
+        false
+    }
+    // Medium filling the inside of a closed object
+    fn medium(&self, incident: &Incident<F>) -> Option<Arc<dyn Medium<F>>> {
+        let _ = incident;
+        None
+    }
+
+    // Axis and cosine of the half-angle spanning all surface normals
+    fn normal_cone(&self) -> (Vector3D<F>, F) {
+        (Vector3D::new(F::zero(), F::zero(), F::one()), -F::one())
+    }
 
     fn sample_position(&self) -> (Vector3D<F>, Vector3D<F>, F);
+    // Same as sample_position, but may favor the part visible from reference; pdf is per area
+    fn sample_position_from(&self, reference: Vector3D<F>) -> (Vector3D<F>, Vector3D<F>, F) {
+        let _ = reference;
+        self.sample_position()
+    }
+    // Area density of sample_position_from picking coords
+    fn pdf_position_from(&self, reference: Vector3D<F>, coords: Vector3D<F>) -> F {
+        let _ = (reference, coords);
+        F::one() / self.area()
+    }
     fn sample_direction(&self, coords: Vector3D<F>, normal: Vector3D<F>) -> (Vector3D<F>, F);
+    // Solid angle density of sample_direction picking direction
+    fn pdf_direction(&self, coords: Vector3D<F>, normal: Vector3D<F>, direction: Vector3D<F>) -> F {
+        let _ = coords;
+        if direction.dot(normal) > F::zero() {
+            return F::from(0.5f32).unwrap() * F::FRAC_1_PI();
+        }
+
+        F::zero()
+    }
 
     fn sample_light(&self) -> LightSample<F> {
         let (coords, normal, position_pdf) = self.sample_position();
@@ -57,6 +194,7 @@ pub trait RayTraceable<F: Float>
         LightSample {
             ray,
             normal,
+            emit: self.emit().unwrap_or(Vector3D::zero()),
             position_pdf,
             direction_pdf,
         }
@@ -66,8 +204,24 @@ pub trait RayTraceable<F: Float>
 pub struct LightSample<F: Float> {
     pub ray: Ray<F>,
     pub normal: Vector3D<F>,
+    pub emit: Vector3D<F>,
 
     pub position_pdf: F,
     pub direction_pdf: F,
 }
 
+impl<F: Float> LightSample<F> {
+    // The sampled point as a hit leaving along the ray, bounding the
+    // rounding error of its position
+    pub fn incident(&self) -> Incident<F> {
+        Incident::new(self.ray.origin(), self.normal, F::zero(), self.ray.direction(), false)
+    }
+
+    // The ray, started just far enough off the light not to hit it again
+    pub fn spawn_ray(&self) -> Ray<F> {
+        let direction = self.ray.direction();
+
+        Ray::new_unchecked(self.incident().spawn_origin(direction), direction)
+    }
+}
+
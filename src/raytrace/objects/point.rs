@@ -0,0 +1,147 @@
+use crate::raytrace::{Incident, ProcessedIncident, Ray, to_world};
+use crate::raytrace::lights::IesProfile;
+use crate::raytrace::objects::{Bounded, LightInteractable, LightSample, PartialBounded, RayTraceable};
+use crate::color::luminance;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::Arc;
+
+pub struct PointLight<F: Float> {
+    position: Vector3D<F>,
+
+    // Radiant intensity, per steradian
+    intensity: Vector3D<F>,
+
+    profile: Option<(Arc<IesProfile<F>>, Vector3D<F>)>,
+}
+
+impl<F: Float> PointLight<F> {
+    pub fn new(position: Vector3D<F>, intensity: Vector3D<F>) -> Self {
+        Self {
+            position,
+            intensity,
+            profile: None,
+        }
+    }
+
+    // Profile nadir points along axis
+    pub fn with_profile(mut self, profile: Arc<IesProfile<F>>, axis: Vector3D<F>) -> Self {
+        self.profile = Some((profile, axis.norm()));
+        self
+    }
+}
+
+impl<F: Float> PointLight<F> {
+    fn intensity_toward(&self, direction: Vector3D<F>) -> Vector3D<F> {
+        match &self.profile {
+            Some((profile, axis)) => self.intensity * profile.eval(direction, *axis),
+            None => self.intensity,
+        }
+    }
+}
+
+impl<F: Float> Bounded<F> for PointLight<F> {
+    fn hit(&self, _ray: &Ray<F>) -> Option<Incident<F>> {
+        None
+    }
+}
+
+impl<F: Float> PartialBounded<F> for PointLight<F> {
+    fn partial_hit(&self, _ray: &Ray<F>) -> bool {
+        false
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        (self.position, self.position)
+    }
+}
+
+impl<F: Float> LightInteractable<F> for PointLight<F> {
+    fn interact(&self, _incident: Incident<F>, _seed: F) -> ProcessedIncident<F> {
+        unreachable!("point lights cannot be hit")
+    }
+
+    fn interact_predetermined(
+        &self,
+        _incident: Incident<F>,
+        _w_r: Vector3D<F>,
+        _pdf: F,
+        _seed: F) -> ProcessedIncident<F> {
+        unreachable!("point lights cannot be hit")
+    }
+
+    fn pdf(&self, _incident: &Incident<F>, _w_r: Vector3D<F>) -> F {
+        F::zero()
+    }
+}
+
+impl<F: Float> RayTraceable<F> for PointLight<F> {
+    fn name(&self) -> String {
+        "point_light".to_string()
+    }
+
+    fn area(&self) -> F {
+        F::zero()
+    }
+    fn emit(&self) -> Option<Vector3D<F>> {
+        Some(self.intensity)
+    }
+    fn emit_at(&self, incident: &Incident<F>) -> Vector3D<F> {
+        self.intensity_toward(incident.w_i())
+    }
+    fn power(&self) -> F {
+        let average = match &self.profile {
+            Some((profile, _)) => profile.average(),
+            None => F::one(),
+        };
+
+        F::from(4u32).unwrap() * F::PI() * luminance(self.intensity).max(F::zero()) * average
+    }
+    fn delta(&self) -> bool {
+        true
+    }
+
+    fn focus(&self) -> bool {
+        false
+    }
+
+    fn sample_position(&self) -> (Vector3D<F>, Vector3D<F>, F) {
+        let axis = match &self.profile {
+            Some((_, axis)) => *axis,
+            None => Vector3D::new(F::zero(), -F::one(), F::zero()),
+        };
+
+        (self.position, axis, F::one())
+    }
+
+    fn sample_direction(&self, _coords: Vector3D<F>, normal: Vector3D<F>) -> (Vector3D<F>, F) {
+        let _two = F::from(2u32).unwrap();
+
+        let z = F::one() - _two * F::sample_rand();
+        let r = (F::one() - z * z).max(F::zero()).sqrt();
+        let phi = _two * F::PI() * F::sample_rand();
+
+        let direction = to_world(Vector3D::new(r * phi.cos(), r * phi.sin(), z), normal);
+        let direction_pdf = F::one() / (_two * _two * F::PI());
+
+        (direction, direction_pdf)
+    }
+
+    fn pdf_direction(&self, _coords: Vector3D<F>, _normal: Vector3D<F>, _direction: Vector3D<F>) -> F {
+        F::one() / (F::from(4u32).unwrap() * F::PI())
+    }
+
+    fn sample_light(&self) -> LightSample<F> {
+        let (coords, normal, position_pdf) = self.sample_position();
+        let (direction, direction_pdf) = self.sample_direction(coords, normal);
+
+        LightSample {
+            ray: Ray::new(coords, direction),
+            normal: direction, // No surface to be foreshortened
+            emit: self.intensity_toward(direction),
+            position_pdf,
+            direction_pdf,
+        }
+    }
+}
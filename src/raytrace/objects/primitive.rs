@@ -0,0 +1,270 @@
+use crate::vector::Vector3D;
+use crate::raytrace::{Incident, Ray, ProcessedIncident, to_world};
+use crate::raytrace::objects::{hit_box, Bounded, LightInteractable, LightSample, PartialBounded, RayTraceable};
+use crate::raytrace::materials::Material;
+use crate::raytrace::media::Medium;
+use crate::types::Float;
+
+use std::sync::Arc;
+
+use super::base;
+use super::base::Shape;
+use super::csg;
+use super::csg::CsgOp;
+
+// Analytic shape with a material, constructed through the aliases below
+pub struct Primitive<F: Float, S: Shape<F>> {
+    inner: S,
+
+    material: Box<dyn Material<F>>,
+
+    medium: Option<Arc<dyn Medium<F>>>,
+}
+
+pub type Quad<F> = Primitive<F, base::Quad<F>>;
+pub type Disk<F> = Primitive<F, base::Disk<F>>;
+pub type Plane<F> = Primitive<F, base::Plane<F>>;
+pub type Cuboid<F> = Primitive<F, base::Cuboid<F>>;
+pub type Cylinder<F> = Primitive<F, base::Cylinder<F>>;
+pub type Cone<F> = Primitive<F, base::Cone<F>>;
+pub type Sdf<F> = Primitive<F, base::Sdf<F>>;
+pub type Curve<F> = Primitive<F, base::Curve<F>>;
+pub type Csg<F> = Primitive<F, csg::Csg<F>>;
+
+impl<F: Float, S: Shape<F>> Primitive<F, S> {
+    pub fn from_shape(inner: S, material: Box<dyn Material<F>>) -> Self {
+        Self { inner, material, medium: None }
+    }
+
+    // Fills the inside, only meaningful for closed shapes
+    pub fn with_medium(mut self, medium: Arc<dyn Medium<F>>) -> Self {
+        self.medium = Some(medium);
+        self
+    }
+}
+
+impl<F: Float> Quad<F> {
+    pub fn new(corner: Vector3D<F>, edge_u: Vector3D<F>, edge_v: Vector3D<F>,
+               material: Box<dyn Material<F>>) -> Self {
+        Self::from_shape(base::Quad::new(corner, edge_u, edge_v), material)
+    }
+}
+
+impl<F: Float> Disk<F> {
+    pub fn new(center: Vector3D<F>, normal: Vector3D<F>, radius: F,
+               material: Box<dyn Material<F>>) -> Self {
+        Self::from_shape(base::Disk::new(center, normal, radius), material)
+    }
+}
+
+impl<F: Float> Plane<F> {
+    pub fn new(point: Vector3D<F>, normal: Vector3D<F>,
+               material: Box<dyn Material<F>>) -> Self {
+        Self::from_shape(base::Plane::new(point, normal), material)
+    }
+}
+
+impl<F: Float> Cuboid<F> {
+    pub fn new(min: Vector3D<F>, max: Vector3D<F>,
+               material: Box<dyn Material<F>>) -> Self {
+        Self::from_shape(base::Cuboid::new(min, max), material)
+    }
+}
+
+impl<F: Float> Cylinder<F> {
+    pub fn new(base: Vector3D<F>, axis: Vector3D<F>, radius: F,
+               material: Box<dyn Material<F>>) -> Self {
+        Self::from_shape(base::Cylinder::new(base, axis, radius), material)
+    }
+}
+
+impl<F: Float> Cone<F> {
+    pub fn new(base: Vector3D<F>, axis: Vector3D<F>, radius: F,
+               material: Box<dyn Material<F>>) -> Self {
+        Self::from_shape(base::Cone::new(base, axis, radius), material)
+    }
+}
+
+impl<F: Float> Sdf<F> {
+    pub fn new(root: base::SdfNode<F>,
+               material: Box<dyn Material<F>>) -> Self {
+        Self::from_shape(base::Sdf::new(root), material)
+    }
+}
+
+impl<F: Float> Curve<F> {
+    // width is at the first and last control point, Curves holds many of them
+    pub fn new(points: [Vector3D<F>; 4], width: (F, F), kind: base::CurveKind,
+               material: Box<dyn Material<F>>) -> Self {
+        Self::from_shape(base::Curve::new(points, width, kind), material)
+    }
+}
+
+// The children only give the shape, the material covers all of it
+impl<F: Float> Csg<F> {
+    pub fn union(a: Arc<dyn RayTraceable<F>>, b: Arc<dyn RayTraceable<F>>,
+                 material: Box<dyn Material<F>>) -> Self {
+        Self::from_shape(csg::Csg::new(CsgOp::Union, a, b), material)
+    }
+
+    pub fn intersection(a: Arc<dyn RayTraceable<F>>, b: Arc<dyn RayTraceable<F>>,
+                        material: Box<dyn Material<F>>) -> Self {
+        Self::from_shape(csg::Csg::new(CsgOp::Intersection, a, b), material)
+    }
+
+    pub fn difference(a: Arc<dyn RayTraceable<F>>, b: Arc<dyn RayTraceable<F>>,
+                      material: Box<dyn Material<F>>) -> Self {
+        Self::from_shape(csg::Csg::new(CsgOp::Difference, a, b), material)
+    }
+}
+
+impl<F: Float, S: Shape<F>> Bounded<F> for Primitive<F, S> {
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        self.inner.hit(ray)
+    }
+}
+
+impl<F: Float, S: Shape<F>> PartialBounded<F> for Primitive<F, S> {
+    fn partial_hit(&self, ray: &Ray<F>) -> bool {
+        let (min, max) = self.inner.bounds();
+
+        hit_box(min, max, ray)
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        self.inner.bounds()
+    }
+}
+
+impl<F: Float, S: Shape<F>> LightInteractable<F> for Primitive<F, S> {
+    fn interact(
+        &self,
+        incident: Incident<F>,
+        seed: F,
+    ) -> ProcessedIncident<F> {
+        self.material.interact(incident, seed)
+    }
+
+    fn interact_predetermined(
+        &self,
+        incident: Incident<F>,
+        w_r: Vector3D<F>,
+        pdf: F,
+        seed: F) -> ProcessedIncident<F> {
+        self.material.interact_predetermined(
+            incident,
+            w_r,
+            pdf,
+            seed,
+        )
+    }
+
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F {
+        self.material.pdf(incident, w_r)
+    }
+}
+
+impl<F: Float, S: Shape<F>> RayTraceable<F> for Primitive<F, S> {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn area(&self) -> F {
+        self.inner.area()
+    }
+    fn emit(&self) -> Option<Vector3D<F>> {
+        if self.inner.area().is_infinite() { // Could never be sampled
+            return None;
+        }
+
+        self.material.emission()
+    }
+    fn emit_at(&self, incident: &Incident<F>) -> Vector3D<F> {
+        self.material.emit(incident)
+    }
+
+    fn focus(&self) -> bool {
+        self.material.focus()
+    }
+    fn interface(&self, _incident: &Incident<F>) -> bool {
+        self.material.interface()
+    }
+    fn medium(&self, _incident: &Incident<F>) -> Option<Arc<dyn Medium<F>>> {
+        self.medium.clone().or_else(|| self.material.medium())
+    }
+
+    fn normal_cone(&self) -> (Vector3D<F>, F) {
+        let (axis, cos_theta) = self.inner.normal_cone();
+        if self.material.two_sided() {
+            return (axis, -F::one());
+        }
+
+        (axis, cos_theta)
+    }
+
+    fn sample_position(&self) -> (Vector3D<F>, Vector3D<F>, F) {
+        let (coords, normal, _) = self.inner.sample_location();
+
+        (coords, normal, F::one() / self.area())
+    }
+
+    fn sample_direction(&self, _coords: Vector3D<F>, normal: Vector3D<F>) -> (Vector3D<F>, F) {
+        let local_direction = {
+            let x_1 = F::sample_rand();
+            let x_2 = F::sample_rand();
+            let z = x_1; // Uniform over the hemisphere
+            let r = (F::one() - z * z).sqrt();
+            let phi: F = F::from(2u32).unwrap() * F::PI() * x_2;
+
+            Vector3D::new(r * phi.cos(), r * phi.sin(), z)
+        };
+        let direction = to_world(local_direction, normal);
+        let direction_pdf = F::from(0.5).unwrap() * F::FRAC_1_PI();
+        if !self.material.two_sided() {
+            return (direction, direction_pdf);
+        }
+
+        let _half = F::from(0.5).unwrap();
+        if F::sample_rand() < _half { // Leave through the back face
+            return (-direction, direction_pdf * _half);
+        }
+
+        (direction, direction_pdf * _half)
+    }
+
+    fn pdf_direction(&self, _coords: Vector3D<F>, normal: Vector3D<F>, direction: Vector3D<F>) -> F {
+        let _half = F::from(0.5).unwrap();
+        if self.material.two_sided() {
+            return _half * _half * F::FRAC_1_PI();
+        }
+        if direction.dot(normal) > F::zero() {
+            return _half * F::FRAC_1_PI();
+        }
+
+        F::zero()
+    }
+
+    fn sample_light(&self) -> LightSample<F> {
+        let (coords, normal, uv) = self.inner.sample_location();
+        let (direction, direction_pdf) = self.sample_direction(coords, normal);
+
+        let back = direction.dot(normal) < F::zero();
+        let normal = if back { -normal } else { normal };
+
+        let incident = Incident::new(
+            coords,
+            normal,
+            F::zero(),
+            direction,
+            back,
+        ).with_uv(uv);
+
+        LightSample {
+            ray: Ray::new(coords, direction),
+            normal,
+            emit: self.material.emit(&incident),
+            position_pdf: F::one() / self.area(),
+            direction_pdf,
+        }
+    }
+}
@@ -1,11 +1,15 @@
 use num::traits::real::Real;
 use crate::vector::Vector3D;
-use crate::raytrace::{Incident, Ray, ProcessedIncident, to_world};
+use crate::raytrace::{gamma, Incident, Ray, ProcessedIncident, to_world};
 use crate::raytrace::objects::{Bounded, LightInteractable, LightSample, PartialBounded, RayTraceable};
 use crate::raytrace::materials::Material;
+use crate::raytrace::media::Medium;
 use crate::types::Float;
 
+use std::sync::Arc;
+
 use super::base;
+use super::base::quadratic;
 
 pub struct Sphere<F: Float> {
     inner: base::Sphere<F>,
@@ -13,6 +17,8 @@ pub struct Sphere<F: Float> {
     bound: BoundImpl<F>,
 
     material: Box<dyn Material<F>>,
+
+    medium: Option<Arc<dyn Medium<F>>>,
 }
 
 impl<F: Float> Sphere<F> {
@@ -22,7 +28,13 @@ impl<F: Float> Sphere<F> {
 
         let bound = BoundImpl::new(inner);
 
-        Self { inner, bound, material }
+        Self { inner, bound, material, medium: None }
+    }
+
+    // Fills the sphere, pair with Interface for a surface light passes through
+    pub fn with_medium(mut self, medium: Arc<dyn Medium<F>>) -> Self {
+        self.medium = Some(medium);
+        self
     }
 }
 
@@ -33,28 +45,14 @@ struct BoundImpl<F: Float> {
     radius_2: F,
 }
 
-fn quadratic<F: Float>(a: F, b: F, c: F) -> Option<(F, F)> {
-    let _neg_half = F::from(-0.5).unwrap();
+fn spherical_uv<F: Float>(normal: Vector3D<F>) -> (F, F) {
+    let _two = F::from(2u32).unwrap();
+    let _half = F::from(0.5).unwrap();
 
-    let discr: F = b * b - F::from(4).unwrap() * a * c;
-    if discr < F::zero() {
-        return None;
-    }
-    if discr == F::zero() {
-        let x = _neg_half * b / a;
-        return Some((x, x));
-    }
-
-    let q = if b > F::zero() {
-        _neg_half * (b + discr.sqrt())
-    } else {
-        _neg_half * (b - discr.sqrt())
-    };
+    let u = normal.z.atan2(normal.x) / (_two * F::PI()) + _half;
+    let v = F::one() - normal.y.max(-F::one()).min(F::one()).acos() / F::PI();
 
-    let x0 = q / a;
-    let x1 = c / q;
-
-    Some((x0.min(x1), x0.max(x1)))
+    (u, v)
 }
 
 impl<F: Float> BoundImpl<F> {
@@ -66,7 +64,7 @@ impl<F: Float> BoundImpl<F> {
         }
     }
 
-    fn hit_impl(&self, ray: &Ray<F>, inv: bool) -> Option<Incident<F>> {
+    fn hit_impl(&self, ray: &Ray<F>) -> Option<Incident<F>> {
         let distance = ray.origin() - self.inner.center();
         let direction = ray.direction();
 
@@ -79,17 +77,23 @@ impl<F: Float> BoundImpl<F> {
                 return None;
             }
 
-            let incident_dist = if t0 < F::zero() { t1 } else { t0 };
-            let incident_coords = ray.origin() + ray.direction() * incident_dist;
+            let inv = t0 < F::zero(); // Starts inside, so leaves through the far side
+            let incident_dist = if inv { t1 } else { t0 };
+            let local = ray.origin() + ray.direction() * incident_dist - self.inner.center();
+
+            // Back onto the surface, the root itself may be far off
+            let local = local * (self.inner.radius() / local.magnitude());
+            let incident_coords = self.inner.center() + local;
+            let error = local.abs() * gamma::<F>(5) + incident_coords.abs() * gamma::<F>(1);
 
-            let normal = (incident_coords - self.inner.center()).norm();
+            let normal = local.norm();
 
             return Some(
                 Incident::new(incident_coords,
                               if inv { -normal } else { normal },
                               incident_dist,
                               -ray.direction(),
-                              inv)
+                              inv).with_uv(spherical_uv(normal)).with_error(error)
             );
         }
 
@@ -99,7 +103,7 @@ impl<F: Float> BoundImpl<F> {
 
 impl<F: Float> Bounded<F> for BoundImpl<F> {
     fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
-        self.hit_impl(ray, ray.inside())
+        self.hit_impl(ray)
     }
 }
 
@@ -113,6 +117,13 @@ impl<F: Float> PartialBounded<F> for Sphere<F> {
     fn partial_hit(&self, ray: &Ray<F>) -> bool {
         self.hit(ray).is_some()
     }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        (
+            self.inner.center() - self.inner.radius(),
+            self.inner.center() + self.inner.radius(),
+        )
+    }
 }
 
 impl<F: Float> LightInteractable<F> for Sphere<F> {
@@ -137,6 +148,10 @@ impl<F: Float> LightInteractable<F> for Sphere<F> {
             seed,
         )
     }
+
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F {
+        self.material.pdf(incident, w_r)
+    }
 }
 
 impl<F: Float> RayTraceable<F> for Sphere<F> {
@@ -148,37 +163,101 @@ impl<F: Float> RayTraceable<F> for Sphere<F> {
         self.inner.area()
     }
     fn emit(&self) -> Option<Vector3D<F>> {
-        None
+        self.material.emission()
+    }
+    fn emit_at(&self, incident: &Incident<F>) -> Vector3D<F> {
+        self.material.emit(incident)
     }
 
     fn focus(&self) -> bool {
         self.material.focus()
     }
+    fn interface(&self, _incident: &Incident<F>) -> bool {
+        self.material.interface()
+    }
+    fn medium(&self, _incident: &Incident<F>) -> Option<Arc<dyn Medium<F>>> {
+        self.medium.clone().or_else(|| self.material.medium())
+    }
 
     fn sample_position(&self) -> (Vector3D<F>, Vector3D<F>, F) {
         let _two = F::from(2u32).unwrap();
 
-        let normal = {
-            let theta = _two * F::PI() * F::sample_rand();
-            let phi = F::PI() * F::sample_rand();
+        let normal = { // Uniform over the area
+            let z = F::one() - _two * F::sample_rand();
+            let r = (F::one() - z * z).max(F::zero()).sqrt();
+            let phi = _two * F::PI() * F::sample_rand();
 
-            Vector3D::new(
-                phi.cos(),
-                phi.sin() * theta.cos(),
-                phi.sin() * theta.sin())
+            Vector3D::new(r * phi.cos(), r * phi.sin(), z)
         };
 
-        let coords = self.inner.center() + normal.clone() * self.inner.radius();
+        let coords = self.inner.center() + normal * self.inner.radius();
         let position_pdf = F::one() / self.area();
 
         (coords, normal, position_pdf)
     }
 
+    fn sample_position_from(&self, reference: Vector3D<F>) -> (Vector3D<F>, Vector3D<F>, F) {
+        let _two = F::from(2u32).unwrap();
+
+        let center = self.inner.center();
+        let radius = self.inner.radius();
+
+        let dc_2 = (center - reference).dot(center - reference);
+        if dc_2 <= radius * radius { // Inside, every point is visible
+            return self.sample_position();
+        }
+        let dc = dc_2.sqrt();
+
+        // Uniform over the cone subtended by the sphere
+        let sin_theta_max_2 = radius * radius / dc_2;
+        let cos_theta_max = (F::one() - sin_theta_max_2).max(F::zero()).sqrt();
+        let cos_theta = F::one() - F::sample_rand() * (F::one() - cos_theta_max);
+        let sin_theta_2 = (F::one() - cos_theta * cos_theta).max(F::zero());
+        let phi = _two * F::PI() * F::sample_rand();
+
+        // Project onto the sphere
+        let ds = dc * cos_theta - (radius * radius - dc_2 * sin_theta_2).max(F::zero()).sqrt();
+        let cos_alpha = ((dc_2 + radius * radius - ds * ds) / (_two * dc * radius)).min(F::one());
+        let sin_alpha = (F::one() - cos_alpha * cos_alpha).max(F::zero()).sqrt();
+
+        let w_c = (center - reference) / dc;
+        let normal = to_world(
+            Vector3D::new(sin_alpha * phi.cos(), sin_alpha * phi.sin(), cos_alpha),
+            -w_c,
+        );
+        let coords = center + normal * radius;
+        let position_pdf = self.pdf_position_from(reference, coords);
+
+        (coords, normal, position_pdf)
+    }
+
+    fn pdf_position_from(&self, reference: Vector3D<F>, coords: Vector3D<F>) -> F {
+        let _two = F::from(2u32).unwrap();
+
+        let center = self.inner.center();
+        let radius = self.inner.radius();
+
+        let dc_2 = (center - reference).dot(center - reference);
+        if dc_2 <= radius * radius {
+            return F::one() / self.area();
+        }
+
+        let sin_theta_max_2 = radius * radius / dc_2;
+        let cos_theta_max = (F::one() - sin_theta_max_2).max(F::zero()).sqrt();
+        let solid_angle_pdf = F::one() / (_two * F::PI() * (F::one() - cos_theta_max));
+
+        let normal = (coords - center).norm();
+        let x_diff = reference - coords;
+        let cos_light = normal.dot(x_diff.norm()).abs();
+
+        solid_angle_pdf * cos_light / x_diff.dot(x_diff)
+    }
+
     fn sample_direction(&self, coords: Vector3D<F>, normal: Vector3D<F>) -> (Vector3D<F>, F) {
         let local_direction = {
             let x_1 = F::sample_rand();
             let x_2 = F::sample_rand();
-            let z = F::one().abs_sub(x_1 * F::from(2u32).unwrap());
+            let z = x_1; // Uniform over the hemisphere
             let r = (F::one() - z * z).sqrt();
             let phi: F = F::from(2u32).unwrap() * F::PI() * x_2;
 
@@ -0,0 +1,180 @@
+use crate::raytrace::{Incident, ProcessedIncident, Ray, to_world};
+use crate::raytrace::lights::IesProfile;
+use crate::raytrace::objects::{Bounded, LightInteractable, LightSample, PartialBounded, RayTraceable};
+use crate::color::luminance;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::Arc;
+
+pub struct SpotLight<F: Float> {
+    position: Vector3D<F>,
+    direction: Vector3D<F>,
+
+    // Radiant intensity along the axis, per steradian
+    intensity: Vector3D<F>,
+
+    cos_total_width: F,
+    cos_falloff_start: F,
+
+    profile: Option<Arc<IesProfile<F>>>,
+}
+
+impl<F: Float> SpotLight<F> {
+    // Angles are half-angles from the axis, in degrees
+    pub fn new(
+        position: Vector3D<F>,
+        direction: Vector3D<F>,
+        intensity: Vector3D<F>,
+        total_width: F,
+        falloff_start: F,
+    ) -> Self {
+        Self {
+            position,
+            direction: direction.norm(),
+            intensity,
+            cos_total_width: total_width.to_radians().cos(),
+            cos_falloff_start: falloff_start.min(total_width).to_radians().cos(),
+            profile: None,
+        }
+    }
+
+    // Profile nadir points along the spot direction
+    pub fn with_profile(mut self, profile: Arc<IesProfile<F>>) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+}
+
+impl<F: Float> SpotLight<F> {
+    fn falloff(&self, direction: Vector3D<F>) -> F {
+        let cos_theta = direction.dot(self.direction);
+        if cos_theta < self.cos_total_width {
+            return F::zero();
+        }
+        if cos_theta >= self.cos_falloff_start {
+            return F::one();
+        }
+
+        // Smoothstep between the two cones
+        let t = (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        t * t * (F::from(3u32).unwrap() - F::from(2u32).unwrap() * t)
+    }
+
+    fn intensity_toward(&self, direction: Vector3D<F>) -> Vector3D<F> {
+        let profile = match &self.profile {
+            Some(profile) => profile.eval(direction, self.direction),
+            None => F::one(),
+        };
+
+        self.intensity * (self.falloff(direction) * profile)
+    }
+}
+
+impl<F: Float> Bounded<F> for SpotLight<F> {
+    fn hit(&self, _ray: &Ray<F>) -> Option<Incident<F>> {
+        None
+    }
+}
+
+impl<F: Float> PartialBounded<F> for SpotLight<F> {
+    fn partial_hit(&self, _ray: &Ray<F>) -> bool {
+        false
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        (self.position, self.position)
+    }
+}
+
+impl<F: Float> LightInteractable<F> for SpotLight<F> {
+    fn interact(&self, _incident: Incident<F>, _seed: F) -> ProcessedIncident<F> {
+        unreachable!("spot lights cannot be hit")
+    }
+
+    fn interact_predetermined(
+        &self,
+        _incident: Incident<F>,
+        _w_r: Vector3D<F>,
+        _pdf: F,
+        _seed: F) -> ProcessedIncident<F> {
+        unreachable!("spot lights cannot be hit")
+    }
+
+    fn pdf(&self, _incident: &Incident<F>, _w_r: Vector3D<F>) -> F {
+        F::zero()
+    }
+}
+
+impl<F: Float> RayTraceable<F> for SpotLight<F> {
+    fn name(&self) -> String {
+        "spot_light".to_string()
+    }
+
+    fn area(&self) -> F {
+        F::zero()
+    }
+    fn emit(&self) -> Option<Vector3D<F>> {
+        Some(self.intensity)
+    }
+    fn emit_at(&self, incident: &Incident<F>) -> Vector3D<F> {
+        self.intensity_toward(incident.w_i())
+    }
+    fn power(&self) -> F {
+        let _half = F::from(0.5).unwrap();
+        let solid_angle = F::from(2u32).unwrap() * F::PI()
+            * (F::one() - _half * (self.cos_falloff_start + self.cos_total_width));
+
+        luminance(self.intensity).max(F::zero()) * solid_angle
+    }
+    fn delta(&self) -> bool {
+        true
+    }
+
+    fn focus(&self) -> bool {
+        false
+    }
+
+    fn normal_cone(&self) -> (Vector3D<F>, F) {
+        (self.direction, self.cos_total_width)
+    }
+
+    fn sample_position(&self) -> (Vector3D<F>, Vector3D<F>, F) {
+        (self.position, self.direction, F::one())
+    }
+
+    fn sample_direction(&self, _coords: Vector3D<F>, _normal: Vector3D<F>) -> (Vector3D<F>, F) {
+        let _two = F::from(2u32).unwrap();
+
+        // Uniform over the outer cone
+        let z = F::one() - F::sample_rand() * (F::one() - self.cos_total_width);
+        let r = (F::one() - z * z).max(F::zero()).sqrt();
+        let phi = _two * F::PI() * F::sample_rand();
+
+        let direction = to_world(Vector3D::new(r * phi.cos(), r * phi.sin(), z), self.direction);
+        let direction_pdf = F::one() / (_two * F::PI() * (F::one() - self.cos_total_width));
+
+        (direction, direction_pdf)
+    }
+
+    fn pdf_direction(&self, _coords: Vector3D<F>, _normal: Vector3D<F>, direction: Vector3D<F>) -> F {
+        if direction.dot(self.direction) < self.cos_total_width {
+            return F::zero();
+        }
+
+        F::one() / (F::from(2u32).unwrap() * F::PI() * (F::one() - self.cos_total_width))
+    }
+
+    fn sample_light(&self) -> LightSample<F> {
+        let (coords, normal, position_pdf) = self.sample_position();
+        let (direction, direction_pdf) = self.sample_direction(coords, normal);
+
+        LightSample {
+            ray: Ray::new(coords, direction),
+            normal: direction, // No surface to be foreshortened
+            emit: self.intensity_toward(direction),
+            position_pdf,
+            direction_pdf,
+        }
+    }
+}
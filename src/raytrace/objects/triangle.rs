@@ -0,0 +1,167 @@
+use crate::raytrace::{Incident, ProcessedIncident, Ray};
+use crate::raytrace::materials::Material;
+use crate::raytrace::objects::{Bounded, LightInteractable, LightSample, PartialBounded, RayTraceable};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::Arc;
+
+use super::base;
+
+// Single face of a mesh, so emissive faces can be sampled as lights
+pub struct Triangle<F: Float> {
+    name: String,
+
+    inner: base::Triangle<F>,
+    primitive: usize,
+
+    material: Arc<dyn Material<F>>,
+}
+
+impl<F: Float> Triangle<F> {
+    pub fn new(
+        name: String,
+        inner: base::Triangle<F>,
+        primitive: usize,
+        material: Arc<dyn Material<F>>,
+    ) -> Self {
+        Self {
+            name,
+            inner,
+            primitive,
+            material,
+        }
+    }
+}
+
+impl<F: Float> Bounded<F> for Triangle<F> {
+    fn hit(&self, ray: &Ray<F>) -> Option<Incident<F>> {
+        self.inner.hit(ray)
+            .map(|incident| incident.with_primitive(self.primitive))
+    }
+}
+
+impl<F: Float> PartialBounded<F> for Triangle<F> {
+    fn partial_hit(&self, ray: &Ray<F>) -> bool {
+        self.hit(ray).is_some()
+    }
+
+    fn bounds(&self) -> (Vector3D<F>, Vector3D<F>) {
+        let (v0, v1, v2) = self.inner.vertices();
+
+        (v0.min(v1.min(v2)), v0.max(v1.max(v2)))
+    }
+}
+
+impl<F: Float> LightInteractable<F> for Triangle<F> {
+    fn interact(
+        &self,
+        incident: Incident<F>,
+        seed: F,
+    ) -> ProcessedIncident<F> {
+        self.material.interact(incident, seed)
+    }
+
+    fn interact_predetermined(
+        &self,
+        incident: Incident<F>,
+        w_r: Vector3D<F>,
+        pdf: F,
+        seed: F) -> ProcessedIncident<F> {
+        self.material.interact_predetermined(
+            incident,
+            w_r,
+            pdf,
+            seed,
+        )
+    }
+
+    fn pdf(&self, incident: &Incident<F>, w_r: Vector3D<F>) -> F {
+        self.material.pdf(incident, w_r)
+    }
+}
+
+impl<F: Float> RayTraceable<F> for Triangle<F> {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn area(&self) -> F {
+        self.inner.area()
+    }
+    fn emit(&self) -> Option<Vector3D<F>> {
+        self.material.emission()
+    }
+    fn emit_at(&self, incident: &Incident<F>) -> Vector3D<F> {
+        self.material.emit(incident)
+    }
+
+    fn focus(&self) -> bool {
+        self.material.focus()
+    }
+
+    fn normal_cone(&self) -> (Vector3D<F>, F) {
+        if self.material.two_sided() {
+            return (self.inner.normal(), -F::one());
+        }
+
+        (self.inner.normal(), F::one())
+    }
+
+    fn sample_position(&self) -> (Vector3D<F>, Vector3D<F>, F) {
+        let (coords, position_pdf) = self.inner.sample_location();
+
+        (coords, self.inner.normal(), position_pdf)
+    }
+
+    fn sample_direction(&self, _coords: Vector3D<F>, _normal: Vector3D<F>) -> (Vector3D<F>, F) {
+        let (direction, direction_pdf) = self.inner.sample_direction();
+        if !self.material.two_sided() {
+            return (direction, direction_pdf);
+        }
+
+        let _half = F::from(0.5).unwrap();
+        if F::sample_rand() < _half { // Leave through the back face
+            return (-direction, direction_pdf * _half);
+        }
+
+        (direction, direction_pdf * _half)
+    }
+
+    fn pdf_direction(&self, _coords: Vector3D<F>, _normal: Vector3D<F>, direction: Vector3D<F>) -> F {
+        let _half = F::from(0.5).unwrap();
+        let cos_theta = direction.dot(self.inner.normal());
+        if self.material.two_sided() {
+            return _half * _half * F::FRAC_1_PI();
+        }
+        if cos_theta > F::zero() {
+            return _half * F::FRAC_1_PI();
+        }
+
+        F::zero()
+    }
+
+    fn sample_light(&self) -> LightSample<F> {
+        let (coords, uv, position_pdf) = self.inner.sample_location_uv();
+        let (direction, direction_pdf) = self.sample_direction(coords, self.inner.normal());
+
+        let back = direction.dot(self.inner.normal()) < F::zero();
+        let normal = if back { -self.inner.normal() } else { self.inner.normal() };
+
+        let incident = Incident::new(
+            coords,
+            normal,
+            F::zero(),
+            direction,
+            back,
+        ).with_uv(uv).with_primitive(self.primitive);
+
+        LightSample {
+            ray: Ray::new(coords, direction),
+            normal,
+            emit: self.material.emit(&incident),
+            position_pdf,
+            direction_pdf,
+        }
+    }
+}
@@ -1,12 +1,90 @@
 use crate::types::Float;
 use crate::vector::Vector3D;
 
+// Deeper nesting forgets the outermost dielectrics
+const NESTING_DEPTH: usize = 4;
+
+// Dielectric a path is inside
+#[derive(Debug, Clone, Copy)]
+pub struct Dielectric<F: Float> {
+    // Address of the material, only ever compared
+    pub id: usize,
+    // Overlapping dielectrics defer to the highest
+    pub priority: u32,
+    pub index_of_coin: F,
+    pub absorption: Vector3D<F>,
+}
+
+// Every dielectric a path is inside, oldest first
+#[derive(Debug, Clone, Copy)]
+pub struct DielectricStack<F: Float> {
+    entries: [Option<Dielectric<F>>; NESTING_DEPTH],
+}
+
+impl<F: Float> Default for DielectricStack<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Float> DielectricStack<F> {
+    pub fn new() -> Self {
+        Self {
+            entries: [None; NESTING_DEPTH],
+        }
+    }
+
+    // The one light is actually travelling through, latest wins ties
+    pub fn top(&self) -> Option<Dielectric<F>> {
+        let mut top: Option<Dielectric<F>> = None;
+        for dielectric in self.entries.iter().flatten() {
+            if top.is_none_or(|top| dielectric.priority >= top.priority) {
+                top = Some(*dielectric);
+            }
+        }
+
+        top
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.entries.iter().flatten().any(|dielectric| dielectric.id == id)
+    }
+
+    pub fn push(mut self, dielectric: Dielectric<F>) -> Self {
+        if self.contains(dielectric.id) {
+            return self;
+        }
+
+        match self.entries.iter().position(|entry| entry.is_none()) {
+            Some(free) => self.entries[free] = Some(dielectric),
+            None => { // Full, drop the oldest
+                self.entries.rotate_left(1);
+                self.entries[NESTING_DEPTH - 1] = Some(dielectric);
+            }
+        }
+
+        self
+    }
+
+    pub fn remove(mut self, id: usize) -> Self {
+        if let Some(found) = self.entries.iter().position(|entry| entry.is_some_and(|d| d.id == id)) {
+            self.entries[found] = None;
+            self.entries[found..].rotate_left(1);
+        }
+
+        self
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Ray<F: Float> {
     origin: Vector3D<F>,
     direction: Vector3D<F>,
 
     inside: bool,
+    stack: DielectricStack<F>,
+    // In nm on spectral paths, none when rendering RGB
+    wavelength: Option<F>,
 }
 
 impl<F: Float> Ray<F> {
@@ -19,6 +97,8 @@ impl<F: Float> Ray<F> {
             origin,
             direction,
             inside: true,
+            stack: DielectricStack::new(),
+            wavelength: None,
         }
     }
 
@@ -31,8 +111,20 @@ impl<F: Float> Ray<F> {
             origin,
             direction,
             inside: false,
+            stack: DielectricStack::new(),
+            wavelength: None,
         }
     }
+
+    pub fn with_stack(mut self, stack: DielectricStack<F>) -> Self {
+        self.stack = stack;
+        self
+    }
+
+    pub fn with_wavelength(mut self, wavelength: Option<F>) -> Self {
+        self.wavelength = wavelength;
+        self
+    }
 }
 
 impl<F: Float> Ray<F> {
@@ -47,4 +139,12 @@ impl<F: Float> Ray<F> {
     pub fn inside(&self) -> bool {
         self.inside
     }
+
+    pub fn stack(&self) -> DielectricStack<F> {
+        self.stack
+    }
+
+    pub fn wavelength(&self) -> Option<F> {
+        self.wavelength
+    }
 }
@@ -1,7 +1,7 @@
 mod simple;
-mod cast;
 
 use crate::raytrace::SceneGenerator;
+use crate::raytrace::integrators::{Integrator, MisPathTracer};
 use crate::types::Float;
 use crate::vector::Vector3D;
 
@@ -23,7 +23,7 @@ pub struct Renderer<F: Float> {
     fov: u32,
     spp: u32,
 
-    rr: F,
+    integrator: Box<dyn Integrator<F>>,
 
     scene_gen: Arc<dyn SceneGenerator<F>>,
 
@@ -44,25 +44,26 @@ impl<F: Float> Renderer<F> {
             },
             fov,
             spp: 64,
-            rr: F::from(0.8 as f64).unwrap(),
+            integrator: Box::new(MisPathTracer::new(F::from(0.8 as f64).unwrap())),
             scene_gen,
             thread_count,
             progress_bar: ProgressBar::new((width * height) as u64),
         }
     }
+
+    pub fn with_integrator(mut self, integrator: Box<dyn Integrator<F>>) -> Self {
+        self.integrator = integrator;
+        self
+    }
 }
 
 impl<F: Float> Renderer<F> {
-    pub fn render(&self, eye_pos: Vector3D<F>) -> image::DynamicImage {
-        let start = std::time::Instant::now();
-        let the_tree = cast::gen_photon_map(
-            self.rr,
-            10000000, // 10m photon, a portion wasted
+    pub fn render(&mut self, eye_pos: Vector3D<F>) -> image::DynamicImage {
+        println!("Rendering with the {} integrator", self.integrator.name());
+        self.integrator.preprocess(
             self.scene_gen.clone(),
             self.thread_count,
         );
-        let duration = start.elapsed();
-        println!("Time elapsed in gen_photon_map() is: {:?}", duration);
 
         let mut im = image::DynamicImage::new_rgb8(self.dims.width, self.dims.height);
 
@@ -71,16 +72,12 @@ impl<F: Float> Renderer<F> {
             self.dims,
             self.fov,
             self.spp,
-            self.rr,
             self.scene_gen.clone(),
             self.thread_count,
-            the_tree,
-            8,
-            F::from(10u32).unwrap(),
             self.progress_bar.clone(),
         );
 
-        let res_vec = simple_renderer.render(eye_pos);
+        let res_vec = simple_renderer.render(self.integrator.as_ref(), eye_pos);
 
         for w in 0..self.dims.width {
             for h in 0..self.dims.height {
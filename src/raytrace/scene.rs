@@ -1,10 +1,17 @@
+use crate::raytrace::environment::Environment;
+use crate::raytrace::media::Medium;
 use crate::raytrace::objects::RayTraceable;
 use crate::types::Float;
 
 use std::sync::Arc;
 
 pub struct Scene<F: Float> {
-    pub objects: Vec<Arc<dyn RayTraceable<F>>>
+    pub objects: Vec<Arc<dyn RayTraceable<F>>>,
+    pub environment: Option<Arc<dyn Environment<F>>>,
+    // Fills the space outside every closed object
+    pub medium: Option<Arc<dyn Medium<F>>>,
+    // Trace every path at a sampled wavelength, needed for dispersion
+    pub spectral: bool,
 }
 
 pub trait SceneGenerator<F: Float>: Send + Sync {
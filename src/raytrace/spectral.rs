@@ -0,0 +1,86 @@
+use crate::color::{cie_xyz, xyz_to_rgb};
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+use std::sync::OnceLock;
+
+// Visible range wavelengths are sampled from, in nm
+pub const MIN_WAVELENGTH: f64 = 380.0;
+pub const MAX_WAVELENGTH: f64 = 780.0;
+
+// Uniform over the visible range
+pub fn sample_wavelength<F: Float>() -> F {
+    let t = F::sample_rand().to_f64().unwrap();
+
+    F::from(MIN_WAVELENGTH + (MAX_WAVELENGTH - MIN_WAVELENGTH) * t).unwrap()
+}
+
+// Smooth red, green and blue spectra RGB is upsampled with
+fn basis(wavelength: f64) -> [f64; 3] {
+    let g = |mu: f64, sigma: f64| {
+        let t = (wavelength - mu) / sigma;
+        (-0.5 * t * t).exp()
+    };
+
+    [g(610.0, 40.0), g(540.0, 35.0), g(455.0, 30.0)]
+}
+
+// Inverse of the linear sRGB each basis spectrum integrates to, so upsampled
+// spectra integrate back to the RGB they came from
+fn inverse_response() -> &'static [[f64; 3]; 3] {
+    static INVERSE: OnceLock<[[f64; 3]; 3]> = OnceLock::new();
+
+    INVERSE.get_or_init(|| {
+        let mut response = [[0.0; 3]; 3];
+        let mut wavelength = MIN_WAVELENGTH;
+        while wavelength < MAX_WAVELENGTH {
+            let rgb = xyz_to_rgb(cie_xyz(wavelength));
+            let rgb = [rgb.x, rgb.y, rgb.z];
+            for (i, b) in basis(wavelength).into_iter().enumerate() {
+                for j in 0..3 {
+                    response[j][i] += rgb[j] * b;
+                }
+            }
+            wavelength += 1.0;
+        }
+
+        invert(response)
+    })
+}
+
+fn invert(m: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let adjugate = [
+        [cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+        [-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+        [cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)],
+    ];
+    let determinant = m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+
+    adjugate.map(|row| row.map(|v| v / determinant))
+}
+
+// Value at wavelength of the spectrum upsampled from linear sRGB
+pub fn upsample<F: Float>(rgb: Vector3D<F>, wavelength: F) -> F {
+    let inverse = inverse_response();
+    let rgb = [rgb.x, rgb.y, rgb.z].map(|v| v.to_f64().unwrap());
+
+    let mut value = 0.0;
+    for (i, b) in basis(wavelength.to_f64().unwrap()).into_iter().enumerate() {
+        let weight = inverse[i][0] * rgb[0] + inverse[i][1] * rgb[1] + inverse[i][2] * rgb[2];
+        value += weight * b;
+    }
+
+    F::from(value).unwrap()
+}
+
+// Linear sRGB estimate from rgb seen only at a sampled wavelength, averages
+// back to rgb over many wavelengths but splits into a rainbow where paths
+// depended on the wavelength
+pub fn project<F: Float>(rgb: Vector3D<F>, wavelength: F) -> Vector3D<F> {
+    let range = F::from(MAX_WAVELENGTH - MIN_WAVELENGTH).unwrap();
+
+    xyz_to_rgb(cie_xyz(wavelength)) * upsample(rgb, wavelength) * range
+}
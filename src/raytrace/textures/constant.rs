@@ -0,0 +1,26 @@
+use crate::raytrace::textures::Texture;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Constant<F: Float> {
+    value: Vector3D<F>,
+}
+
+impl<F: Float> Constant<F> {
+    pub fn new(value: Vector3D<F>) -> Self {
+        Self {
+            value,
+        }
+    }
+}
+
+impl<F: Float> Texture<F> for Constant<F> {
+    fn value(&self, _uv: (F, F), _coords: Vector3D<F>) -> Vector3D<F> {
+        self.value
+    }
+
+    fn average(&self) -> Vector3D<F> {
+        self.value
+    }
+}
@@ -0,0 +1,82 @@
+use crate::raytrace::textures::Texture;
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+// Bilinearly filtered, repeating image texture in linear RGB
+#[derive(Debug, Clone)]
+pub struct Image<F: Float> {
+    width: usize,
+    height: usize,
+
+    texels: Vec<Vector3D<F>>,
+
+    average: Vector3D<F>,
+}
+
+impl<F: Float> Image<F> {
+    pub fn new(source: String) -> Self {
+        let im = image::open(&source)
+            .expect("Something went wrong reading the image")
+            .into_rgb8();
+
+        let width = im.width() as usize;
+        let height = im.height() as usize;
+
+        let decode = |c: u8| F::from((c as f64 / 255.0).powf(2.2)).unwrap();
+
+        let mut texels = Vec::with_capacity(width * height);
+        let mut average = Vector3D::zero();
+        for pixel in im.pixels() {
+            let texel = Vector3D::new(decode(pixel[0]), decode(pixel[1]), decode(pixel[2]));
+            average += texel;
+            texels.push(texel);
+        }
+        let average = average / F::from(texels.len().max(1)).unwrap();
+
+        Self {
+            width,
+            height,
+            texels,
+            average,
+        }
+    }
+}
+
+impl<F: Float> Image<F> {
+    fn texel(&self, x: i64, y: i64) -> Vector3D<F> {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+
+        self.texels[y * self.width + x]
+    }
+}
+
+impl<F: Float> Texture<F> for Image<F> {
+    fn value(&self, uv: (F, F), _coords: Vector3D<F>) -> Vector3D<F> {
+        if self.texels.is_empty() {
+            return Vector3D::zero();
+        }
+
+        let _half = F::from(0.5).unwrap();
+
+        // v points up, rows go down
+        let x = uv.0 * F::from(self.width).unwrap() - _half;
+        let y = (F::one() - uv.1) * F::from(self.height).unwrap() - _half;
+
+        let x_0 = x.floor();
+        let y_0 = y.floor();
+        let dx = x - x_0;
+        let dy = y - y_0;
+        let x_0 = x_0.to_i64().unwrap_or(0);
+        let y_0 = y_0.to_i64().unwrap_or(0);
+
+        self.texel(x_0, y_0) * ((F::one() - dx) * (F::one() - dy))
+            + self.texel(x_0 + 1, y_0) * (dx * (F::one() - dy))
+            + self.texel(x_0, y_0 + 1) * ((F::one() - dx) * dy)
+            + self.texel(x_0 + 1, y_0 + 1) * (dx * dy)
+    }
+
+    fn average(&self) -> Vector3D<F> {
+        self.average
+    }
+}
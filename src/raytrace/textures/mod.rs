@@ -0,0 +1,14 @@
+mod constant;
+mod image;
+
+pub use self::constant::Constant;
+pub use self::image::Image;
+
+use crate::types::Float;
+use crate::vector::Vector3D;
+
+pub trait Texture<F: Float> {
+    fn value(&self, uv: (F, F), coords: Vector3D<F>) -> Vector3D<F>;
+
+    fn average(&self) -> Vector3D<F>;
+}
@@ -68,6 +68,10 @@ impl<F: Float> TheTree<F> {
     }
 
     pub fn within_radius(&self, coords: Vector3D<F>, radius: F) -> bool {
+        if self.inner.is_empty() { // No photon was cast
+            return false;
+        }
+
         let found = self.inner.within_radius(
             &[
                 coords.x.to_f64().unwrap(),
@@ -80,7 +84,30 @@ impl<F: Float> TheTree<F> {
         !found.is_empty()
     }
 
+    pub fn within(&self, coords: Vector3D<F>, radius: F) -> Vec<Photon<F>> {
+        if self.inner.is_empty() { // No photon was cast
+            return Vec::new();
+        }
+
+        let found = self.inner.within_radius(
+            &[
+                coords.x.to_f64().unwrap(),
+                coords.y.to_f64().unwrap(),
+                coords.z.to_f64().unwrap(),
+            ],
+            radius.to_f64().unwrap(),
+        );
+
+        found.into_iter()
+            .cloned()
+            .collect()
+    }
+
     pub fn knn(&self, coords: Vector3D<F>, k: u32) -> (Vec<Photon<F>>, F) {
+        if self.inner.is_empty() { // No photon was cast
+            return (Vec::new(), F::zero());
+        }
+
         let found = self.inner.nearests(
             &[
                 coords.x.to_f64().unwrap(),
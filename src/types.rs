@@ -1,4 +1,4 @@
-pub trait Float: std::fmt::Debug + num::Float + Send + 'static + num::traits::FloatConst + CanRNG {}
+pub trait Float: std::fmt::Debug + num::Float + Send + Sync + 'static + num::traits::FloatConst + CanRNG {}
 
 pub trait CanRNG {
     fn sample_rand() -> Self;
@@ -89,6 +89,10 @@ impl<F: Float> Vector3D<F> {
             z: self.z.max(op.z),
         }
     }
+
+    pub fn abs(self) -> Self {
+        Self::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
 }
 
 impl<F: Float> std::ops::Neg for Vector3D<F> {